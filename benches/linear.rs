@@ -0,0 +1,70 @@
+//! Times `remove --auto` on generated files of doubling size and fails if the time per byte
+//! grows with the input, which would mean the engine is no longer linear.
+//!
+//! Run with `cargo bench`.
+
+use std::{
+    env, fs,
+    path::Path,
+    process::Command,
+    time::{Duration, Instant},
+};
+
+const BASE_LINES: usize = 20_000;
+const STEPS: u32 = 5;
+/// Allowed growth of the time per byte between the smallest and largest input
+const MAX_SLOWDOWN: f64 = 3.0;
+
+fn main() {
+    let dir = env::temp_dir().join(format!("comment-remover-bench-{}", std::process::id()));
+    fs::create_dir_all(&dir).expect("Failed to create benchmark directory");
+
+    println!("{:>10} {:>12} {:>12} {:>14}", "Lines", "Bytes", "Time", "ns/byte");
+    let mut per_byte = Vec::new();
+    for step in 0..STEPS {
+        let lines = BASE_LINES << step;
+        let file = dir.join("input.rs");
+        let content = generate(lines);
+        fs::write(&file, &content).expect("Failed to write benchmark input");
+
+        let elapsed = run(&file);
+        let ns_per_byte = elapsed.as_nanos() as f64 / content.len() as f64;
+        println!("{:>10} {:>12} {:>12.2?} {:>14.2}", lines, content.len(), elapsed, ns_per_byte);
+        per_byte.push(ns_per_byte);
+    }
+    let _ = fs::remove_dir_all(&dir);
+
+    let slowdown = per_byte[per_byte.len() - 1] / per_byte[0];
+    println!("Time per byte grew by {:.2}x over a {}x larger input", slowdown, 1 << (STEPS - 1));
+    if slowdown > MAX_SLOWDOWN {
+        eprintln!("Comment removal is not linear in the input size");
+        std::process::exit(1);
+    }
+}
+
+/// Rust source mixing code, line comments, block comments and strings holding comment markers.
+fn generate(lines: usize) -> String {
+    let mut content = String::new();
+    for i in 0..lines {
+        match i % 5 {
+            0 => content.push_str(&format!("// comment number {}\n", i)),
+            1 => content.push_str(&format!("fn function_{}() -> u32 {{ {} }}\n", i, i)),
+            2 => content.push_str("/* a block\n   comment */\n"),
+            3 => content.push_str("let url = \"http://example.com/*not a comment*/\";\n"),
+            _ => content.push_str("    let value = compute(1, 2) + other;\n"),
+        }
+    }
+    content
+}
+
+fn run(file: &Path) -> Duration {
+    let start = Instant::now();
+    let status = Command::new(env!("CARGO_BIN_EXE_rust_remove_comments_cli"))
+        .args(["--quiet", "remove", "--auto", "--force", "--max-size", "0"])
+        .arg(file)
+        .status()
+        .expect("Failed to run comment_remover");
+    let elapsed = start.elapsed();
+    assert!(status.success(), "comment_remover failed on {}", file.display());
+    elapsed
+}
@@ -0,0 +1,28 @@
+//! A comment remover that never removes comments naming a Jira ticket, e.g. `// PROJ-123`.
+//!
+//! Run with `cargo run --example keep_tickets -- remove --auto src`.
+
+use regex::Regex;
+use rust_remove_comments_cli::{
+    plugin::{self, Action, CommentHandler, FileContext},
+    result::CommentSpan,
+};
+
+struct KeepTickets {
+    ticket: Regex,
+}
+
+impl CommentHandler for KeepTickets {
+    fn name(&self) -> &str {
+        "keep-tickets"
+    }
+
+    fn classify(&self, comment: &CommentSpan, _context: &FileContext) -> Action {
+        if self.ticket.is_match(&comment.text) { Action::Keep } else { Action::Default }
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    plugin::register(KeepTickets { ticket: Regex::new(r"\b[A-Z][A-Z0-9]+-[0-9]+\b")? });
+    rust_remove_comments_cli::run()
+}
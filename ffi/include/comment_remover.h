@@ -0,0 +1,54 @@
+#ifndef COMMENT_REMOVER_H
+#define COMMENT_REMOVER_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The comments were removed and *out holds the result */
+#define CR_OK 0
+/* A string argument is NULL or not UTF-8 */
+#define CR_INVALID_ARGUMENT 1
+/* Removal failed and *out holds the error message */
+#define CR_ERROR 2
+
+/* A set of options with the rules compiled for it; not to be used on two threads at once */
+typedef struct CrStripper CrStripper;
+
+/*
+ * Parses `options`, `remove` flags separated by whitespace such as "--strip-docstrings
+ * --preserve-lines", or NULL, into a stripper for cr_stripper_strip, to be freed with
+ * cr_stripper_free. On CR_OK, *out is set to the stripper; on CR_ERROR, *error is set to the
+ * error message, to be freed with cr_free_string.
+ */
+int cr_stripper_new(const char *options, CrStripper **out, char **error);
+
+/*
+ * Removes comments from `input` in `lang` (a language key or name such as "rust" or "C++")
+ * with the options of `stripper`. On CR_OK and CR_ERROR, *out is set to the result or the
+ * error message, to be freed with cr_free_string.
+ */
+int cr_stripper_strip(const CrStripper *stripper, const char *lang, const char *input, char **out);
+
+/* Frees a stripper from cr_stripper_new; NULL is ignored. */
+void cr_stripper_free(CrStripper *stripper);
+
+/*
+ * Removes comments from `input` in `lang` (a language key or name such as "rust" or "C++").
+ * `options` holds `remove` flags separated by whitespace, e.g. "--strip-docstrings
+ * --preserve-lines", or is NULL. On CR_OK and CR_ERROR, *out is set to the result or the
+ * error message, to be freed with cr_free_string. The options are parsed on every call;
+ * cr_stripper_new parses them once for many inputs.
+ *
+ * The syntax rules built into the library are used; nothing is read from disk.
+ */
+int cr_remove_comments(const char *lang, const char *input, const char *options, char **out);
+
+/* Frees a string returned by any of the functions above; NULL is ignored. */
+void cr_free_string(char *text);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
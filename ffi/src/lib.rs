@@ -0,0 +1,233 @@
+//! C API for embedding the comment remover, declared in `include/comment_remover.h`.
+//!
+//! Build with `cargo build -p comment_remover_ffi --release`.
+
+use std::{
+    ffi::{c_char, c_int, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+    sync::OnceLock,
+};
+
+use rust_remove_comments_cli::{Rules, Stripper};
+
+/// Returned when the comments were removed and `*out` holds the result
+pub const CR_OK: c_int = 0;
+/// Returned when a string argument is NULL or not UTF-8
+pub const CR_INVALID_ARGUMENT: c_int = 1;
+/// Returned when removal failed and `*out` holds the error message
+pub const CR_ERROR: c_int = 2;
+
+/// A set of options with the rules compiled for it, from `cr_stripper_new`
+pub struct CrStripper(Stripper<'static>);
+
+/// The bundled syntax rules, parsed on first use and shared by every stripper
+fn rules() -> &'static Rules {
+    static RULES: OnceLock<Rules> = OnceLock::new();
+    RULES.get_or_init(Rules::bundled)
+}
+
+/// Parses `options`, `remove` flags separated by whitespace such as `--strip-docstrings
+/// --preserve-lines`, or NULL, into a stripper to pass to `cr_stripper_strip` any number of
+/// times and free with `cr_stripper_free`. On `CR_OK`, `*out` is set to the stripper; on
+/// `CR_ERROR`, `*error` is set to the error message, which the caller frees with
+/// `cr_free_string`.
+///
+/// # Safety
+///
+/// A non-NULL `options` must be a NUL-terminated string, and `out` and `error` must be valid
+/// for writing a pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cr_stripper_new(
+    options: *const c_char,
+    out: *mut *mut CrStripper,
+    error: *mut *mut c_char,
+) -> c_int {
+    if out.is_null() || error.is_null() {
+        return CR_INVALID_ARGUMENT;
+    }
+    // SAFETY: the caller guarantees `out` and `error` are writable
+    unsafe {
+        *out = ptr::null_mut();
+        *error = ptr::null_mut();
+    }
+    // SAFETY: the caller guarantees a NULL or NUL-terminated string
+    let Some(options) = (unsafe { options_str(options) }) else {
+        return CR_INVALID_ARGUMENT;
+    };
+    let result = panic::catch_unwind(|| {
+        let options: Vec<&str> = options.split_whitespace().collect();
+        Stripper::new(rules(), &options).map_err(|e| format!("{:#}", e))
+    })
+    .unwrap_or_else(|_| Err("The comment remover panicked".to_string()));
+    match result {
+        Ok(stripper) => {
+            // SAFETY: checked for NULL above
+            unsafe { *out = Box::into_raw(Box::new(CrStripper(stripper))) };
+            CR_OK
+        }
+        // SAFETY: checked for NULL above
+        Err(message) => unsafe { set_text(error, CR_ERROR, message) },
+    }
+}
+
+/// Removes comments from `input` in `lang` (a language key or name such as `rust` or `C++`)
+/// with the options of `stripper`. On `CR_OK` and `CR_ERROR`, `*out` is set to the result or
+/// the error message, which the caller frees with `cr_free_string`.
+///
+/// # Safety
+///
+/// `stripper` must come from `cr_stripper_new`, not have been freed and not be in use on
+/// another thread; `lang` and `input` must be NUL-terminated strings, and `out` must be valid
+/// for writing a pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cr_stripper_strip(
+    stripper: *const CrStripper,
+    lang: *const c_char,
+    input: *const c_char,
+    out: *mut *mut c_char,
+) -> c_int {
+    if out.is_null() {
+        return CR_INVALID_ARGUMENT;
+    }
+    // SAFETY: the caller guarantees `out` is writable
+    unsafe { *out = ptr::null_mut() };
+    // SAFETY: the caller guarantees these are NUL-terminated strings
+    let (Some(lang), Some(input)) = (unsafe { to_str(lang) }, unsafe { to_str(input) }) else {
+        return CR_INVALID_ARGUMENT;
+    };
+    // SAFETY: the caller guarantees a live stripper not used elsewhere
+    let Some(stripper) = (unsafe { stripper.as_ref() }) else {
+        return CR_INVALID_ARGUMENT;
+    };
+    // SAFETY: checked for NULL above
+    unsafe { strip(&stripper.0, lang, input, out) }
+}
+
+/// Frees a stripper from `cr_stripper_new`; NULL is ignored.
+///
+/// # Safety
+///
+/// `stripper` must come from `cr_stripper_new` and not have been freed already.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cr_stripper_free(stripper: *mut CrStripper) {
+    if !stripper.is_null() {
+        // SAFETY: the caller guarantees it was created by `Box::into_raw` above
+        drop(unsafe { Box::from_raw(stripper) });
+    }
+}
+
+/// Removes comments from `input` in `lang` (a language key or name such as `rust` or `C++`).
+/// `options` holds `remove` flags separated by whitespace, e.g. `--strip-docstrings
+/// --preserve-lines`, or is NULL. On `CR_OK` and `CR_ERROR`, `*out` is set to the result or
+/// the error message, which the caller frees with `cr_free_string`. The options are parsed
+/// again on every call; `cr_stripper_new` parses them once for many inputs.
+///
+/// # Safety
+///
+/// `lang`, `input` and a non-NULL `options` must be NUL-terminated strings, and `out` must be
+/// valid for writing a pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cr_remove_comments(
+    lang: *const c_char,
+    input: *const c_char,
+    options: *const c_char,
+    out: *mut *mut c_char,
+) -> c_int {
+    if out.is_null() {
+        return CR_INVALID_ARGUMENT;
+    }
+    // SAFETY: the caller guarantees `out` is writable
+    unsafe { *out = ptr::null_mut() };
+    // SAFETY: the caller guarantees these are NUL-terminated strings
+    let (Some(lang), Some(input)) = (unsafe { to_str(lang) }, unsafe { to_str(input) }) else {
+        return CR_INVALID_ARGUMENT;
+    };
+    // SAFETY: as above
+    let Some(options) = (unsafe { options_str(options) }) else {
+        return CR_INVALID_ARGUMENT;
+    };
+
+    let options: Vec<&str> = options.split_whitespace().collect();
+    let stripper = match panic::catch_unwind(|| Stripper::new(rules(), &options)) {
+        Ok(Ok(stripper)) => stripper,
+        // SAFETY: checked for NULL above
+        Ok(Err(e)) => return unsafe { set_text(out, CR_ERROR, format!("{:#}", e)) },
+        // SAFETY: checked for NULL above
+        Err(_) => return unsafe { set_text(out, CR_ERROR, "The comment remover panicked".to_string()) },
+    };
+    // SAFETY: checked for NULL above
+    unsafe { strip(&stripper, lang, input, out) }
+}
+
+/// Strips `input` and hands the result or the error message to C through `out`.
+///
+/// # Safety
+///
+/// `out` must be valid for writing a pointer.
+unsafe fn strip(stripper: &Stripper, lang: &str, input: &str, out: *mut *mut c_char) -> c_int {
+    // Unwinding into C is undefined behaviour, so a panic becomes an error
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        stripper.strip(input, lang).map(|result| result.content).map_err(|e| format!("{:#}", e))
+    }))
+    .unwrap_or_else(|_| Err("The comment remover panicked".to_string()));
+    // SAFETY: the caller guarantees `out` is writable
+    match result {
+        Ok(text) => unsafe { set_text(out, CR_OK, text) },
+        Err(message) => unsafe { set_text(out, CR_ERROR, message) },
+    }
+}
+
+/// Hands `text` to C through `out` and returns `status`, or `CR_ERROR` if it cannot be a C
+/// string.
+///
+/// # Safety
+///
+/// `out` must be valid for writing a pointer.
+unsafe fn set_text(out: *mut *mut c_char, status: c_int, text: String) -> c_int {
+    // Comments can hold NUL bytes that C strings cannot
+    let Ok(text) = CString::new(text.replace('\0', "")) else {
+        return CR_ERROR;
+    };
+    // SAFETY: the caller guarantees `out` is writable
+    unsafe { *out = text.into_raw() };
+    status
+}
+
+/// Frees a string returned by `cr_remove_comments`, `cr_stripper_new` or `cr_stripper_strip`;
+/// NULL is ignored.
+///
+/// # Safety
+///
+/// `text` must come from one of those functions and not have been freed already.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cr_free_string(text: *mut c_char) {
+    if !text.is_null() {
+        // SAFETY: the caller guarantees it was created by `CString::into_raw` above
+        drop(unsafe { CString::from_raw(text) });
+    }
+}
+
+/// # Safety
+///
+/// `text` must be NULL or a NUL-terminated string.
+unsafe fn to_str<'a>(text: *const c_char) -> Option<&'a str> {
+    if text.is_null() {
+        return None;
+    }
+    // SAFETY: the caller guarantees a NUL-terminated string
+    unsafe { CStr::from_ptr(text) }.to_str().ok()
+}
+
+/// `options` as a string, with NULL meaning no options.
+///
+/// # Safety
+///
+/// `options` must be NULL or a NUL-terminated string.
+unsafe fn options_str<'a>(options: *const c_char) -> Option<&'a str> {
+    if options.is_null() {
+        return Some("");
+    }
+    // SAFETY: the caller guarantees a NUL-terminated string
+    unsafe { to_str(options) }
+}
@@ -0,0 +1,167 @@
+use anyhow::{Context, Result};
+use colored::*;
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use crate::{Error, SyntaxRules};
+
+struct NewLanguage {
+    key: String,
+    name: String,
+    extensions: Vec<String>,
+    single_line: Vec<String>,
+    multi_line: Vec<String>,
+}
+
+/// Prompts for a new language and appends it to the rules file at `rules_path`.
+/// Returns the key of the added language, or `None` if the user cancelled.
+pub fn run(rules_path: &Path, rules: &SyntaxRules) -> Result<Option<String>> {
+    let original = fs::read_to_string(rules_path)
+        .with_context(|| format!("Failed to read syntax rules from {}", rules_path.display()))?;
+    let existing: SyntaxRules = serde_json::from_str(&original)
+        .map_err(|e| Error::SyntaxRules(e.to_string()))?;
+
+    let language = loop {
+        let language = prompt_language()?;
+        match validate(&language, &existing, rules) {
+            Ok(()) => break language,
+            Err(e) => eprintln!("{} {}", "Error:".red(), e),
+        }
+        eprintln!("Let's try again.\n");
+    };
+
+    let entry = render_entry(&language);
+    eprintln!("\n{}", entry);
+    if !prompt("Add this language? (y/n)")?.eq_ignore_ascii_case("y") {
+        return Ok(None);
+    }
+
+    let updated = insert_entry(&original, &entry)?;
+    // Never write a file the tool itself could not load afterwards
+    serde_json::from_str::<SyntaxRules>(&updated).map_err(|e| Error::SyntaxRules(e.to_string()))?;
+    fs::write(rules_path, updated)
+        .with_context(|| format!("Failed to write syntax rules to {}", rules_path.display()))?;
+
+    Ok(Some(language.key))
+}
+
+fn prompt(question: &str) -> Result<String> {
+    eprint!("{} ", question);
+    io::stderr().flush()?;
+    let mut input = String::new();
+    if io::stdin().read_line(&mut input)? == 0 {
+        anyhow::bail!("Unexpected end of input");
+    }
+    Ok(input.trim().to_string())
+}
+
+fn split_list(input: &str) -> Vec<String> {
+    input
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn prompt_language() -> Result<NewLanguage> {
+    let name = prompt("Language name (e.g. Lua):")?;
+    let key: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+
+    let extensions = split_list(&prompt("File extensions, comma separated (e.g. lua, luau):")?)
+        .into_iter()
+        .map(|extension| extension.trim_start_matches('.').to_string())
+        .collect();
+    let single_line = split_list(&prompt("Single-line comment markers, comma separated (e.g. --):")?);
+
+    let multi_line = split_list(&prompt("Block comment delimiters as START END, comma separated (e.g. --[[ ]]):")?);
+
+    Ok(NewLanguage { key, name, extensions, single_line, multi_line })
+}
+
+fn validate(language: &NewLanguage, existing: &SyntaxRules, loaded: &SyntaxRules) -> Result<()> {
+    let invalid = |message: String| -> Result<()> { Err(Error::InvalidLanguage(message).into()) };
+
+    if language.name.is_empty() {
+        return invalid("the name must not be empty".to_string());
+    }
+    if existing.languages.contains_key(&language.key) {
+        return invalid(format!("a language with the key '{}' already exists", language.key));
+    }
+    if language.extensions.is_empty() {
+        return invalid("at least one extension is required".to_string());
+    }
+    if let Some(extension) = language.extensions.iter().find(|extension| extension.contains(char::is_whitespace)) {
+        return invalid(format!("extension '{}' contains whitespace", extension));
+    }
+    for rules in [existing, loaded] {
+        for (key, other) in &rules.languages {
+            if let Some(extension) = language.extensions.iter().find(|extension| other.extensions.contains(extension)) {
+                return invalid(format!("extension '{}' is already used by '{}'", extension, key));
+            }
+        }
+    }
+    if let Some(pair) = language.multi_line.iter().find(|pair| pair.split_whitespace().count() != 2) {
+        return invalid(format!("'{}' is not a START END pair", pair));
+    }
+    if language.single_line.is_empty() && language.multi_line.is_empty() {
+        return invalid("at least one single-line marker or block delimiter pair is required".to_string());
+    }
+    Ok(())
+}
+
+/// Formats the entry like the hand-written entries in syntax_rules.json.
+fn render_entry(language: &NewLanguage) -> String {
+    let quote = |text: &str| serde_json::to_string(text).unwrap();
+
+    let single_line: Vec<String> = language
+        .single_line
+        .iter()
+        .map(|pattern| {
+            format!(
+                "            {{\n                \"pattern\": {},\n                \"description\": \"Single-line comments\"\n            }}",
+                quote(pattern)
+            )
+        })
+        .collect();
+    let multi_line: Vec<String> = language
+        .multi_line
+        .iter()
+        .filter_map(|pair| pair.split_once(char::is_whitespace))
+        .map(|(start, end)| {
+            format!(
+                "            {{\n                \"start\": {},\n                \"end\": {},\n                \"description\": \"Multi-line comments\"\n            }}",
+                quote(start),
+                quote(end.trim())
+            )
+        })
+        .collect();
+    let list = |items: Vec<String>| {
+        if items.is_empty() { "[]".to_string() } else { format!("[\n{}\n        ]", items.join(",\n")) }
+    };
+
+    format!(
+        "    {}: {{\n        \"name\": {},\n        \"extensions\": [{}],\n        \"single_line\": {},\n        \"multi_line\": {}\n    }}",
+        quote(&language.key),
+        quote(&language.name),
+        language.extensions.iter().map(|extension| quote(extension)).collect::<Vec<_>>().join(", "),
+        list(single_line),
+        list(multi_line)
+    )
+}
+
+fn insert_entry(original: &str, entry: &str) -> Result<String> {
+    let closing = original
+        .rfind('}')
+        .ok_or_else(|| Error::SyntaxRules("the rules file is not a JSON object".to_string()))?;
+    let body = original[..closing].trim_end();
+    let separator = if body.ends_with('{') { "" } else { "," };
+    Ok(format!("{}{}\n{}\n}}\n", body, separator, entry))
+}
@@ -0,0 +1,60 @@
+use anyhow::Result;
+use std::{
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{git, result::CommentSpan};
+
+/// Parses an age like 90d, 12w, 1y or 36h into seconds.
+pub fn parse_age(value: &str) -> std::result::Result<u64, String> {
+    let value = value.trim();
+    let (number, unit) = match value.char_indices().last() {
+        Some((i, 'h')) => (&value[..i], 60 * 60),
+        Some((i, 'd')) => (&value[..i], 24 * 60 * 60),
+        Some((i, 'w')) => (&value[..i], 7 * 24 * 60 * 60),
+        Some((i, 'y')) => (&value[..i], 365 * 24 * 60 * 60),
+        _ => ("", 0),
+    };
+    number
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(unit))
+        .ok_or_else(|| format!("'{}' is not an age like 36h, 90d, 12w or 1y", value))
+}
+
+/// Unix timestamp `age` seconds ago.
+pub fn cutoff(age: u64) -> i64 {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |now| now.as_secs());
+    now.saturating_sub(age) as i64
+}
+
+/// When each line of a file was last changed, for `--older-than`.
+pub struct LineAges {
+    /// By line, from the first; `None` when not committed
+    times: Vec<Option<i64>>,
+}
+
+impl LineAges {
+    pub fn blame(path: &Path) -> Result<Self> {
+        Ok(LineAges { times: git::blame(path)? })
+    }
+
+    /// Ages that are not known, e.g. for an untracked file; nothing counts as old.
+    pub fn unknown() -> Self {
+        LineAges { times: Vec::new() }
+    }
+
+    /// Whether every line of `comment` was last changed before `cutoff`. `first_line` is the
+    /// line of the file the text holding the comment starts on.
+    pub fn older_than(&self, comment: &CommentSpan, first_line: usize, cutoff: i64) -> bool {
+        (comment.start.line..=comment.end.line).all(|line| {
+            self.times
+                .get(first_line + line - 2)
+                .copied()
+                .flatten()
+                .is_some_and(|time| time < cutoff)
+        })
+    }
+}
@@ -0,0 +1,133 @@
+use crate::LanguageRules;
+
+/// What `--action annotations` makes of a comment.
+pub enum Stripped {
+    /// Nothing worth keeping, the comment goes as a whole
+    Prose,
+    /// Nothing but annotations, the comment stays as it is
+    Annotations,
+    /// The annotation lines between the comment's delimiters, without the prose around them
+    Rebuilt(String),
+}
+
+/// Splits a block comment into the annotations to keep, like Javadoc `@param`, Doxygen
+/// `\return`, `@ts-expect-error` or `SPDX-License-Identifier:` lines, and the prose to drop.
+/// Lines following an annotation belong to it up to the next blank line. Line comments are
+/// all prose, each one is a comment of its own.
+pub fn strip_prose(text: &str, language: &LanguageRules) -> Stripped {
+    let Some(rule) = language
+        .multi_line
+        .iter()
+        .filter(|rule| {
+            text.len() >= rule.start.len() + rule.end.len() && text.starts_with(&rule.start) && text.ends_with(&rule.end)
+        })
+        .max_by_key(|rule| rule.start.len())
+    else {
+        return Stripped::Prose;
+    };
+    let body = &text[rule.start.len()..text.len() - rule.end.len()];
+    let newline = if body.contains("\r\n") { "\r\n" } else { "\n" };
+    let lines: Vec<&str> = body.split('\n').map(|line| line.trim_end_matches('\r')).collect();
+
+    let mut kept = vec![false; lines.len()];
+    let mut in_annotation = false;
+    for (index, line) in lines.iter().enumerate() {
+        let line = undecorated(line);
+        if is_annotation(line) {
+            in_annotation = true;
+        } else if line.is_empty() {
+            in_annotation = false;
+        }
+        kept[index] = in_annotation;
+    }
+    if !kept.contains(&true) {
+        return Stripped::Prose;
+    }
+    if lines.iter().zip(&kept).all(|(line, &kept)| kept || undecorated(line).is_empty()) {
+        return Stripped::Annotations;
+    }
+
+    // The first line goes on after the opening delimiter, the last holds the closing one
+    let last = lines.len() - 1;
+    let mut rebuilt = rule.start.clone();
+    if kept[0] {
+        rebuilt.push_str(lines[0]);
+    }
+    for index in (1..last).filter(|&index| kept[index]) {
+        rebuilt.push_str(newline);
+        rebuilt.push_str(lines[index]);
+    }
+    if kept[last] || undecorated(lines[last]).is_empty() {
+        rebuilt.push_str(newline);
+        rebuilt.push_str(lines[last]);
+    } else {
+        rebuilt.push(' ');
+    }
+    rebuilt.push_str(&rule.end);
+    Stripped::Rebuilt(rebuilt)
+}
+
+/// A comment line without indentation and the `*` many block comments start their lines with.
+fn undecorated(line: &str) -> &str {
+    line.trim().trim_start_matches('*').trim()
+}
+
+fn is_annotation(line: &str) -> bool {
+    let tag = |marker: char| {
+        line.strip_prefix(marker).is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_alphabetic()))
+    };
+    tag('@') || tag('\\') || line.starts_with("SPDX-")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Rules, Stripper};
+
+    fn strip(language: &str, source: &str) -> String {
+        let rules = Rules::bundled();
+        Stripper::new(&rules, &["--action", "annotations"]).unwrap().strip(source, language).unwrap().content
+    }
+
+    #[test]
+    fn comments_of_only_annotations_are_kept() {
+        for source in [
+            "/**\n * @param a the first\n *        of two\n * @return the sum\n */\nint f(int a);\n",
+            "/* SPDX-License-Identifier: MIT */\nint a;\n",
+            "/*\n * \\brief Doxygen\n */\nint a;\n",
+        ] {
+            assert_eq!(strip("java", source), source);
+        }
+    }
+
+    #[test]
+    fn prose_only_comments_are_removed() {
+        for (source, expected) in [
+            ("/* Just prose, email me@example.com */\nint a;\n", "\nint a;\n"),
+            ("/**\n * Adds numbers.\n */\nint f();\n", "\nint f();\n"),
+            ("// @param a line comments are all prose\nint a;\n", "\nint a;\n"),
+        ] {
+            assert_eq!(strip("java", source), expected, "in {:?}", source);
+        }
+    }
+
+    #[test]
+    fn prose_around_annotations_is_dropped() {
+        for (source, expected) in [
+            (
+                "/**\n * Adds two numbers.\n *\n * @param a the first\n * @param b the second\n */\nint f(int a, int b);\n",
+                "/**\n * @param a the first\n * @param b the second\n */\nint f(int a, int b);\n",
+            ),
+            (
+                "/** Parses it. @deprecated\n * @since 2\n *\n * More prose. */\nint g();\n",
+                "/**\n * @since 2 */\nint g();\n",
+            ),
+            (
+                "/* @ts-expect-error the type is wrong\n\n   because of prose */\nf(x);\n",
+                "/* @ts-expect-error the type is wrong */\nf(x);\n",
+            ),
+            ("/*\r\n * Prose.\r\n * @see Other\r\n */\r\nint h();\r\n", "/*\r\n * @see Other\r\n */\r\nint h();\r\n"),
+        ] {
+            assert_eq!(strip("java", source), expected, "in {:?}", source);
+        }
+    }
+}
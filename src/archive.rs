@@ -0,0 +1,150 @@
+use anyhow::{Context, Result};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{cache::fingerprint, paths, Error};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    fn of(path: &Path) -> Option<(Self, &'static str)> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        [(".tar.gz", ArchiveKind::TarGz), (".tgz", ArchiveKind::TarGz), (".tar", ArchiveKind::Tar), (".zip", ArchiveKind::Zip)]
+            .into_iter()
+            .find(|(suffix, _)| name.len() > suffix.len() && name.ends_with(suffix))
+            .map(|(suffix, kind)| (kind, suffix))
+    }
+}
+
+/// Whether `path` is a source bundle `remove` can work inside: `.zip`, `.tar`, `.tar.gz` or `.tgz`.
+pub fn is_archive(path: &Path) -> bool {
+    ArchiveKind::of(path).is_some() && path.is_file()
+}
+
+/// An archive unpacked into a scratch directory, which is deleted when dropped. Unpacking and
+/// packing go through `tar`, and `unzip` and `zip` for zip files.
+pub struct Unpacked {
+    archive: PathBuf,
+    kind: ArchiveKind,
+    /// The directory holding the scratch copy, named after the archive so messages about its
+    /// files say where they come from
+    root: PathBuf,
+    pub dir: PathBuf,
+}
+
+impl Unpacked {
+    pub fn new(archive: &Path) -> Result<Self> {
+        let (kind, _) = ArchiveKind::of(archive)
+            .ok_or_else(|| Error::UnsupportedFileType(format!("{} is not a zip or tar archive", archive.display())))?;
+        let archive = paths::plain(
+            &fs::canonicalize(archive).with_context(|| format!("Failed to resolve path: {}", archive.display()))?,
+        );
+        // The same archive always unpacks to the same place, so --record/--replay answers match
+        let root = env::temp_dir()
+            .join("comment-remover")
+            .join(fingerprint(archive.to_string_lossy().as_bytes()));
+        let dir = root.join(archive.file_name().unwrap_or_default());
+        if root.exists() {
+            fs::remove_dir_all(&root).with_context(|| format!("Failed to clear {}", root.display()))?;
+        }
+        fs::create_dir_all(&dir).with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+        let unpacked = Unpacked { archive, kind, root, dir };
+
+        let mut command = match kind {
+            ArchiveKind::Zip => {
+                let mut command = Command::new("unzip");
+                command.arg("-q").arg(&unpacked.archive).arg("-d").arg(&unpacked.dir);
+                command
+            }
+            ArchiveKind::Tar | ArchiveKind::TarGz => {
+                let mut command = Command::new("tar");
+                command
+                    .arg(if kind == ArchiveKind::TarGz { "-xzf" } else { "-xf" })
+                    .arg(&unpacked.archive)
+                    .arg("-C")
+                    .arg(&unpacked.dir);
+                command
+            }
+        };
+        run(&mut command, "unpack", &unpacked.archive)?;
+        Ok(unpacked)
+    }
+
+    /// Where the processed copy goes: the archive itself with `in_place`, otherwise a sibling
+    /// with `.stripped` before the extension, like `src.stripped.tar.gz`.
+    pub fn output_path(&self, in_place: bool) -> PathBuf {
+        if in_place {
+            return self.archive.clone();
+        }
+        let name = self.archive.file_name().unwrap_or_default().to_string_lossy();
+        let suffix = ArchiveKind::of(&self.archive).map_or("", |(_, suffix)| suffix);
+        let stem = &name[..name.len() - suffix.len()];
+        self.archive.with_file_name(format!("{}.stripped{}", stem, &name[stem.len()..]))
+    }
+
+    /// Packs the scratch copy into `output`, replacing it only once the new archive is complete.
+    pub fn pack(&self, output: &Path) -> Result<()> {
+        let mut members: Vec<_> = fs::read_dir(&self.dir)
+            .with_context(|| format!("Failed to read directory: {}", self.dir.display()))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.file_name()))
+            .collect();
+        members.sort();
+        // Next to the output, so the finished archive is renamed into place in one step
+        let partial = output.with_file_name(format!(".{}.partial", output.file_name().unwrap_or_default().to_string_lossy()));
+        // zip adds to an existing archive instead of replacing it
+        let _ = fs::remove_file(&partial);
+
+        let mut command = match self.kind {
+            ArchiveKind::Zip => {
+                let mut command = Command::new("zip");
+                command.current_dir(&self.dir).args(["-q", "-r", "-X"]).arg(&partial);
+                command
+            }
+            ArchiveKind::Tar | ArchiveKind::TarGz => {
+                let mut command = Command::new("tar");
+                command
+                    .arg(if self.kind == ArchiveKind::TarGz { "-czf" } else { "-cf" })
+                    .arg(&partial)
+                    .arg("-C")
+                    .arg(&self.dir);
+                command
+            }
+        };
+        command.args(&members);
+        if let Err(e) = run(&mut command, "pack", &self.archive) {
+            let _ = fs::remove_file(&partial);
+            return Err(e);
+        }
+        fs::rename(&partial, output).with_context(|| format!("Failed to write {}", output.display()))
+    }
+}
+
+impl Drop for Unpacked {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+fn run(command: &mut Command, action: &str, archive: &Path) -> Result<()> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let output = command
+        .output()
+        .with_context(|| format!("Failed to run {}, is it installed?", program))?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "Failed to {} {}: {}",
+            action,
+            archive.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use anyhow::{Context, Result};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    process,
+};
+
+/// Replaces `path` with `contents` so that readers only ever see the old or the new file.
+/// The data goes to a temporary file next to the target that first takes over the original's
+/// permissions and ownership, is synced to disk, and is then renamed over the original.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let temp_path = temp_path_for(path);
+    let result = write_temp(path, &temp_path, contents)
+        .and_then(|_| {
+            fs::rename(&temp_path, path)
+                .with_context(|| format!("Failed to replace {}", path.display()))
+        });
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+        return result;
+    }
+
+    sync_parent_dir(path);
+    Ok(())
+}
+
+fn temp_path_for(path: &Path) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.comment-remover.{}.tmp", file_name, process::id()))
+}
+
+fn write_temp(original: &Path, temp_path: &Path, contents: &[u8]) -> Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    // Private until the original's mode is in place, so the new contents are never readable by more users
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(temp_path)
+        .with_context(|| format!("Failed to create temporary file: {}", temp_path.display()))?;
+
+    if let Ok(metadata) = fs::metadata(original) {
+        copy_ownership(&metadata, temp_path);
+        file.set_permissions(metadata.permissions())
+            .with_context(|| format!("Failed to copy permissions to {}", temp_path.display()))?;
+    }
+    file.write_all(contents)
+        .with_context(|| format!("Failed to write temporary file: {}", temp_path.display()))?;
+    file.sync_all()
+        .with_context(|| format!("Failed to sync temporary file: {}", temp_path.display()))?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_ownership(metadata: &fs::Metadata, temp_path: &Path) {
+    use std::os::unix::fs::MetadataExt;
+    // Only privileged users can hand files to someone else, so a failure here is expected
+    let _ = std::os::unix::fs::chown(temp_path, Some(metadata.uid()), Some(metadata.gid()));
+}
+
+#[cfg(not(unix))]
+fn copy_ownership(_metadata: &fs::Metadata, _temp_path: &Path) {}
+
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) {
+    // Persist the rename itself; the file contents are already synced
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    if let Ok(dir) = File::open(parent) {
+        let _ = dir.sync_all();
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) {}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn rewritten_mode(name: &str, mode: u32) -> u32 {
+        let dir = std::env::temp_dir().join(format!("comment-remover-atomic-{}-{}", process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("file");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+
+        write_atomic(&path, b"new").unwrap();
+        let written = fs::metadata(&path).unwrap().permissions().mode() & 0o7777;
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        fs::remove_dir_all(&dir).unwrap();
+        written
+    }
+
+    #[test]
+    fn keeps_the_mode_of_the_original() {
+        assert_eq!(rewritten_mode("private", 0o600), 0o600);
+        assert_eq!(rewritten_mode("executable", 0o755), 0o755);
+        assert_eq!(rewritten_mode("read-only", 0o444), 0o444);
+    }
+}
@@ -0,0 +1,65 @@
+use std::ops::Range;
+
+use crate::LanguageRules;
+
+/// How many leading words of a line are searched for a declaration keyword, enough for
+/// modifiers like `pub(crate) async unsafe fn` or `export default abstract class`
+const MAX_MODIFIERS: usize = 5;
+
+/// Whether the comment at `range` sits on its own lines directly above a declaration, with
+/// only other comments, attributes or decorators in between.
+pub fn precedes_declaration(content: &str, range: &Range<usize>, language: &LanguageRules) -> bool {
+    !language.declarations.is_empty()
+        && next_code_line(content, range, language).is_some_and(|line| starts_declaration(line, language))
+}
+
+/// The trimmed line of code the comment at `range` belongs to: the first line after it that
+/// is not a comment, attribute or decorator. `None` for inline comments and when a blank
+/// line comes first, since that detaches the comment.
+pub fn next_code_line<'c>(content: &'c str, range: &Range<usize>, language: &LanguageRules) -> Option<&'c str> {
+    let line_start = content[..range.start].rfind('\n').map_or(0, |i| i + 1);
+    if !content[line_start..range.start].trim().is_empty() {
+        return None;
+    }
+
+    // The first piece is the rest of the comment's own line
+    let mut lines = content[range.end..].split('\n');
+    if !lines.next().is_some_and(|rest| rest.trim().is_empty()) {
+        return None;
+    }
+    for line in lines {
+        let line = line.trim();
+        if line.is_empty() {
+            return None;
+        }
+        if is_annotation(line) || is_comment_line(line, language) {
+            continue;
+        }
+        return Some(line);
+    }
+    None
+}
+
+/// Attributes and decorators that may sit between a comment and its item.
+fn is_annotation(line: &str) -> bool {
+    line.starts_with("#[") || line.starts_with('@')
+}
+
+fn is_comment_line(line: &str, language: &LanguageRules) -> bool {
+    language.single_line.iter().any(|rule| rule.strip_marker(line).is_some())
+        || language
+            .multi_line
+            .iter()
+            .any(|rule| line.starts_with(&rule.start) || line.ends_with(&rule.end))
+        || line.starts_with('*')
+}
+
+/// Looks for a declaration keyword among the words before the first `(`, `=`, `{` or `;`,
+/// so `int add(int a)` counts but `x = (int) y;` does not.
+fn starts_declaration(line: &str, language: &LanguageRules) -> bool {
+    let head = line.split(['(', '=', '{', ';']).next().unwrap_or(line);
+    head.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| !word.is_empty())
+        .take(MAX_MODIFIERS)
+        .any(|word| language.declarations.iter().any(|keyword| keyword == word))
+}
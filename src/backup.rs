@@ -0,0 +1,213 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use regex::Regex;
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    env, fs,
+    path::{Component, Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{collect_files, paths, Error};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BackupStrategy {
+    /// <file>.bak, replaced on every run
+    Suffix,
+    /// <file>.<UTC timestamp>.bak, one per run
+    Timestamped,
+    /// <file>.bak.<N>, one per run
+    Numbered,
+    /// No backup
+    None,
+}
+
+/// Stands for a `..` out of the base directory in mirrored paths
+const PARENT_DIR: &str = "__parent__";
+
+/// Where and how backups are written before a file is modified.
+pub struct BackupPolicy {
+    pub strategy: BackupStrategy,
+    pub dir: Option<PathBuf>,
+    /// The backups written in this run and the files they hold, so a file never replaces the
+    /// backup of another
+    written: RefCell<HashMap<PathBuf, PathBuf>>,
+}
+
+impl BackupPolicy {
+    pub fn new(strategy: BackupStrategy, dir: Option<PathBuf>) -> Self {
+        BackupPolicy { strategy, dir, written: RefCell::default() }
+    }
+
+    /// Copies `file` to its backup location and returns that location, if backups are enabled.
+    pub fn create(&self, file: &Path) -> Result<Option<PathBuf>> {
+        let Some(backup_path) = self.backup_path(file)? else {
+            return Ok(None);
+        };
+        claim(&self.written, &backup_path, file)?;
+        if let Some(parent) = backup_path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create backup directory: {}", parent.display()))?;
+        }
+        fs::copy(file, &backup_path)
+            .with_context(|| format!("Failed to create backup file: {}", backup_path.display()))?;
+        Ok(Some(backup_path))
+    }
+
+    fn backup_path(&self, file: &Path) -> Result<Option<PathBuf>> {
+        let base = match &self.dir {
+            Some(dir) => dir.join(mirrored_path(file, &env::current_dir()?)?),
+            None => file.to_path_buf(),
+        };
+        let name = base.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+
+        let backup_name = match self.strategy {
+            BackupStrategy::None => return Ok(None),
+            BackupStrategy::Suffix => format!("{}.bak", name),
+            BackupStrategy::Timestamped => format!("{}.{}.bak", name, utc_timestamp()),
+            BackupStrategy::Numbered => {
+                let next = (1..)
+                    .find(|n| !base.with_file_name(format!("{}.bak.{}", name, n)).exists())
+                    .unwrap_or(1);
+                format!("{}.bak.{}", name, next)
+            }
+        };
+        Ok(Some(base.with_file_name(backup_name)))
+    }
+}
+
+/// The path of `file` below a backup, history or output directory, relative to `base`. Each
+/// step up out of `base` becomes a `__parent__` directory, so nothing escapes the directory
+/// and `a.c` and `../a.c` do not end up in the same place.
+pub fn mirrored_path(file: &Path, base: &Path) -> Result<PathBuf> {
+    let absolute = if file.is_absolute() { file.to_path_buf() } else { env::current_dir()?.join(file) };
+    // A canonical `\\?\C:\src` and the `C:\src` of the working directory are the same place
+    let absolute = paths::normalized(&paths::plain(&absolute));
+    let base = paths::normalized(&paths::plain(base));
+    let common = absolute.components().zip(base.components()).take_while(|(a, b)| a == b).count();
+    if common == 0 {
+        // Another drive or share, which becomes directories of its own like `D` or `server/share`
+        return Ok(absolute
+            .components()
+            .flat_map(|component| match component {
+                Component::Normal(part) => vec![part.to_os_string()],
+                Component::Prefix(prefix) => paths::prefix_dirs(prefix.kind()),
+                _ => Vec::new(),
+            })
+            .collect());
+    }
+    let mut mirrored: PathBuf = base.components().skip(common).map(|_| PARENT_DIR).collect();
+    mirrored.extend(absolute.components().skip(common));
+    Ok(mirrored)
+}
+
+/// Records that `target` is written from `source` in this run, failing if it already was from
+/// another file.
+pub fn claim(written: &RefCell<HashMap<PathBuf, PathBuf>>, target: &Path, source: &Path) -> Result<()> {
+    let source = fs::canonicalize(source).unwrap_or_else(|_| source.to_path_buf());
+    let mut written = written.borrow_mut();
+    match written.get(target) {
+        Some(other) if *other != source => Err(Error::PathConflict(format!(
+            "{} and {} both go to {}",
+            paths::plain(other).display(),
+            paths::plain(&source).display(),
+            target.display()
+        ))
+        .into()),
+        _ => {
+            written.insert(target.to_path_buf(), source);
+            Ok(())
+        }
+    }
+}
+
+/// Compact UTC timestamp such as `20240131T235959Z`, sortable as text.
+pub fn utc_timestamp() -> String {
+    let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs());
+    let days = (seconds / 86_400) as i64;
+    let time_of_day = seconds % 86_400;
+
+    // Civil-from-days conversion (proleptic Gregorian calendar)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        time_of_day / 3_600,
+        time_of_day % 3_600 / 60,
+        time_of_day % 60
+    )
+}
+
+/// Deletes all but the `keep` newest backups of each file below `root`.
+/// Returns the backups that were (or with `dry_run` would be) deleted.
+pub fn clean(root: &Path, keep: usize, dry_run: bool) -> Result<Vec<PathBuf>> {
+    let backup_name = Regex::new(r"^(.+?)(?:\.\d{8}T\d{6}Z)?\.bak(?:\.\d+)?$").unwrap();
+
+    let mut backups: BTreeMap<PathBuf, Vec<(SystemTime, PathBuf)>> = BTreeMap::new();
+    for file in collect_files(root, false)? {
+        let name = file.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        let Some(captures) = backup_name.captures(&name) else {
+            continue;
+        };
+        let original = file.with_file_name(&captures[1]);
+        let modified = fs::metadata(&file).and_then(|metadata| metadata.modified()).unwrap_or(UNIX_EPOCH);
+        backups.entry(original).or_default().push((modified, file));
+    }
+
+    let mut removed = Vec::new();
+    for mut versions in backups.into_values() {
+        // Newest first, the file name breaks ties between backups made in the same second
+        versions.sort_by(|a, b| b.cmp(a));
+        for (_, backup) in versions.into_iter().skip(keep) {
+            if !dry_run {
+                fs::remove_file(&backup)
+                    .with_context(|| format!("Failed to remove backup: {}", backup.display()))?;
+            }
+            removed.push(backup);
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mirrors_files_below_the_base() {
+        let mirrored = mirrored_path(Path::new("/work/src/./lib/a.c"), Path::new("/work")).unwrap();
+        assert_eq!(mirrored, Path::new("src/lib/a.c"));
+    }
+
+    #[test]
+    fn keeps_steps_out_of_the_base_apart() {
+        let base = Path::new("/work/project");
+        assert_eq!(mirrored_path(Path::new("/work/project/a.c"), base).unwrap(), Path::new("a.c"));
+        assert_eq!(mirrored_path(Path::new("/work/project/../a.c"), base).unwrap(), Path::new("__parent__/a.c"));
+        assert_eq!(
+            mirrored_path(Path::new("/other/a.c"), base).unwrap(),
+            Path::new("__parent__/__parent__/other/a.c")
+        );
+    }
+
+    #[test]
+    fn claims_a_target_once() {
+        let written = RefCell::default();
+        claim(&written, Path::new("bk/a.c.bak"), Path::new("/nonexistent/a.c")).unwrap();
+        claim(&written, Path::new("bk/a.c.bak"), Path::new("/nonexistent/a.c")).unwrap();
+        let error = claim(&written, Path::new("bk/a.c.bak"), Path::new("/nonexistent/b/a.c")).unwrap_err();
+        assert!(matches!(error.downcast_ref::<Error>(), Some(Error::PathConflict(_))));
+    }
+}
@@ -0,0 +1,229 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+use crate::{find_language, remove::Remover, Error, LanguageRules, SyntaxRules};
+
+/// Each corpus is this many times smaller than the next, so growth of the time per byte shows
+/// work that is not linear in the input
+const SIZE_STEP: usize = 4;
+const SIZES: u32 = 3;
+
+/// Source the corpora are built from, by rules key: code with line, block and doc comments and
+/// strings holding comment markers. `{n}` is replaced by a counter so repeats differ.
+const SAMPLES: &[(&str, &str)] = &[
+    (
+        "rust",
+        r##"/// Adds {n} to the value.
+fn add_{n}(value: u32) -> u32 {
+    // the url below is not a comment
+    let url = "http://example.com/*{n}*/";
+    /* a block
+       comment */
+    value + {n} + url.len() as u32
+}
+"##,
+    ),
+    (
+        "python",
+        r##"def add_{n}(value):
+    """Adds {n} to the value."""
+    # the string below is not a comment
+    text = "# not a comment {n}"
+    return value + {n} + len(text)  # trailing
+"##,
+    ),
+    (
+        "javascript",
+        r##"/** Adds {n} to the value. */
+function add{n}(value) {
+    // the url below is not a comment
+    const url = `http://example.com/${value}/*{n}*/`;
+    return value + {n} + url.length; /* trailing */
+}
+"##,
+    ),
+    (
+        "c",
+        r##"/* Adds {n} to the value. */
+static int add_{n}(int value) {
+    // the string below is not a comment
+    const char *text = "// not a comment {n}";
+    return value + {n} + (int)strlen(text);
+}
+"##,
+    ),
+    (
+        "go",
+        r##"// Add{n} adds {n} to the value.
+func Add{n}(value int) int {
+	url := "http://example.com/{n}" // trailing
+	/* a block
+	   comment */
+	return value + {n} + len(url)
+}
+"##,
+    ),
+    (
+        "ruby",
+        r##"# Adds {n} to the value.
+def add_{n}(value)
+  text = "# not a comment {n}"
+  value + {n} + text.length # trailing
+end
+"##,
+    ),
+];
+
+/// The language mixes benchmarked, each made of the samples of these rules keys.
+const MIXES: &[(&str, &[&str])] = &[
+    ("rust", &["rust"]),
+    ("python", &["python"]),
+    ("mixed", &["rust", "python", "javascript", "c", "go", "ruby"]),
+];
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum BenchFormat {
+    Table,
+    Json,
+}
+
+/// One corpus: a file per language of the mix, together `bytes` long.
+#[derive(Serialize)]
+pub struct CorpusResult {
+    pub mix: String,
+    pub bytes: usize,
+    pub files: usize,
+    pub comments: usize,
+    /// Time of the fastest run
+    pub seconds: f64,
+    pub mb_per_second: f64,
+    pub comments_per_second: f64,
+}
+
+#[derive(Serialize)]
+pub struct BenchReport {
+    pub corpora: Vec<CorpusResult>,
+    pub slowdown: Vec<Slowdown>,
+}
+
+/// The time per byte on a mix's largest corpus over that on its smallest; about 1 when the
+/// work grows linearly with the input.
+#[derive(Serialize)]
+pub struct Slowdown {
+    pub mix: String,
+    pub factor: f64,
+}
+
+/// Times `remover` on the bundled corpora, the largest `size` bytes long, keeping the fastest
+/// of `runs` runs of each.
+pub fn run(remover: &Remover, rules: &SyntaxRules, size: usize, runs: usize) -> Result<BenchReport> {
+    let mut report = BenchReport { corpora: Vec::new(), slowdown: Vec::new() };
+    for (mix, keys) in MIXES {
+        let languages = keys.iter().map(|key| find_language(key, rules)).collect::<Result<Vec<_>>>()?;
+        let mut per_byte = Vec::new();
+        for step in (0..SIZES).rev() {
+            let bytes = size / SIZE_STEP.pow(step);
+            let files: Vec<(&LanguageRules, String, String)> = keys
+                .iter()
+                .zip(&languages)
+                .map(|(key, language)| {
+                    let name = format!("bench.{}", language.extensions.first().map_or("txt", String::as_str));
+                    (*language, name, generate(key, bytes / keys.len()))
+                })
+                .collect();
+            let (elapsed, comments) = fastest(remover, &files, runs.max(1))?;
+            let bytes: usize = files.iter().map(|(_, _, content)| content.len()).sum();
+            let seconds = elapsed.as_secs_f64().max(f64::EPSILON);
+            per_byte.push(seconds / bytes.max(1) as f64);
+            report.corpora.push(CorpusResult {
+                mix: mix.to_string(),
+                bytes,
+                files: files.len(),
+                comments,
+                seconds,
+                mb_per_second: bytes as f64 / seconds / 1_000_000.0,
+                comments_per_second: comments as f64 / seconds,
+            });
+        }
+        report.slowdown.push(Slowdown { mix: mix.to_string(), factor: per_byte[per_byte.len() - 1] / per_byte[0] });
+    }
+    Ok(report)
+}
+
+/// The time of the fastest of `runs` runs over `files`, and how many comments they hold.
+fn fastest(remover: &Remover, files: &[(&LanguageRules, String, String)], runs: usize) -> Result<(Duration, usize)> {
+    let mut best = Duration::MAX;
+    let mut comments = 0;
+    for _ in 0..runs {
+        let start = Instant::now();
+        comments = 0;
+        for (language, name, content) in files {
+            comments += remover.strip_text(content, name, language)?.found();
+        }
+        best = best.min(start.elapsed());
+    }
+    Ok((best, comments))
+}
+
+/// The sample for `key` repeated to about `bytes` bytes.
+fn generate(key: &str, bytes: usize) -> String {
+    let sample = SAMPLES.iter().find(|(sample_key, _)| *sample_key == key).map_or("", |(_, sample)| sample);
+    let mut content = String::with_capacity(bytes + sample.len());
+    let mut n = 0;
+    while content.len() < bytes && !sample.is_empty() {
+        content.push_str(&sample.replace("{n}", &n.to_string()));
+        n += 1;
+    }
+    content
+}
+
+/// Fails when a mix slowed down by more than `max_slowdown` from the smallest to the largest corpus.
+pub fn check(report: &BenchReport, max_slowdown: f64) -> Result<()> {
+    match report.slowdown.iter().find(|slowdown| slowdown.factor > max_slowdown) {
+        Some(slowdown) => Err(Error::Regression(format!(
+            "the time per byte of the {} corpus grew {:.2}x with a {}x larger input, more than --max-slowdown {}",
+            slowdown.mix,
+            slowdown.factor,
+            SIZE_STEP.pow(SIZES - 1),
+            max_slowdown
+        ))
+        .into()),
+        None => Ok(()),
+    }
+}
+
+pub fn render(report: &BenchReport, format: BenchFormat) -> Result<String> {
+    match format {
+        BenchFormat::Json => Ok(serde_json::to_string_pretty(report)? + "\n"),
+        BenchFormat::Table => {
+            let mut output = format!(
+                "{:<8} {:>12} {:>6} {:>10} {:>12} {:>10} {:>14}\n",
+                "Corpus", "Bytes", "Files", "Comments", "Time", "MB/s", "Comments/s"
+            );
+            for corpus in &report.corpora {
+                output.push_str(&format!(
+                    "{:<8} {:>12} {:>6} {:>10} {:>12.2?} {:>10.2} {:>14.0}\n",
+                    corpus.mix,
+                    corpus.bytes,
+                    corpus.files,
+                    corpus.comments,
+                    Duration::from_secs_f64(corpus.seconds),
+                    corpus.mb_per_second,
+                    corpus.comments_per_second
+                ));
+            }
+            output.push('\n');
+            for slowdown in &report.slowdown {
+                output.push_str(&format!(
+                    "{}: time per byte grew {:.2}x over a {}x larger input\n",
+                    slowdown.mix,
+                    slowdown.factor,
+                    SIZE_STEP.pow(SIZES - 1)
+                ));
+            }
+            Ok(output)
+        }
+    }
+}
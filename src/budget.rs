@@ -0,0 +1,115 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use crate::{config::ProjectConfig, find_language, stats::{CommentStats, StatsReport}, Error, SyntaxRules};
+
+/// What `check` compares against the comment budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CheckScope {
+    /// Every file on its own
+    File,
+    /// The files directly in each directory together
+    Directory,
+}
+
+/// The highest share of comment lines allowed: `--max-comment-ratio` for every language, and
+/// `max_comment_ratio` in the config file for single languages, which takes precedence.
+pub struct Budget {
+    default: Option<f64>,
+    /// By language name
+    languages: HashMap<String, f64>,
+}
+
+impl Budget {
+    pub fn new(default: Option<f64>, config: &ProjectConfig, rules: &SyntaxRules) -> Result<Self> {
+        let mut languages = HashMap::new();
+        for (name, ratio) in &config.max_comment_ratio {
+            if !(0.0..=1.0).contains(ratio) {
+                return Err(Error::Config(format!("max_comment_ratio for {} must be between 0 and 1", name)).into());
+            }
+            languages.insert(find_language(name, rules)?.name.clone(), *ratio);
+        }
+        if default.is_none() && languages.is_empty() {
+            return Err(Error::InvalidOptions(
+                "give --max-comment-ratio or set max_comment_ratio in the config file".to_string(),
+            )
+            .into());
+        }
+        Ok(Budget { default, languages })
+    }
+
+    fn limit(&self, language: &str) -> Option<f64> {
+        self.languages.get(language).copied().or(self.default)
+    }
+}
+
+/// A file or directory with more comments than its budget allows.
+pub struct Offender {
+    pub path: String,
+    pub language: String,
+    pub stats: CommentStats,
+    pub limit: f64,
+}
+
+/// Everything in `report` over budget. A directory holding a single language gets that
+/// language's limit, a mixed one the `--max-comment-ratio` limit.
+pub fn offenders(report: &StatsReport, budget: &Budget, scope: CheckScope) -> Vec<Offender> {
+    let groups: Vec<(String, String, CommentStats)> = match scope {
+        CheckScope::File => report
+            .files
+            .iter()
+            .map(|file| (file.path.clone(), file.language.clone(), file.stats.clone()))
+            .collect(),
+        CheckScope::Directory => {
+            let mut directories: BTreeMap<String, (Option<String>, CommentStats)> = BTreeMap::new();
+            for file in &report.files {
+                let directory = Path::new(&file.path)
+                    .parent()
+                    .map(|parent| parent.to_string_lossy().into_owned())
+                    .filter(|parent| !parent.is_empty())
+                    .unwrap_or_else(|| ".".to_string());
+                let (language, stats) = directories.entry(directory).or_default();
+                match language {
+                    None if stats.files == 0 => *language = Some(file.language.clone()),
+                    Some(name) if *name != file.language => *language = None,
+                    _ => {}
+                }
+                stats.add(&file.stats);
+            }
+            directories
+                .into_iter()
+                .map(|(directory, (language, stats))| (directory, language.unwrap_or_default(), stats))
+                .collect()
+        }
+    };
+    groups
+        .into_iter()
+        .filter_map(|(path, language, stats)| {
+            let limit = budget.limit(&language)?;
+            (stats.density() > limit).then_some(Offender { path, language, stats, limit })
+        })
+        .collect()
+}
+
+pub fn render(offenders: &[Offender]) -> String {
+    let mut output = format!(
+        "{:<40} {:<12} {:>8} {:>8} {:>8} {:>8}\n",
+        "Path", "Language", "Code", "Comment", "Density", "Limit"
+    );
+    for offender in offenders {
+        output.push_str(&format!(
+            "{:<40} {:<12} {:>8} {:>8} {:>7.1}% {:>7.1}%\n",
+            offender.path,
+            if offender.language.is_empty() { "mixed" } else { &offender.language },
+            offender.stats.code_lines,
+            offender.stats.comment_lines,
+            offender.stats.density() * 100.0,
+            offender.limit * 100.0
+        ));
+    }
+    output
+}
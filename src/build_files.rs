@@ -0,0 +1,113 @@
+use regex::Regex;
+
+use crate::{c_family, Comment, LanguageRules};
+
+pub fn is_dockerfile(language: &LanguageRules) -> bool {
+    language.name == "Dockerfile"
+}
+
+pub fn is_makefile(language: &LanguageRules) -> bool {
+    language.name == "Makefile"
+}
+
+/// Lines starting with `#` inside a heredoc (`RUN <<EOF ... EOF`) are part of the script or
+/// file being created, not Dockerfile comments. A comment inside a continued instruction takes
+/// its line break along, since an empty continuation line is deprecated.
+pub fn fix_dockerfile_comments(content: &str, comments: Vec<Comment>) -> Vec<Comment> {
+    let heredoc = Regex::new(r#"<<-?[ \t]*["']?([A-Za-z_][A-Za-z0-9_]*)["']?"#).unwrap();
+    let mut inside = Vec::new();
+    let mut terminator: Option<String> = None;
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let text = line.trim_end_matches(['\n', '\r']);
+        match &terminator {
+            Some(word) => {
+                if text.trim() == word {
+                    terminator = None;
+                } else {
+                    inside.push(offset..offset + line.len());
+                }
+            }
+            None if !text.trim_start().starts_with('#') => {
+                terminator = heredoc.captures(text).map(|captures| captures[1].to_string());
+            }
+            None => {}
+        }
+        offset += line.len();
+    }
+    comments
+        .into_iter()
+        .filter(|comment| !inside.iter().any(|line| line.contains(&comment.range.start)))
+        .map(|comment| {
+            if !continues_previous_line(content, comment.range.start) {
+                return comment;
+            }
+            let end = match content[comment.range.end..].find('\n') {
+                Some(newline) => comment.range.end + newline + 1,
+                None => comment.range.end,
+            };
+            Comment { range: comment.range.start..end, ..comment }
+        })
+        .collect()
+}
+
+/// Whether the line before the one holding `offset` ends in a backslash.
+fn continues_previous_line(content: &str, offset: usize) -> bool {
+    let line_start = content[..offset].rfind('\n').map_or(0, |i| i + 1);
+    content[..line_start.saturating_sub(1)]
+        .trim_end_matches('\r')
+        .trim_end_matches([' ', '\t'])
+        .ends_with('\\')
+}
+
+/// Recipe lines start with a tab and go to the shell, which has its own quoting, as do the
+/// lines a trailing backslash continues; `#` there is the shell's business. A make comment
+/// ending in a backslash continues on the next line.
+pub fn fix_makefile_comments(content: &str, comments: Vec<Comment>) -> Vec<Comment> {
+    let comments = comments
+        .into_iter()
+        .filter(|comment| {
+            let line_start = content[..comment.range.start].rfind('\n').map_or(0, |i| i + 1);
+            !content[line_start..].starts_with('\t') && !continues_previous_line(content, comment.range.start)
+        })
+        .collect();
+    c_family::join_continuations(content, comments)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Rules, Stripper};
+
+    fn strip(language: &str, source: &str) -> String {
+        let rules = Rules::bundled();
+        Stripper::new(&rules, &[]).unwrap().strip(source, language).unwrap().content
+    }
+
+    #[test]
+    fn dockerfile_heredocs_keep_their_hash_lines() {
+        for (source, expected) in [
+            ("# c\nRUN <<EOF\n#!/bin/sh\n# step one\necho hi\nEOF\n# d\nFROM a\n", "\nRUN <<EOF\n#!/bin/sh\n# step one\necho hi\nEOF\n\nFROM a\n"),
+            ("COPY <<-\"CONF\" /etc/a.conf\n# setting\nCONF\n", "COPY <<-\"CONF\" /etc/a.conf\n# setting\nCONF\n"),
+            ("# RUN <<EOF\n# not a heredoc\nFROM a\n", "\n\nFROM a\n"),
+        ] {
+            assert_eq!(strip("dockerfile", source), expected, "in {:?}", source);
+        }
+    }
+
+    #[test]
+    fn dockerfile_comments_in_continued_instructions_take_their_line_break() {
+        let source = "RUN apt-get update && \\\n    # c\n    apt-get install -y a\n";
+        assert_eq!(strip("dockerfile", source), "RUN apt-get update && \\\n    apt-get install -y a\n");
+    }
+
+    #[test]
+    fn makefile_recipes_belong_to_the_shell() {
+        for (source, expected) in [
+            ("# c\nall:\n\t# shell comment, echoed by make\n\techo '#' \\\n    # still the shell\n", "\nall:\n\t# shell comment, echoed by make\n\techo '#' \\\n    # still the shell\n"),
+            ("all:\n\t@echo a\n# c\nclean:\n", "all:\n\t@echo a\n\nclean:\n"),
+            ("# c \\\nstill comment\nA = 1\n", "\nA = 1\n"),
+        ] {
+            assert_eq!(strip("makefile", source), expected, "in {:?}", source);
+        }
+    }
+}
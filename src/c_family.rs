@@ -0,0 +1,162 @@
+use regex::Regex;
+
+use crate::{Comment, CommentKind, LanguageRules};
+
+pub fn is_c_family(language: &LanguageRules) -> bool {
+    matches!(language.name.as_str(), "C" | "C++")
+}
+
+/// A `//` comment ending in a backslash continues on the next line, since line splicing
+/// happens before comments are recognised. Comments found on the spliced lines belong to it.
+pub fn join_continuations(content: &str, comments: Vec<Comment>) -> Vec<Comment> {
+    let mut joined: Vec<Comment> = Vec::with_capacity(comments.len());
+    for comment in comments {
+        if joined.last().is_some_and(|previous| comment.range.start < previous.range.end) {
+            continue;
+        }
+        let mut end = comment.range.end;
+        if comment.kind != CommentKind::Block {
+            while content[..end].trim_end_matches([' ', '\t']).ends_with('\\') {
+                let Some(newline) = content[end..].find('\n') else {
+                    break;
+                };
+                let next_line = end + newline + 1;
+                end = content[next_line..]
+                    .find('\n')
+                    .map_or(content.len(), |i| next_line + i);
+                end -= usize::from(content[..end].ends_with('\r'));
+            }
+        }
+        joined.push(Comment { range: comment.range.start..end, ..comment });
+    }
+    joined
+}
+
+/// Adds `#if 0` blocks as comments for `--strip-if0`. With an `#else` only the disabled half
+/// and the `#endif` line go, keeping the live branch; blocks with `#elif` are left alone since
+/// removing them would need the condition rewritten. Blocks take their whole lines, line
+/// breaks included, so no blank line is left where they were.
+pub fn add_if0_blocks(content: &str, comments: Vec<Comment>) -> Vec<Comment> {
+    let directive = Regex::new(r"^[ \t]*#[ \t]*(if|ifdef|ifndef|elif|else|endif)\b").unwrap();
+    let if0 = Regex::new(r"^[ \t]*#[ \t]*if[ \t]+0\b").unwrap();
+
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let text = line.trim_end_matches(['\n', '\r']);
+        lines.push((offset, offset + line.len(), text));
+        offset += line.len();
+    }
+
+    let mut blocks: Vec<Comment> = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        let (start, _, text) = lines[index];
+        if !if0.is_match(text) {
+            index += 1;
+            continue;
+        }
+
+        let mut depth = 0;
+        let mut else_line = None;
+        let mut elif = false;
+        let mut endif_line = None;
+        for (line_index, &(_, _, line)) in lines.iter().enumerate().skip(index) {
+            let Some(captures) = directive.captures(line) else {
+                continue;
+            };
+            match &captures[1] {
+                "if" | "ifdef" | "ifndef" => depth += 1,
+                "elif" if depth == 1 => elif = true,
+                "else" if depth == 1 => else_line = Some(line_index),
+                "endif" => {
+                    depth -= 1;
+                    if depth == 0 {
+                        endif_line = Some(line_index);
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let Some(endif_line) = endif_line else {
+            break;
+        };
+        if !elif {
+            let (endif_start, endif_end, _) = lines[endif_line];
+            match else_line {
+                Some(else_line) => {
+                    blocks.push(Comment { kind: CommentKind::Block, range: start..lines[else_line].1 });
+                    blocks.push(Comment { kind: CommentKind::Block, range: endif_start..endif_end });
+                }
+                None => blocks.push(Comment { kind: CommentKind::Block, range: start..endif_end }),
+            }
+        }
+        // An `#if 0` nested in the live `#else` branch is still found
+        index = else_line.filter(|_| !elif).map_or(endif_line + 1, |else_line| else_line + 1);
+    }
+
+    if blocks.is_empty() {
+        return comments;
+    }
+    // Comments inside a removed block go with it
+    let mut merged: Vec<Comment> = comments
+        .into_iter()
+        .filter(|comment| {
+            !blocks
+                .iter()
+                .any(|block| comment.range.start < block.range.end && block.range.start < comment.range.end)
+        })
+        .collect();
+    merged.extend(blocks);
+    merged.sort_by_key(|comment| comment.range.start);
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Rules, Stripper};
+
+    fn strip(options: &[&str], source: &str) -> String {
+        let rules = Rules::bundled();
+        Stripper::new(&rules, options).unwrap().strip(source, "c").unwrap().content
+    }
+
+    #[test]
+    fn line_comments_continue_after_a_backslash() {
+        for (source, expected) in [
+            ("// a \\\nint hidden;\nint a;\n", "\nint a;\n"),
+            ("// a \\  \nstill comment \\\n/* too */\nint a;\n", "\nint a;\n"),
+            ("// a \\\r\nint hidden;\r\nint a;\r\n", "\r\nint a;\r\n"),
+            ("// a\nint a; \\\nint b;\n", "\nint a; \\\nint b;\n"),
+            ("/* a \\ */\nint a;\n", "\nint a;\n"),
+        ] {
+            assert_eq!(strip(&[], source), expected, "in {:?}", source);
+        }
+    }
+
+    #[test]
+    fn if0_blocks_go_with_strip_if0() {
+        let source = "int a;\n#if 0\nint old; // c\n#endif\nint b;\n";
+        assert_eq!(strip(&[], source), source);
+        assert_eq!(strip(&["--strip-if0"], source), "int a;\nint b;\n");
+        let nested = "#if 0\n#ifdef X\nint x;\n#endif\n#endif\nint b;\n";
+        assert_eq!(strip(&["--strip-if0"], nested), "int b;\n");
+        let last = "int a;\r\n#if 0\r\nint old;\r\n#endif\r\n";
+        assert_eq!(strip(&["--strip-if0"], last), "int a;\r\n");
+    }
+
+    #[test]
+    fn if0_with_else_keeps_the_live_branch() {
+        let source = "#if 0\nint old;\n#else\nint live;\n#if 0\nint older;\n#endif\n#endif\nint b;\n";
+        assert_eq!(strip(&["--strip-if0"], source), "int live;\nint b;\n");
+        assert_eq!(strip(&["--strip-if0", "--preserve-lines"], source), "\n\n\nint live;\n\n\n\n\nint b;\n");
+    }
+
+    #[test]
+    fn if0_with_elif_is_left_alone() {
+        let source = "#if 0\nint old;\n#elif X\nint x;\n#else\nint live;\n#endif\n";
+        assert_eq!(strip(&["--strip-if0"], source), source);
+    }
+}
@@ -0,0 +1,79 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{atomic_write, history};
+
+/// Bumped whenever the file layout changes, so an old cache is ignored rather than misread
+const CACHE_VERSION: u32 = 1;
+
+#[derive(Default, Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    /// Hash of everything besides the file contents that decides what gets removed
+    settings: String,
+    /// Content hash of each file that had nothing to remove, by canonical path
+    files: BTreeMap<String, String>,
+}
+
+/// Remembers which file contents were already found to have nothing to remove under the same
+/// rules and options, so unchanged files can be skipped on the next run.
+pub struct Cache {
+    path: PathBuf,
+    data: CacheFile,
+    dirty: bool,
+}
+
+impl Cache {
+    /// Loads `.comment-remover/cache.json`; a missing, unreadable or outdated cache starts empty.
+    pub fn open(settings: &str) -> Result<Self> {
+        let path = history::state_dir()?.join("cache.json");
+        let settings = fingerprint(settings.as_bytes());
+        let data = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<CacheFile>(&content).ok())
+            .filter(|data| data.version == CACHE_VERSION && data.settings == settings)
+            .unwrap_or_else(|| CacheFile { version: CACHE_VERSION, settings, files: BTreeMap::new() });
+        Ok(Cache { path, data, dirty: false })
+    }
+
+    /// Whether `file` still has the contents it had when it was found to have nothing to remove.
+    pub fn is_clean(&self, file: &Path, contents: &[u8]) -> bool {
+        key(file).is_some_and(|key| self.data.files.get(&key) == Some(&fingerprint(contents)))
+    }
+
+    pub fn mark_clean(&mut self, file: &Path, contents: &[u8]) {
+        if let Some(key) = key(file) {
+            self.data.files.insert(key, fingerprint(contents));
+            self.dirty = true;
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create cache directory: {}", parent.display()))?;
+        }
+        atomic_write::write_atomic(&self.path, serde_json::to_string_pretty(&self.data)?.as_bytes())
+            .with_context(|| format!("Failed to write cache: {}", self.path.display()))
+    }
+}
+
+fn key(file: &Path) -> Option<String> {
+    fs::canonicalize(file).ok().map(|path| path.to_string_lossy().into_owned())
+}
+
+/// 64-bit FNV-1a as hex; unlike `DefaultHasher` it is stable across builds, which a file on disk needs.
+pub fn fingerprint(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
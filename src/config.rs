@@ -0,0 +1,58 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{collections::BTreeMap, env, fs, path::PathBuf};
+
+use crate::{normalize::NormalizeRules, profile::Profile, Error};
+
+pub const CONFIG_FILE: &str = ".comment-remover.json";
+
+/// Per-project settings read from `.comment-remover.json`; command line flags add to them.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// Only comments matching one of these regexes are removed
+    #[serde(rename = "match")]
+    pub match_patterns: Vec<String>,
+    /// Comments matching one of these regexes are never removed
+    pub keep: Vec<String>,
+    /// Command run after each modified file is written, unless `--post-cmd` is given
+    pub post_cmd: Option<String>,
+    /// Command that checks each modified file, unless `--verify-cmd` is given
+    pub verify_cmd: Option<String>,
+    /// Syntax rules to download instead of using the local syntax_rules.json, unless
+    /// `--rules-url` is given
+    pub rules_url: Option<String>,
+    /// File suffixes such as `inc` or `tpl.php` mapped to a language key or name
+    pub extensions: BTreeMap<String, String>,
+    /// Preset for what `remove` keeps; `--profile` overrides it
+    pub profile: Option<Profile>,
+    /// The individual options a profile sets, which override it; flags override these
+    pub keep_license_header: Option<bool>,
+    pub strip_docstrings: Option<bool>,
+    pub strip_inner_docs: Option<bool>,
+    pub strip_directives: Option<bool>,
+    pub strip_macros: Option<bool>,
+    /// Highest share of comment lines `check` allows, by language key or name
+    pub max_comment_ratio: BTreeMap<String, f64>,
+    /// What `normalize` changes
+    pub normalize: NormalizeRules,
+}
+
+/// Finds the closest config file in the current directory or one of its parents.
+pub fn find_config_file() -> Result<Option<PathBuf>> {
+    let current_dir = env::current_dir()?;
+    Ok(current_dir
+        .ancestors()
+        .map(|dir| dir.join(CONFIG_FILE))
+        .find(|path| path.is_file()))
+}
+
+pub fn load() -> Result<ProjectConfig> {
+    let Some(path) = find_config_file()? else {
+        return Ok(ProjectConfig::default());
+    };
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    serde_json::from_str(&content)
+        .map_err(|e| Error::Config(format!("{}: {}", path.display(), e)).into())
+}
@@ -0,0 +1,215 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use std::ops::Range;
+
+use crate::{detect_line_ending, Comment, CommentKind, Error, LanguageRules, RuleClass};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CommentStyle {
+    /// Block comments, e.g. `/* ... */`
+    Block,
+    /// Line comments, e.g. `// ...`
+    Line,
+}
+
+/// Outcome of a conversion.
+pub struct Conversion {
+    pub content: String,
+    pub converted: usize,
+    /// Comments that could not be rewritten without changing the code, e.g. a block
+    /// comment followed by code on the same line, which a line comment would swallow
+    pub skipped: usize,
+}
+
+struct Markers<'a> {
+    line: &'a str,
+    start: &'a str,
+    end: &'a str,
+}
+
+/// A comment with the whitespace the regex patterns pick up around it trimmed off.
+struct Span<'a> {
+    range: Range<usize>,
+    text: &'a str,
+    indent: &'a str,
+    own_line: bool,
+    code_after: bool,
+}
+
+/// Rewrites the plain (non-doc) comments in `content` to `style`.
+/// Documentation comments are left alone since their syntax carries meaning.
+pub fn convert(content: &str, comments: &[Comment], language: &LanguageRules, style: CommentStyle) -> Result<Conversion> {
+    let line = language.single_line.iter().find(|rule| rule.class() == RuleClass::Normal);
+    let block = language.multi_line.iter().find(|rule| rule.class() == RuleClass::Normal);
+    let (Some(line), Some(block)) = (line, block) else {
+        let missing = if line.is_none() { "line" } else { "block" };
+        return Err(Error::UnsupportedConversion(language.name.clone(), missing.to_string()).into());
+    };
+    let markers = Markers { line: &line.pattern, start: &block.start, end: &block.end };
+    let newline = detect_line_ending(content);
+
+    let spans: Vec<(CommentKind, Span)> = comments
+        .iter()
+        .filter(|comment| comment.kind != CommentKind::Doc)
+        .map(|comment| (comment.kind, span(content, &comment.range)))
+        .collect();
+
+    let mut conversion = Conversion { content: String::with_capacity(content.len()), converted: 0, skipped: 0 };
+    let mut last_end = 0;
+    let mut index = 0;
+    while index < spans.len() {
+        let (kind, first) = &spans[index];
+        let (range, replacement, consumed) = match (style, kind) {
+            (CommentStyle::Block, CommentKind::Line) => {
+                let group = line_group(content, &spans[index..]);
+                let texts: Vec<&str> = group.iter().map(|span| strip_line_marker(span.text, markers.line)).collect();
+                let range = group[0].range.start..group[group.len() - 1].range.end;
+                (range, to_block(&texts, first.indent, &markers, newline), group.len())
+            }
+            (CommentStyle::Line, CommentKind::Block) => {
+                let replacement = if first.code_after {
+                    None
+                } else {
+                    to_lines(first, &markers, newline)
+                };
+                (first.range.clone(), replacement, 1)
+            }
+            _ => {
+                index += 1;
+                continue;
+            }
+        };
+
+        match replacement {
+            Some(replacement) => {
+                conversion.content.push_str(&content[last_end..range.start]);
+                conversion.content.push_str(&replacement);
+                last_end = range.end;
+                conversion.converted += consumed;
+            }
+            None => conversion.skipped += consumed,
+        }
+        index += consumed;
+    }
+    conversion.content.push_str(&content[last_end..]);
+    Ok(conversion)
+}
+
+fn span<'a>(content: &'a str, range: &Range<usize>) -> Span<'a> {
+    let text = &content[range.clone()];
+    let start = range.start + (text.len() - text.trim_start().len());
+    let end = range.start + text.trim_end().len();
+    let line_start = content[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = content[end..].find('\n').map_or(content.len(), |i| end + i);
+    let before = &content[line_start..start];
+
+    Span {
+        range: start..end,
+        text: &content[start..end],
+        indent: &before[..before.len() - before.trim_start().len()],
+        own_line: before.trim().is_empty(),
+        code_after: !content[end..line_end].trim().is_empty(),
+    }
+}
+
+/// Consecutive line comments that each sit on their own line at the same indentation.
+/// A trailing comment after code only ever forms a group of one.
+fn line_group<'a, 'b>(content: &str, spans: &'b [(CommentKind, Span<'a>)]) -> Vec<&'b Span<'a>> {
+    let first = &spans[0].1;
+    let mut group = vec![first];
+    if !first.own_line {
+        return group;
+    }
+    for (kind, span) in &spans[1..] {
+        let previous = group[group.len() - 1];
+        let between = &content[previous.range.end..span.range.start];
+        let adjacent = between.matches('\n').count() == 1 && between.trim().is_empty();
+        if *kind != CommentKind::Line || !span.own_line || span.indent != first.indent || !adjacent {
+            break;
+        }
+        group.push(span);
+    }
+    group
+}
+
+fn strip_line_marker<'a>(text: &'a str, marker: &str) -> &'a str {
+    let text = text.strip_prefix(marker).unwrap_or(text);
+    text.strip_prefix(' ').unwrap_or(text).trim_end()
+}
+
+fn to_block(texts: &[&str], indent: &str, markers: &Markers, newline: &str) -> Option<String> {
+    // Text containing the end marker would close the block early
+    if texts.iter().any(|text| text.contains(markers.end)) {
+        return None;
+    }
+    if let [text] = texts {
+        return Some(if text.is_empty() {
+            format!("{} {}", markers.start, markers.end)
+        } else {
+            format!("{} {} {}", markers.start, text, markers.end)
+        });
+    }
+
+    // Continuation lines line up with the text of the first one
+    let continuation = format!("{}{}", indent, " ".repeat(markers.start.chars().count() + 1));
+    let mut block = format!("{} {}", markers.start, texts[0]);
+    for text in &texts[1..] {
+        block.push_str(newline);
+        if !text.is_empty() {
+            block.push_str(&continuation);
+            block.push_str(text);
+        }
+    }
+    block.push(' ');
+    block.push_str(markers.end);
+    Some(block)
+}
+
+fn to_lines(span: &Span, markers: &Markers, newline: &str) -> Option<String> {
+    let inner = span.text.strip_prefix(markers.start)?.strip_suffix(markers.end)?;
+    let lines: Vec<&str> = inner.split('\n').map(|line| line.trim_end_matches('\r')).collect();
+    let following = lines[1..].iter().filter(|line| !line.trim().is_empty());
+
+    // ` * ` gutters are part of the block layout, not the text
+    let starred = following.clone().all(|line| line.trim_start().starts_with('*'));
+    let text_indent = following.map(|line| line.len() - line.trim_start().len()).min().unwrap_or(0);
+
+    let mut texts: Vec<&str> = lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            if i == 0 {
+                line.trim()
+            } else if starred {
+                let line = line.trim_start().trim_start_matches('*');
+                line.strip_prefix(' ').unwrap_or(line).trim_end()
+            } else {
+                line.get(text_indent..).unwrap_or(line.trim_start()).trim_end()
+            }
+        })
+        .collect();
+
+    // Drop the lines holding nothing but the delimiters
+    while texts.first().is_some_and(|text| text.is_empty()) {
+        texts.remove(0);
+    }
+    while texts.last().is_some_and(|text| text.is_empty()) {
+        texts.pop();
+    }
+    if texts.is_empty() {
+        return Some(markers.line.to_string());
+    }
+    // A line comment after code cannot continue onto the next line
+    if !span.own_line && texts.len() > 1 {
+        return None;
+    }
+
+    let separator = format!("{}{}", newline, span.indent);
+    Some(
+        texts
+            .iter()
+            .map(|text| if text.is_empty() { markers.line.to_string() } else { format!("{} {}", markers.line, text) })
+            .collect::<Vec<_>>()
+            .join(&separator),
+    )
+}
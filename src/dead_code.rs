@@ -0,0 +1,72 @@
+use crate::LanguageRules;
+
+/// Share of non-empty lines that must look like code for a comment to count as commented-out code
+const CODE_LINE_THRESHOLD: f64 = 0.6;
+
+/// Heuristically decides whether a comment is disabled code rather than prose.
+pub fn is_commented_out_code(comment: &str, language: &LanguageRules) -> bool {
+    let lines: Vec<String> = comment_body(comment, language)
+        .into_iter()
+        .filter(|line| !line.is_empty())
+        .collect();
+    if lines.is_empty() {
+        return false;
+    }
+
+    let code_lines = lines.iter().filter(|line| line_score(line, language) >= 2).count();
+    code_lines as f64 / lines.len() as f64 >= CODE_LINE_THRESHOLD
+}
+
+/// The comment text without markers, one trimmed entry per line.
+fn comment_body(comment: &str, language: &LanguageRules) -> Vec<String> {
+    let mut text = comment.trim();
+    if let Some(rule) = language.multi_line.iter().find(|rule| text.starts_with(&rule.start)) {
+        text = text[rule.start.len()..].strip_suffix(rule.end.as_str()).unwrap_or(&text[rule.start.len()..]);
+    }
+
+    text.lines()
+        .map(|line| {
+            let line = line.trim();
+            let line = language
+                .single_line
+                .iter()
+                .find_map(|rule| rule.strip_marker(line))
+                .unwrap_or(line);
+            // Block comments often prefix continuation lines with ` * `
+            let line = line.strip_prefix('*').unwrap_or(line);
+            line.trim().to_string()
+        })
+        .collect()
+}
+
+fn line_score(line: &str, language: &LanguageRules) -> i32 {
+    let mut score = 0;
+
+    if line.ends_with([';', '{', '}', '(', ')', '[', ']', ',']) {
+        score += 2;
+    }
+
+    let first_word = line
+        .split(|c: char| !c.is_alphanumeric() && c != '_' && c != '#')
+        .next()
+        .unwrap_or_default();
+    if language.keywords.iter().any(|keyword| keyword == first_word) {
+        score += 2;
+    }
+
+    if ["=", "(", "::", "->", "=>", "&&", "||", "++", "!="].iter().any(|token| line.contains(token)) {
+        score += 1;
+    }
+
+    // Sentences are a strong sign of prose
+    let words = line.split_whitespace().count();
+    let symbols = line.chars().filter(|c| c.is_ascii_punctuation() && !matches!(c, '.' | ',' | '\'')).count();
+    if words >= 4 && line.ends_with(['.', '?', '!']) && !line.contains('(') {
+        score -= 2;
+    }
+    if words >= 6 && symbols == 0 {
+        score -= 2;
+    }
+
+    score
+}
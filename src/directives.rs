@@ -0,0 +1,210 @@
+use std::ops::Range;
+
+use crate::{attached, LanguageRules};
+
+/// Markers understood by every language's tooling
+const COMMON: &[&str] = &["@generated", "NOSONAR", "LCOV_EXCL_", "cspell:", "spell-checker:"];
+
+const JAVASCRIPT: &[&str] = &[
+    "eslint-disable",
+    "eslint-enable",
+    "eslint-env",
+    "@ts-ignore",
+    "@ts-expect-error",
+    "@ts-nocheck",
+    "@ts-check",
+    "<reference ",
+    "istanbul ignore",
+    "c8 ignore",
+    "v8 ignore",
+    "prettier-ignore",
+    "biome-ignore",
+    "deno-lint-ignore",
+    "jshint ",
+    "@flow",
+    "@jsx ",
+    "@jsxImportSource",
+    "webpackChunkName",
+    "webpackIgnore",
+    "# sourceMappingURL=",
+];
+
+const C_FAMILY: &[&str] = &[
+    "NOLINT",
+    "clang-format off",
+    "clang-format on",
+    "cppcheck-suppress",
+    "fallthrough",
+    "FALLTHROUGH",
+    "coverity[",
+];
+
+const STYLESHEET: &[&str] = &["stylelint-disable", "stylelint-enable", "prettier-ignore", "csslint "];
+
+/// Built-in directives per language name; a comment containing any of them is kept.
+const DIRECTIVES: &[(&str, &[&str])] = &[
+    (
+        "Python",
+        &[
+            "noqa",
+            "type: ignore",
+            "pylint:",
+            "pragma: no cover",
+            "pragma: no branch",
+            "fmt: off",
+            "fmt: on",
+            "fmt: skip",
+            "isort:",
+            "mypy:",
+            "pyright:",
+            "pyre-ignore",
+            "-*- coding",
+            "vim: set fileencoding",
+        ],
+    ),
+    ("JavaScript", JAVASCRIPT),
+    ("TypeScript", JAVASCRIPT),
+    ("Java", &["noinspection", "CHECKSTYLE:", "@formatter:", "NOPMD"]),
+    ("C", C_FAMILY),
+    ("C++", C_FAMILY),
+    ("Rust", &["SAFETY:"]),
+    ("Ruby", &["rubocop:", ":nocov:", "typed:", "reek:", "steep:ignore"]),
+    ("Perl", &["no critic", "use critic", "perltidy"]),
+    ("Kotlin", &["ktlint-disable", "ktlint-enable", "noinspection", "@formatter:"]),
+    ("Swift", &["swiftlint:", "swift-format-ignore", "sourcery:"]),
+    ("Scala", &["scalastyle:", "scalafmt:", "format: off", "format: on", "scalafix:"]),
+    ("C#", &["ReSharper disable", "ReSharper restore", "<auto-generated", "@formatter:"]),
+    ("Zig", &["zig fmt: off", "zig fmt: on"]),
+    ("CSS", STYLESHEET),
+    ("SCSS", STYLESHEET),
+    ("Sass", STYLESHEET),
+    ("Less", STYLESHEET),
+    ("Go", &["nolint", "lint:ignore", "lint:file-ignore", "#nosec", "revive:disable", "revive:enable"]),
+    (
+        "PHP",
+        &["phpcs:", "@phpstan-ignore", "@psalm-suppress", "@codeCoverageIgnore", "@phpcsSuppress", "@noinspection"],
+    ),
+];
+
+/// Comments the compiler itself reads, by language name, matched at the start of the comment.
+/// Removing one changes or breaks the build, so they are kept even with `--strip-directives`.
+const COMPILER_DIRECTIVES: &[(&str, &[&str])] = &[
+    ("Go", &["//go:", "// +build", "//export ", "//extern ", "//line ", "/*line "]),
+    ("Dockerfile", &["# syntax=", "# escape=", "# check=", "#syntax=", "#escape=", "#check="]),
+    // SwiftPM reads the tools version off the first line of Package.swift
+    ("Swift", &["// swift-tools-version"]),
+    ("Ruby", &["# frozen_string_literal:", "# encoding:", "# coding:", "# -*- coding", "# warn_indent:", "# shareable_constant_value:"]),
+];
+
+/// Whether the comment at `range` is read by the compiler, like `//go:build` or the cgo
+/// preamble above `import "C"`.
+pub fn is_compiler_directive(content: &str, range: &Range<usize>, language: &LanguageRules) -> bool {
+    let comment = content[range.clone()].trim_start();
+    // Directives are read from comments of their own; after code they are prose
+    let line_start = content[..range.start].rfind('\n').map_or(0, |i| i + 1);
+    let own_line = content[line_start..range.start].trim().is_empty();
+    let prefixed = own_line
+        && COMPILER_DIRECTIVES
+            .iter()
+            .filter(|(name, _)| *name == language.name)
+            .any(|(_, prefixes)| prefixes.iter().any(|prefix| comment.starts_with(prefix)));
+    prefixed
+        || (language.name == "Go"
+            && attached::next_code_line(content, range, language).is_some_and(|line| line.starts_with("import \"C\"")))
+}
+
+/// Whether `comment` carries an instruction for a linter, formatter, type checker or
+/// coverage tool, so removing it would change how the code is checked or built.
+pub fn is_directive(comment: &str, language: &LanguageRules) -> bool {
+    let matches = |markers: &[&str]| markers.iter().any(|marker| comment.contains(marker));
+    matches(COMMON)
+        || DIRECTIVES
+            .iter()
+            .filter(|(name, _)| *name == language.name)
+            .any(|(_, markers)| matches(markers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bundled_rules, find_language};
+
+    /// Whether the first comment in `content` is a compiler directive.
+    fn compiler_directive(key: &str, content: &str) -> bool {
+        let rules = bundled_rules();
+        let language = find_language(key, &rules).unwrap();
+        let start = content.find("//").unwrap();
+        let end = content[start..].find('\n').map_or(content.len(), |i| start + i);
+        is_compiler_directive(content, &(start..end), language)
+    }
+
+    fn directive(key: &str, comment: &str) -> bool {
+        let rules = bundled_rules();
+        is_directive(comment, find_language(key, &rules).unwrap())
+    }
+
+    #[test]
+    fn keeps_go_compiler_directives() {
+        for content in [
+            "//go:build linux && amd64\n\npackage main\n",
+            "//go:generate stringer -type=Pill\n",
+            "import _ \"embed\"\n\n//go:embed static/*\nvar static embed.FS\n",
+            "//go:noinline\nfunc f() {}\n",
+            "//go:linkname runtimeNano runtime.nanotime\n",
+            "// +build linux,!cgo\n",
+            "//export Add\nfunc Add(a, b C.int) C.int { return a + b }\n",
+            "//line generated.go:10\n",
+            "\t//go:nosplit\n\tfunc g() {}\n",
+        ] {
+            assert!(compiler_directive("go", content), "in {:?}", content);
+        }
+    }
+
+    #[test]
+    fn keeps_the_cgo_preamble() {
+        assert!(compiler_directive("go", "// #include <stdio.h>\nimport \"C\"\n"));
+        assert!(!compiler_directive("go", "// #include <stdio.h>\n\nimport \"C\"\n"));
+        assert!(!compiler_directive("go", "// prints things\nimport \"fmt\"\n"));
+    }
+
+    #[test]
+    fn go_prose_is_no_compiler_directive() {
+        for content in [
+            "// go:build is read by the go command\n",
+            "// Go builds this package on every platform\n",
+            "// generate the tables with go generate\n",
+            "x := 1 // +build is the old syntax\n",
+        ] {
+            assert!(!compiler_directive("go", content), "in {:?}", content);
+        }
+        // Other languages do not read Go's directives
+        assert!(!compiler_directive("c", "//go:build linux\n"));
+    }
+
+    #[test]
+    fn keeps_go_linter_directives() {
+        for comment in [
+            "//nolint",
+            "//nolint:errcheck,gosec",
+            "// nolint:unused // kept for the plugin API",
+            "//lint:ignore SA1019 still needed",
+            "//lint:file-ignore U1000 generated",
+            "// #nosec G401",
+            "//revive:disable:exported",
+            "//revive:enable:exported",
+        ] {
+            assert!(directive("go", comment), "{:?}", comment);
+        }
+        assert!(!directive("go", "// Parse reads the header."));
+    }
+
+    #[test]
+    fn keeps_common_and_other_language_directives() {
+        assert!(directive("go", "// Code generated by protoc-gen-go. DO NOT EDIT. @generated"));
+        assert!(directive("python", "# noqa: E501"));
+        assert!(directive("python", "# type: ignore[attr-defined]"));
+        assert!(directive("javascript", "// eslint-disable-next-line no-console"));
+        assert!(directive("rust", "// SAFETY: the index is checked above"));
+        assert!(!directive("python", "# eslint-disable-next-line"));
+    }
+}
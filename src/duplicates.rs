@@ -0,0 +1,280 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use colored::*;
+use encoding_rs::Encoding;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    hash::{DefaultHasher, Hash, Hasher},
+    ops::Range,
+    path::PathBuf,
+};
+
+use crate::{
+    detect_comments, encoding, get_comment_patterns,
+    output::{self, FileFailure},
+    remove::Remover,
+    result::Positions,
+    resolve_overlaps, Comment, Engine,
+};
+
+/// Comments are compared by runs of this many words, so rewording one spot only changes a few
+const SHINGLE: usize = 3;
+/// Shingles per comment in the index that picks which comments to compare; comments alike
+/// enough almost always share one of their smallest
+const SKETCH: usize = 4;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum DuplicatesFormat {
+    Text,
+    Json,
+}
+
+/// Where one copy of a comment is.
+#[derive(Serialize)]
+pub struct Occurrence {
+    pub path: String,
+    pub line: usize,
+    pub end_line: usize,
+}
+
+/// A comment found in several places, the copies in file order.
+#[derive(Serialize)]
+pub struct DuplicateGroup {
+    /// Whether every copy has the same words; punctuation, case and comment markers aside
+    pub identical: bool,
+    /// The least share of word runs a copy has in common with the first
+    pub similarity: f64,
+    /// The first copy
+    pub text: String,
+    pub occurrences: Vec<Occurrence>,
+}
+
+/// One block comment, or line comments on consecutive lines, which read as one.
+struct Block {
+    file: usize,
+    range: Range<usize>,
+    line: usize,
+    end_line: usize,
+    text: String,
+}
+
+/// Comments of at least `min_words` words found more than once among `files`, with copies at
+/// least `similarity` alike. Files that cannot be read are reported and left out.
+pub fn find(
+    files: &[PathBuf],
+    remover: &Remover,
+    forced_encoding: Option<&'static Encoding>,
+    engine: Engine,
+    min_words: usize,
+    similarity: f64,
+) -> Result<Vec<DuplicateGroup>> {
+    let mut blocks = Vec::new();
+    for (index, file) in files.iter().enumerate() {
+        let file_path = file.to_string_lossy();
+        // Notebooks and Markdown are not one language
+        let Ok(language) = remover.language_of(&file_path, None) else {
+            continue;
+        };
+        let content = match encoding::read_source(file, forced_encoding) {
+            Ok(source) => source.text,
+            Err(e) => {
+                output::file_error(FileFailure::Skipped, file.display(), &e);
+                continue;
+            }
+        };
+        let patterns = get_comment_patterns(language, false)?;
+        let comments = detect_comments(&content, &file_path, language, &patterns, engine)?;
+        let comments = resolve_overlaps(&content, comments, false)?;
+        let mut positions = Positions::new(&content);
+        for range in comment_blocks(&content, &comments) {
+            // Line comments can take their line break along
+            let end = range.start + content[range.clone()].trim_end().len();
+            blocks.push(Block {
+                file: index,
+                line: positions.at(range.start).line,
+                end_line: positions.at(end).line,
+                text: content[range.clone()].trim().to_string(),
+                range,
+            });
+        }
+    }
+
+    // Copies with the same words are one entry, so only different wordings are compared
+    let mut wordings: Vec<(Vec<String>, Vec<usize>)> = Vec::new();
+    let mut by_words: HashMap<Vec<String>, usize> = HashMap::new();
+    for (index, block) in blocks.iter().enumerate() {
+        let words = words(&block.text);
+        if words.len() < min_words {
+            continue;
+        }
+        let wording = *by_words.entry(words.clone()).or_insert_with(|| {
+            wordings.push((words, Vec::new()));
+            wordings.len() - 1
+        });
+        wordings[wording].1.push(index);
+    }
+    let shingles: Vec<HashSet<u64>> = wordings.iter().map(|(words, _)| shingles(words)).collect();
+
+    let mut groups = Groups::new(wordings.len());
+    if similarity < 1.0 {
+        let mut index: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (wording, set) in shingles.iter().enumerate() {
+            let mut sketch: Vec<u64> = set.iter().copied().collect();
+            sketch.sort_unstable();
+            for hash in sketch.into_iter().take(SKETCH) {
+                let bucket = index.entry(hash).or_default();
+                for &other in bucket.iter() {
+                    if groups.find(other) != groups.find(wording) && jaccard(&shingles[other], set) >= similarity {
+                        groups.union(other, wording);
+                    }
+                }
+                bucket.push(wording);
+            }
+        }
+    }
+
+    let mut members: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for wording in 0..wordings.len() {
+        members.entry(groups.find(wording)).or_default().push(wording);
+    }
+    let mut found = Vec::new();
+    for wording_group in members.into_values() {
+        let mut copies: Vec<(usize, usize)> = wording_group
+            .iter()
+            .flat_map(|&wording| wordings[wording].1.iter().map(move |&block| (block, wording)))
+            .collect();
+        if copies.len() < 2 {
+            continue;
+        }
+        copies.sort_by_key(|&(block, _)| (blocks[block].file, blocks[block].range.start));
+        let (first, first_wording) = copies[0];
+        let order = (blocks[first].file, blocks[first].range.start);
+        let similarity = copies
+            .iter()
+            .map(|&(_, wording)| if wording == first_wording { 1.0 } else { jaccard(&shingles[first_wording], &shingles[wording]) })
+            .fold(1.0, f64::min);
+        found.push((order, DuplicateGroup {
+            identical: wording_group.len() == 1,
+            similarity,
+            text: blocks[first].text.clone(),
+            occurrences: copies
+                .iter()
+                .map(|&(block, _)| Occurrence {
+                    path: files[blocks[block].file].to_string_lossy().into_owned(),
+                    line: blocks[block].line,
+                    end_line: blocks[block].end_line,
+                })
+                .collect(),
+        }));
+    }
+    found.sort_by_key(|(order, _)| *order);
+    Ok(found.into_iter().map(|(_, group)| group).collect())
+}
+
+/// Comment ranges with line comments on consecutive lines, each alone on its line, joined.
+fn comment_blocks(content: &str, comments: &[Comment]) -> Vec<Range<usize>> {
+    let starts_line = |offset: usize| content[..offset].rsplit('\n').next().is_some_and(|before| before.trim().is_empty());
+    let one_line = |range: &Range<usize>| !content[range.clone()].trim_end().contains('\n');
+    let mut blocks: Vec<Range<usize>> = Vec::new();
+    let mut last_joinable = false;
+    for comment in comments {
+        let joinable = one_line(&comment.range) && starts_line(comment.range.start);
+        match blocks.last_mut() {
+            Some(last)
+                if last_joinable
+                    && joinable
+                    && content[last.end..comment.range.start].trim().is_empty()
+                    && content[last.end..comment.range.start].matches('\n').count() <= 1 =>
+            {
+                last.end = comment.range.end;
+            }
+            _ => blocks.push(comment.range.clone()),
+        }
+        last_joinable = joinable;
+    }
+    blocks
+}
+
+/// The words of a comment in lower case, without markers and punctuation.
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric()).filter(|word| !word.is_empty()).map(str::to_lowercase).collect()
+}
+
+fn shingles(words: &[String]) -> HashSet<u64> {
+    words
+        .windows(SHINGLE.min(words.len()).max(1))
+        .map(|window| {
+            let mut hasher = DefaultHasher::new();
+            window.hash(&mut hasher);
+            hasher.finish()
+        })
+        .collect()
+}
+
+fn jaccard(a: &HashSet<u64>, b: &HashSet<u64>) -> f64 {
+    let common = a.intersection(b).count();
+    let all = a.len() + b.len() - common;
+    if all == 0 { 1.0 } else { common as f64 / all as f64 }
+}
+
+/// Union-find over wordings, joining those alike enough.
+struct Groups {
+    parent: Vec<usize>,
+}
+
+impl Groups {
+    fn new(size: usize) -> Self {
+        Groups { parent: (0..size).collect() }
+    }
+
+    fn find(&mut self, mut item: usize) -> usize {
+        while self.parent[item] != item {
+            self.parent[item] = self.parent[self.parent[item]];
+            item = self.parent[item];
+        }
+        item
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        self.parent[a.max(b)] = a.min(b);
+    }
+}
+
+/// The lines of every copy but the first, by path, for `--auto` to remove.
+pub fn later_copies(groups: &[DuplicateGroup]) -> HashMap<String, Vec<Range<usize>>> {
+    let mut copies: HashMap<String, Vec<Range<usize>>> = HashMap::new();
+    for occurrence in groups.iter().flat_map(|group| group.occurrences.iter().skip(1)) {
+        copies.entry(occurrence.path.clone()).or_default().push(occurrence.line..occurrence.end_line + 1);
+    }
+    copies
+}
+
+pub fn render(groups: &[DuplicateGroup], format: DuplicatesFormat) -> Result<String> {
+    match format {
+        DuplicatesFormat::Json => Ok(serde_json::to_string_pretty(groups)? + "\n"),
+        DuplicatesFormat::Text => {
+            let mut output = String::new();
+            for group in groups {
+                let kind = if group.identical {
+                    "Identical comment".to_string()
+                } else {
+                    format!("Similar comment ({:.0}% alike)", group.similarity * 100.0)
+                };
+                output.push_str(&format!("{} in {} places:\n", kind.bold(), group.occurrences.len()));
+                for occurrence in &group.occurrences {
+                    let lines = if occurrence.end_line > occurrence.line {
+                        format!("{}-{}", occurrence.line, occurrence.end_line)
+                    } else {
+                        occurrence.line.to_string()
+                    };
+                    output.push_str(&format!("  {}:{}\n", occurrence.path, lines));
+                }
+                let preview = group.text.lines().next().unwrap_or_default();
+                output.push_str(&format!("    {}\n", preview.dimmed()));
+            }
+            Ok(output)
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use anyhow::{Context, Result};
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use std::{fs, path::Path};
+
+use crate::Error;
+
+/// Decoded file contents together with what is needed to write them back unchanged.
+pub struct SourceText {
+    pub text: String,
+    pub encoding: &'static Encoding,
+    pub bom: bool,
+}
+
+pub fn parse_encoding(label: &str) -> Result<&'static Encoding> {
+    Encoding::for_label(label.trim().as_bytes())
+        .ok_or_else(|| Error::UnknownEncoding(label.to_string()).into())
+}
+
+/// Reads a source file, using the byte order mark when present, otherwise `forced` or UTF-8.
+pub fn read_source(path: &Path, forced: Option<&'static Encoding>) -> Result<SourceText> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read file: {}", path.display()))?;
+    decode(&bytes, forced).with_context(|| format!("Failed to decode file: {}", path.display()))
+}
+
+pub fn decode(bytes: &[u8], forced: Option<&'static Encoding>) -> Result<SourceText> {
+    let (encoding, bom_length) = match Encoding::for_bom(bytes) {
+        Some((encoding, length)) if forced.is_none_or(|forced| forced == encoding) => (encoding, length),
+        _ => (forced.unwrap_or(UTF_8), 0),
+    };
+    // UTF-16 text is full of NUL bytes, so only sniff when nothing says what the bytes are
+    if bom_length == 0 && forced.is_none() && looks_binary(bytes) {
+        return Err(Error::BinaryFile.into());
+    }
+
+    // Refuse to guess: replacement characters would be written back and corrupt the file
+    let text = encoding
+        .decode_without_bom_handling_and_without_replacement(&bytes[bom_length..])
+        .ok_or_else(|| Error::Decode(encoding.name().to_string()))?;
+
+    Ok(SourceText {
+        text: text.into_owned(),
+        encoding,
+        bom: bom_length > 0,
+    })
+}
+
+/// Encodes `text` the same way the original file was stored, including its BOM.
+pub fn encode(text: &str, source: &SourceText) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() + 3);
+    if source.encoding == UTF_16LE || source.encoding == UTF_16BE {
+        // encoding_rs only decodes UTF-16, so encode it by hand
+        let little_endian = source.encoding == UTF_16LE;
+        if source.bom {
+            bytes.extend_from_slice(if little_endian { &[0xFF, 0xFE] } else { &[0xFE, 0xFF] });
+        }
+        for unit in text.encode_utf16() {
+            let unit_bytes = if little_endian { unit.to_le_bytes() } else { unit.to_be_bytes() };
+            bytes.extend_from_slice(&unit_bytes);
+        }
+        return Ok(bytes);
+    }
+
+    if source.bom && source.encoding == UTF_8 {
+        bytes.extend_from_slice(&[0xEF, 0xBB, 0xBF]);
+    }
+    let (encoded, _, unmappable) = source.encoding.encode(text);
+    if unmappable {
+        return Err(Error::Encode(source.encoding.name().to_string()).into());
+    }
+    bytes.extend_from_slice(&encoded);
+    Ok(bytes)
+}
+
+/// Sniffs the start of the data like git does: text has no NUL bytes and few control characters.
+fn looks_binary(bytes: &[u8]) -> bool {
+    let sample = &bytes[..bytes.len().min(8000)];
+    if sample.contains(&0) {
+        return true;
+    }
+    let control = sample
+        .iter()
+        .filter(|&&byte| byte < 0x20 && !matches!(byte, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b))
+        .count();
+    control * 10 > sample.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::WINDOWS_1252;
+
+    fn error(result: Result<impl Sized>) -> Error {
+        match result {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => e.downcast::<Error>().unwrap(),
+        }
+    }
+
+    #[test]
+    fn byte_order_marks_round_trip() {
+        for (bytes, encoding) in [
+            (&b"\xEF\xBB\xBFa\r\n\xC3\xA9"[..], UTF_8),
+            (b"\xFF\xFEa\0\r\0\n\0\xE9\0", UTF_16LE),
+            (b"\xFE\xFF\0a\0\r\0\n\0\xE9", UTF_16BE),
+        ] {
+            let source = decode(bytes, None).unwrap();
+            assert_eq!(source.text, "a\r\né");
+            assert_eq!(source.encoding, encoding);
+            assert!(source.bom);
+            assert_eq!(encode(&source.text, &source).unwrap(), bytes);
+        }
+        let plain = decode("é".as_bytes(), None).unwrap();
+        assert!(!plain.bom);
+        assert_eq!(encode(&plain.text, &plain).unwrap(), "é".as_bytes());
+    }
+
+    #[test]
+    fn a_forced_encoding_reads_and_writes_legacy_bytes() {
+        assert!(matches!(error(decode(b"caf\xE9", None)), Error::Decode(_)));
+        let source = decode(b"caf\xE9", Some(parse_encoding("latin1").unwrap())).unwrap();
+        assert_eq!(source.encoding, WINDOWS_1252);
+        assert_eq!(source.text, "café");
+        assert_eq!(encode("café!", &source).unwrap(), b"caf\xE9!");
+        // A byte order mark still wins over a forced encoding that agrees with it
+        let source = decode(b"\xFF\xFEa\0", Some(UTF_16LE)).unwrap();
+        assert!(source.bom);
+    }
+
+    #[test]
+    fn unmappable_characters_are_refused() {
+        let source = decode(b"a", Some(WINDOWS_1252)).unwrap();
+        assert!(matches!(error(encode("a \u{65E5}", &source)), Error::Encode(_)));
+    }
+
+    #[test]
+    fn binary_data_is_sniffed_unless_the_encoding_is_known() {
+        for bytes in [&b"a\0b"[..], b"\x01\x02\x03\x04\x05abc"] {
+            assert!(matches!(error(decode(bytes, None)), Error::BinaryFile), "{:?}", bytes);
+        }
+        assert!(decode(b"a\tb\x0c\x1b[0m\r\n", None).is_ok());
+        assert_eq!(decode(b"a\0b\0", Some(UTF_16LE)).unwrap().text, "ab");
+        assert!(matches!(error(parse_encoding("no-such-encoding")), Error::UnknownEncoding(_)));
+    }
+}
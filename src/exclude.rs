@@ -0,0 +1,57 @@
+use anyhow::Result;
+use regex::Regex;
+use std::path::Path;
+
+use crate::Error;
+
+/// Glob patterns (`*`, `**`, `?`) for paths that must never be processed.
+/// A pattern without a `/` matches any path component, e.g. `vendor` or `*.min.js`.
+#[derive(Default)]
+pub struct ExcludeSet {
+    patterns: Vec<Regex>,
+}
+
+impl ExcludeSet {
+    pub fn new(globs: &[String]) -> Result<Self> {
+        let patterns = globs
+            .iter()
+            .map(|glob| Regex::new(&glob_to_regex(glob)).map_err(|e| Error::InvalidPattern(e.to_string()).into()))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(ExcludeSet { patterns })
+    }
+
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        if self.patterns.is_empty() {
+            return false;
+        }
+        let path = path.to_string_lossy().replace('\\', "/");
+        let path = path.trim_start_matches("./");
+        self.patterns.iter().any(|pattern| pattern.is_match(path))
+    }
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let glob = glob.trim_start_matches("./").trim_end_matches('/');
+    let mut regex = String::from(if glob.contains('/') { "^" } else { "(^|/)" });
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                // `**/` also matches no directory at all
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    // Excluding a directory excludes everything below it
+    regex.push_str("(/|$)");
+    regex
+}
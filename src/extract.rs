@@ -0,0 +1,57 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::{line_number, Comment, LanguageRules};
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ExtractFormat {
+    Markdown,
+    Json,
+}
+
+impl ExtractFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExtractFormat::Markdown => "md",
+            ExtractFormat::Json => "json",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExtractedComment {
+    file: String,
+    line: usize,
+    text: String,
+}
+
+pub fn extract_comments(file_path: &str, content: &str, comments: &[Comment]) -> Vec<ExtractedComment> {
+    comments
+        .iter()
+        .map(|comment| {
+            // Patterns may start in the indentation before the marker, report the marker's line
+            let text = &content[comment.range.clone()];
+            let leading = text.len() - text.trim_start().len();
+            ExtractedComment {
+                file: file_path.to_string(),
+                line: line_number(content, comment.range.start + leading),
+                text: text.trim().to_string(),
+            }
+        })
+        .collect()
+}
+
+pub fn render(comments: &[ExtractedComment], file_path: &str, language: &LanguageRules, format: ExtractFormat) -> Result<String> {
+    match format {
+        ExtractFormat::Json => Ok(serde_json::to_string_pretty(comments)? + "\n"),
+        ExtractFormat::Markdown => {
+            let mut output = format!("# Comments in `{}`\n\n", file_path);
+            output.push_str(&format!("Language: {}, {} comments\n", language.name, comments.len()));
+            for comment in comments {
+                output.push_str(&format!("\n## Line {}\n\n```\n{}\n```\n", comment.line, comment.text));
+            }
+            Ok(output)
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use anyhow::{Context, Result};
+use std::{
+    collections::HashMap,
+    env,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::Error;
+
+fn run_git(args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .context("Failed to run git, is it installed?")?;
+    if !output.status.success() {
+        return Err(Error::Git(String::from_utf8_lossy(&output.stderr).trim().to_string()).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Turns the repository-relative paths git prints into paths usable from the current directory.
+fn resolve_paths(listing: &str) -> Result<Vec<PathBuf>> {
+    let top_level = run_git(&["rev-parse", "--show-toplevel"])?;
+    let top_level = Path::new(top_level.trim());
+    let current_dir = env::current_dir()?;
+
+    Ok(listing
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let path = top_level.join(line);
+            path.strip_prefix(&current_dir).map(Path::to_path_buf).unwrap_or(path)
+        })
+        .collect())
+}
+
+/// Files added, copied, modified or renamed in the index.
+pub fn staged_files() -> Result<Vec<PathBuf>> {
+    resolve_paths(&run_git(&["diff", "--cached", "--name-only", "--diff-filter=ACMR"])?)
+}
+
+/// Files in the working tree that differ from `reference`, deleted files excluded.
+pub fn changed_files(reference: &str) -> Result<Vec<PathBuf>> {
+    resolve_paths(&run_git(&["diff", "--name-only", "--diff-filter=ACMR", reference, "--"])?)
+}
+
+/// The directory git runs hooks from, honouring `core.hooksPath`.
+pub fn hooks_dir() -> Result<PathBuf> {
+    let hooks = run_git(&["rev-parse", "--git-path", "hooks"])?;
+    Ok(PathBuf::from(hooks.trim()))
+}
+
+/// Fails unless the current directory is inside a git work tree.
+pub fn ensure_repository() -> Result<()> {
+    run_git(&["rev-parse", "--is-inside-work-tree"]).map(|_| ())
+}
+
+/// When each line of `path` was last changed according to `git blame`, as a Unix timestamp
+/// of the authoring; `None` for lines that are not committed yet.
+pub fn blame(path: &Path) -> Result<Vec<Option<i64>>> {
+    let path = path.to_string_lossy();
+    let porcelain = run_git(&["blame", "--porcelain", "--", &path])?;
+
+    // Commit details are only printed the first time a commit shows up
+    let mut times: HashMap<&str, i64> = HashMap::new();
+    let mut lines = Vec::new();
+    let mut commit = "";
+    for line in porcelain.lines() {
+        if line.starts_with('\t') {
+            let committed = !commit.bytes().all(|byte| byte == b'0');
+            lines.push(times.get(commit).copied().filter(|_| committed));
+        } else if let Some(time) = line.strip_prefix("author-time ") {
+            if let Ok(time) = time.parse() {
+                times.insert(commit, time);
+            }
+        } else if let Some(sha) = line.split(' ').next()
+            && sha.len() == 40
+            && sha.bytes().all(|byte| byte.is_ascii_hexdigit())
+        {
+            commit = sha;
+        }
+    }
+    Ok(lines)
+}
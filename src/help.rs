@@ -0,0 +1,311 @@
+use anyhow::Result;
+use clap::{Arg, ArgAction, Command};
+use clap_mangen::Man;
+use colored::*;
+
+use crate::{languages, SyntaxRules};
+
+/// Name the tool is installed and documented under
+pub const BIN_NAME: &str = "comment_remover";
+
+/// Flags wider than this get their help text on the next line
+const FLAG_WIDTH: usize = 16;
+
+const EXAMPLES: &[&str] = &[
+    "remove main.rs",
+    "remove --auto main.rs",
+    "remove --context 8 main.rs",
+    "remove --auto --force main.rs",
+    "remove --auto src",
+    "remove --auto --staged",
+    "remove --check src",
+    "remove --auto --lang php templates/header.inc",
+    "remove --auto --output patch src > remove-comments.patch",
+    "remove --check --report sarif src > comments.sarif",
+    "remove --auto --report html src > cleanup.html",
+    "remove --auto --post-cmd \"rustfmt {file}\" src",
+    "remove --auto --verify-cmd \"python -m py_compile {file}\" scripts",
+    "remove --auto --markdown docs",
+    "remove --auto --match DEBUG --keep Copyright main.rs",
+    "remove --auto --profile aggressive --strip-directives=false src",
+    "remove --auto --older-than 180d src",
+    "remove --auto --action collapse src",
+    "remove --auto --action annotations src/main/java",
+    "remove --record decisions.json --replay decisions.json generated.rs",
+    "remove --auto release-1.2.tar.gz",
+    "remove --auto --out-dir ../published src",
+    "remove --resume",
+    "remove --auto --backup-dir .backups --backup-strategy timestamped src",
+    "review --exclude vendor src",
+    "serve --lsp --strip-docstrings",
+    "clean-backups --keep 2 --delete .backups",
+    "undo --steps 2 main.rs",
+    "ignore list",
+    "watch --exclude vendor src",
+    "extract --format json main.rs",
+    "convert --to line main.c",
+    "normalize --check src",
+    "remove --auto --strip-if0 src/legacy.c",
+    "stats src",
+    "check --max-comment-ratio 0.25 --by directory src",
+    "languages --format json",
+    "print src/main.c | less",
+    "analyze duplicates src --similarity 0.8",
+    "analyze duplicates src --auto --keep-license-header false",
+    "validate-rules my_rules.json",
+    "bench --max-slowdown 3 --format json",
+    "--rules-url https://example.com/team/rules.json remove --check src",
+    "--error-format json remove --check src",
+    "add-language",
+    "install-hook",
+    "completions zsh > ~/.zfunc/_comment_remover",
+    "info --generate-man > comment_remover.1",
+];
+
+const NOTES: &[&str] = &[
+    "By default, the tool runs in interactive mode and only removes non-inline comments",
+    "Prompts go file by file: `b` returns to the previous comment and `s` leaves the file unchanged, since nothing is written until its last comment is answered",
+    "Prompts show each comment with --context lines of code around it (3 by default), with line numbers and keywords highlighted",
+    "--replay answers prompts from a --record file by comment text, so answers survive regenerated or merged files",
+    "Answering `i` at the prompt keeps a comment and never asks about it again; `ignore clear` undoes that",
+    "A .zip, .tar or .tar.gz path is unpacked with unzip/tar and processed inside; the result goes to a `.stripped` copy, or back into the archive with --in-place",
+    "--out-dir writes every processed file, changed or not, to a mirrored tree and never touches the originals, so no backups or history are needed",
+    "In --out-dir, --backup-dir and history trees each step up out of the working directory is a `__parent__` directory, so ../lib/a.c goes to `__parent__/lib/a.c`; on Windows another drive or network share goes to `D/...` or `server/share/...`. A file that would land where another one of the run already went is reported instead of overwriting it",
+    "`normalize` in .comment-remover.json sets what `normalize` tidies, e.g. {\"banner\": \"=\", \"block_to_line\": false}; doc comments and directives are never touched",
+    "A run that modifies files logs its progress in .comment-remover/run.log until it finishes; after a crash or Ctrl-C, `remove --resume` continues it with the same options and skips the files already done",
+    "A backup file (.bak) is created unless --force or --backup-strategy none is used",
+    "Every modification is recorded in .comment-remover/history so `undo` can roll it back, unless --no-history",
+    "A leading license/SPDX header is preserved unless --keep-license-header false",
+    "Comments containing `comment-remover:keep` are never removed",
+    "With --action annotations, lines following an `@tag` belong to it up to the next blank line; a block comment of nothing but annotations is kept as it is",
+    "Compiler directives such as `//go:build`, `//go:embed` or a cgo preamble are never removed",
+    "Linter/tool directives like `eslint-disable`, `# noqa` or `NOLINT` are kept unless --strip-directives",
+    "Nothing between `comment-remover:off` and `comment-remover:on` is touched",
+    "--profile conservative also keeps doc comments and TODO/FIXME notes, --profile aggressive strips docstrings, directives and license headers too",
+    "A `profile` and the individual options in .comment-remover.json override the profile, and flags override both",
+    "--older-than goes by git blame, so comments on uncommitted lines or in untracked files are kept",
+    "Comments are detected based on language-specific syntax",
+    "Rules files with \"schema\": 2 can give rules regex `flags`, `must_start_line`, `inside_strings` (never, always) and a `class` (normal, doc, license, directive); files without it still load",
+    "A file claimed by several languages goes to the longest matching extension or file name, then the higher `priority`, then the key sorting first",
+    "Overlapping comment matches are resolved by keeping the one starting first, then the longest; --strict fails the file instead",
+    "Comment rules that match at the same position are tried by `priority`, then single-line before multi-line rules in the order listed",
+    "A single-line rule in syntax_rules.json can list `not_followed_by` texts and set `word_boundary` so its marker only starts a comment where it really is one",
+    "Files such as Makefile, Dockerfile or CMakeLists.txt are recognised by name; in Makefiles comments in recipes belong to the shell and are kept",
+    "The longest matching extension picks the language, so `.blade.php` is Blade rather than PHP",
+    "--rules-url or `rules_url` in .comment-remover.json downloads shared rules with curl; the copy is rechecked hourly and used when offline",
+    "`extensions` in .comment-remover.json maps more suffixes to languages, e.g. {\"inc\": \"php\"}",
+    "In Jupyter notebooks (.ipynb) only the code cells are changed",
+    "In Rust, `//!` crate/module docs and comments in `macro_rules!` bodies and doc attributes are kept by default",
+    "In Ruby and Perl, heredocs, quote-like literals such as %q{} or qw() and everything after __END__ are left alone",
+    "In C and C++, a `//` comment ending in a backslash continues on the next line",
+    "Block comments nest in Kotlin, Swift and Scala (`\"nested\": true` on a multi_line rule); raw, verbatim and multi-line strings are recognised there and in C# and Zig",
+    "KDoc, Scaladoc, Swift and C# `///` and `/** */` comments and Zig `///` and `//!` comments are documentation; four slashes make a regular comment",
+    "In PHP files, `<!-- -->` comments apply outside PHP tags and PHP comments inside",
+    "Files are written back in their original encoding, keeping any BOM",
+    "`max_comment_ratio` in .comment-remover.json sets per-language limits for `check`, e.g. {\"python\": 0.4}",
+    "`match` and `keep` lists and a `post_cmd` and `verify_cmd` in .comment-remover.json apply to every run in the project",
+    "Symlinks inside directories are skipped unless --follow-symlinks; writing a symlinked file replaces the link unless --dereference",
+    "Binary files and files over --max-size are skipped",
+    "Automatic and --check runs skip files unchanged since they were found to have nothing to remove (--no-cache to re-check)",
+    "Read-only files and files in read-only directories are skipped unless --force-readonly, which restores their permissions afterwards",
+    "Files are locked while being processed, and one changed by something else in the meantime is not written",
+    "A file that cannot be processed does not stop the run; the exit status is then 3",
+    "Exit status: 0 clean, 1 comments found by --check or `check`, 2 usage error, 3 files failed or the run failed, 4 invalid syntax rules",
+    "--error-format json prints each error on stderr as a JSON object with `severity` (skipped, error, fatal), `code`, `message` and a `path` or `exit_code`",
+    "Status messages go to stderr, so stdout can be piped or redirected",
+    "A progress bar is shown for automatic runs over several files in a terminal",
+    "`analyze duplicates` compares comments by their words, ignoring markers, case and punctuation; consecutive line comments count as one, and --auto removes every copy but the first the `remove` options allow removing",
+    "`bench` times the engine in memory on generated Rust, Python and mixed-language corpora of three sizes, with the `remove` options given; --max-slowdown fails when the time per byte grows with the input size",
+    "--engine tree-sitter needs a build with `--features tree-sitter`",
+];
+
+/// One documented argument: how it is written on the command line and what it does.
+struct ArgDoc {
+    usage: String,
+    help: String,
+}
+
+fn arg_doc(arg: &Arg) -> ArgDoc {
+    let value_name = || {
+        let possible: Vec<String> = arg
+            .get_possible_values()
+            .iter()
+            .filter(|value| !value.is_hide_set())
+            .map(|value| value.get_name().to_string())
+            .collect();
+        if !possible.is_empty() {
+            return format!("<{}>", possible.join("|"));
+        }
+        let name = match arg.get_value_names() {
+            Some(names) => names.iter().map(|name| name.to_string()).collect::<Vec<_>>().join(" "),
+            None => arg.get_id().as_str().to_uppercase(),
+        };
+        format!("<{}>", name)
+    };
+
+    let usage = if arg.is_positional() {
+        let repeated = if matches!(arg.get_action(), ArgAction::Append) { "..." } else { "" };
+        if arg.is_required_set() {
+            format!("{}{}", value_name(), repeated)
+        } else {
+            format!("[{}]{}", value_name(), repeated)
+        }
+    } else {
+        let mut names: Vec<String> = Vec::new();
+        if let Some(short) = arg.get_short() {
+            names.push(format!("-{}", short));
+        }
+        if let Some(long) = arg.get_long() {
+            names.push(format!("--{}", long));
+        }
+        let mut usage = names.join(", ");
+        if arg.get_action().takes_values() {
+            usage = format!("{} {}", usage, value_name());
+        }
+        usage
+    };
+
+    let mut help = arg.get_help().map(|help| help.to_string()).unwrap_or_default();
+    let defaults: Vec<String> = arg
+        .get_default_values()
+        .iter()
+        .map(|value| value.to_string_lossy().into_owned())
+        .collect();
+    if arg.get_action().takes_values() && !defaults.is_empty() {
+        help = format!("{} (default: {})", help, defaults.join(", "));
+    }
+    ArgDoc { usage, help }
+}
+
+fn arg_docs(command: &Command) -> Vec<ArgDoc> {
+    command
+        .get_arguments()
+        .filter(|arg| !arg.is_hide_set() && !arg.is_global_set())
+        .map(arg_doc)
+        .collect()
+}
+
+fn command_usage(command: &Command) -> String {
+    command
+        .get_positionals()
+        .filter(|arg| !arg.is_hide_set())
+        .fold(command.get_name().to_string(), |usage, arg| format!("{} {}", usage, arg_doc(arg).usage))
+}
+
+fn visible_subcommands(command: &Command) -> impl Iterator<Item = &Command> {
+    command.get_subcommands().filter(|sub| !sub.is_hide_set())
+}
+
+fn entry(output: &mut String, usage: &str, help: &str) {
+    if usage.len() <= FLAG_WIDTH {
+        output.push_str(&format!("  {:<width$} {}\n", usage, help, width = FLAG_WIDTH));
+    } else {
+        output.push_str(&format!("  {}\n  {:<width$} {}\n", usage, "", help, width = FLAG_WIDTH));
+    }
+}
+
+/// The `info` text, built from the clap command tree so it always matches the real flags.
+pub fn render_info(command: &Command, rules: &SyntaxRules) -> String {
+    let mut output = format!("\n{}\n", "Comment Removal CLI".bold().green());
+    if let Some(about) = command.get_about() {
+        output.push_str(&format!("{}\n", about));
+    }
+
+    output.push_str(&format!("\n{}\n  {} <COMMAND> [OPTIONS]\n", "USAGE:".bold(), BIN_NAME));
+
+    output.push_str(&format!("\n{}\n", "COMMANDS:".bold()));
+    for sub in visible_subcommands(command) {
+        let about = sub.get_about().map(|about| about.to_string()).unwrap_or_default();
+        entry(&mut output, &command_usage(sub), &about);
+    }
+
+    output.push_str(&format!("\n{}\n", "GLOBAL OPTIONS:".bold()));
+    for arg in command.get_arguments().filter(|arg| arg.is_global_set() && !arg.is_hide_set()) {
+        let doc = arg_doc(arg);
+        entry(&mut output, &doc.usage, &doc.help);
+    }
+
+    for sub in visible_subcommands(command) {
+        let options: Vec<ArgDoc> = arg_docs(sub).into_iter().filter(|doc| doc.usage.starts_with('-')).collect();
+        if options.is_empty() {
+            continue;
+        }
+        output.push_str(&format!("\n{}\n", format!("{} OPTIONS:", sub.get_name().to_uppercase()).bold()));
+        for doc in options {
+            entry(&mut output, &doc.usage, &doc.help);
+        }
+    }
+
+    output.push_str(&format!("\n{}\n", "EXAMPLES:".bold()));
+    for example in EXAMPLES {
+        output.push_str(&format!("  {} {}\n", BIN_NAME, example));
+    }
+
+    output.push_str(&format!("\n{}\n", "SUPPORTED LANGUAGES:".bold()));
+    for language in languages::list(rules) {
+        let extensions: Vec<String> = language.extensions.iter().map(|ext| format!(".{}", ext)).collect();
+        let extensions = [extensions.as_slice(), language.filenames.as_slice()].concat();
+        output.push_str(&format!("  • {} ({})\n", language.name, extensions.join(", ")));
+    }
+
+    output.push_str(&format!("\n{}\n", "NOTES:".bold()));
+    for note in NOTES {
+        output.push_str(&format!("  • {}\n", note));
+    }
+    output
+}
+
+/// Escapes text for roff: backslashes, dashes, and a leading `.` or `'` that would start a request.
+fn roff_escape(text: &str) -> String {
+    let escaped = text.replace('\\', "\\e").replace('-', "\\-");
+    if escaped.starts_with('.') || escaped.starts_with('\'') {
+        format!("\\&{}", escaped)
+    } else {
+        escaped
+    }
+}
+
+/// A section 1 man page: clap_mangen renders the top-level page, and every command with its
+/// options, the examples, the languages from the rules and the notes are appended.
+pub fn render_man(command: Command, rules: &SyntaxRules) -> Result<Vec<u8>> {
+    let man = Man::new(command.clone());
+    let mut page = Vec::new();
+    man.render_title(&mut page)?;
+    man.render_name_section(&mut page)?;
+    man.render_synopsis_section(&mut page)?;
+    man.render_description_section(&mut page)?;
+    man.render_options_section(&mut page)?;
+
+    // Documented inline rather than as separate comment_remover-<command>(1) pages
+    let mut extra = String::from(".SH COMMANDS\n");
+    for sub in visible_subcommands(&command) {
+        extra.push_str(&format!(".SS \"{}\"\n", roff_escape(&command_usage(sub))));
+        if let Some(about) = sub.get_about() {
+            extra.push_str(&format!("{}\n", roff_escape(&about.to_string())));
+        }
+        for doc in arg_docs(sub) {
+            extra.push_str(&format!(".TP\n\\fB{}\\fR\n{}\n", roff_escape(&doc.usage), roff_escape(&doc.help)));
+        }
+    }
+
+    extra.push_str(".SH EXAMPLES\n");
+    for example in EXAMPLES {
+        extra.push_str(&format!(".TP\n{} {}\n", BIN_NAME, roff_escape(example)));
+    }
+
+    extra.push_str(".SH \"SUPPORTED LANGUAGES\"\n");
+    for language in languages::list(rules) {
+        let extensions: Vec<String> = language.extensions.iter().map(|ext| format!(".{}", ext)).collect();
+        let extensions = [extensions.as_slice(), language.filenames.as_slice()].concat();
+        extra.push_str(&format!(".IP \\(bu 2\n{} ({})\n", roff_escape(&language.name), roff_escape(&extensions.join(", "))));
+    }
+
+    extra.push_str(".SH NOTES\n");
+    for note in NOTES {
+        extra.push_str(&format!(".IP \\(bu 2\n{}\n", roff_escape(note)));
+    }
+    page.extend_from_slice(extra.as_bytes());
+
+    man.render_version_section(&mut page)?;
+    Ok(page)
+}
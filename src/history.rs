@@ -0,0 +1,129 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    atomic_write,
+    backup::{mirrored_path, utc_timestamp},
+    patch, Error,
+};
+
+/// Project state directory; the history lives below it and the nearest one up the tree is used.
+pub const STATE_DIR: &str = ".comment-remover";
+
+/// One recorded modification: the file as it was before, and a diff of what changed.
+pub struct Entry {
+    pub sequence: u32,
+    pub timestamp: String,
+    pub snapshot: PathBuf,
+    pub diff: PathBuf,
+}
+
+/// Per-file journal of modifications under `.comment-remover/history/`, so changes can be
+/// rolled back step by step even after several runs.
+pub struct Journal {
+    root: PathBuf,
+}
+
+/// The `.comment-remover` directory of the closest directory that has one, or of the current directory.
+pub fn state_dir() -> Result<PathBuf> {
+    let current_dir = env::current_dir()?;
+    let project = current_dir
+        .ancestors()
+        .find(|dir| dir.join(STATE_DIR).is_dir())
+        .unwrap_or(current_dir.as_path());
+    Ok(project.join(STATE_DIR))
+}
+
+impl Journal {
+    pub fn open() -> Result<Self> {
+        Ok(Journal {
+            root: state_dir()?.join("history"),
+        })
+    }
+
+    fn file_dir(&self, file: &Path) -> Result<PathBuf> {
+        let file = fs::canonicalize(file).with_context(|| format!("Failed to resolve path: {}", file.display()))?;
+        let project = self.root.parent().and_then(Path::parent).unwrap_or(&self.root);
+        let project = fs::canonicalize(project).unwrap_or_else(|_| project.to_path_buf());
+        Ok(self.root.join(mirrored_path(&file, &project)?))
+    }
+
+    /// Records that `file` is about to change from `before` to `after`; call before writing.
+    pub fn record(&self, file: &Path, before: &str, after: &str) -> Result<Entry> {
+        let dir = self.file_dir(file)?;
+        fs::create_dir_all(&dir).with_context(|| format!("Failed to create history directory: {}", dir.display()))?;
+
+        let sequence = self.entries(file)?.last().map_or(1, |entry| entry.sequence + 1);
+        let timestamp = utc_timestamp();
+        let stem = format!("{:04}-{}", sequence, timestamp);
+        let entry = Entry {
+            sequence,
+            timestamp,
+            snapshot: dir.join(format!("{}.orig", stem)),
+            diff: dir.join(format!("{}.diff", stem)),
+        };
+
+        // The snapshot is a byte copy so undo restores the exact encoding and line endings
+        fs::copy(file, &entry.snapshot)
+            .with_context(|| format!("Failed to write history snapshot: {}", entry.snapshot.display()))?;
+        fs::write(&entry.diff, patch::file_diff(&patch::patch_path(file), before, after))
+            .with_context(|| format!("Failed to write history diff: {}", entry.diff.display()))?;
+        Ok(entry)
+    }
+
+    /// Drops an entry again when its modification was rolled back right away.
+    pub fn forget(&self, entry: &Entry) {
+        let _ = fs::remove_file(&entry.snapshot);
+        let _ = fs::remove_file(&entry.diff);
+    }
+
+    /// Recorded modifications of `file`, oldest first.
+    pub fn entries(&self, file: &Path) -> Result<Vec<Entry>> {
+        let dir = self.file_dir(file)?;
+        let Ok(read_dir) = fs::read_dir(&dir) else {
+            return Ok(Vec::new());
+        };
+        let snapshot_name = Regex::new(r"^(\d+)-(\d{8}T\d{6}Z)\.orig$").unwrap();
+
+        let mut entries: Vec<Entry> = read_dir
+            .filter_map(|item| item.ok())
+            .filter_map(|item| {
+                let name = item.file_name().to_string_lossy().into_owned();
+                let captures = snapshot_name.captures(&name)?;
+                Some(Entry {
+                    sequence: captures[1].parse().ok()?,
+                    timestamp: captures[2].to_string(),
+                    snapshot: item.path(),
+                    diff: item.path().with_extension("diff"),
+                })
+            })
+            .collect();
+        entries.sort_by_key(|entry| entry.sequence);
+        Ok(entries)
+    }
+
+    /// Restores `file` to how it was before the last `steps` recorded modifications and drops
+    /// them from the history. Returns the entries that were undone, newest first.
+    pub fn undo(&self, file: &Path, steps: usize) -> Result<Vec<Entry>> {
+        let mut entries = self.entries(file)?;
+        if steps == 0 || steps > entries.len() {
+            return Err(Error::NotEnoughHistory(file.display().to_string(), steps, entries.len()).into());
+        }
+
+        let undone: Vec<Entry> = entries.split_off(entries.len() - steps).into_iter().rev().collect();
+        let target = undone.last().expect("at least one step");
+        let contents = fs::read(&target.snapshot)
+            .with_context(|| format!("Failed to read history snapshot: {}", target.snapshot.display()))?;
+        atomic_write::write_atomic(file, &contents)
+            .with_context(|| format!("Failed to restore file: {}", file.display()))?;
+
+        for entry in &undone {
+            self.forget(entry);
+        }
+        Ok(undone)
+    }
+}
@@ -0,0 +1,85 @@
+use anyhow::{Context, Result};
+use std::{env, fs, path::PathBuf};
+
+use crate::{git, Error, SyntaxRules};
+
+const HOOK_MARKER: &str = "# Installed by comment_remover install-hook";
+
+/// Writes `.git/hooks/pre-commit`, refusing to replace a hook someone else wrote unless `force` is set.
+pub fn install(force: bool) -> Result<PathBuf> {
+    let hooks_dir = git::hooks_dir()?;
+    let hook_path = hooks_dir.join("pre-commit");
+
+    if let Ok(existing) = fs::read_to_string(&hook_path)
+        && !existing.contains(HOOK_MARKER)
+        && !force
+    {
+        return Err(Error::HookExists(hook_path.display().to_string()).into());
+    }
+
+    // Point at this binary so the hook works even when it is not on PATH
+    let exe = env::current_exe().context("Failed to locate the comment_remover executable")?;
+    let script = format!(
+        "#!/bin/sh\n{}\nexec \"{}\" remove --check --staged\n",
+        HOOK_MARKER,
+        exe.display()
+    );
+
+    fs::create_dir_all(&hooks_dir)
+        .with_context(|| format!("Failed to create hooks directory: {}", hooks_dir.display()))?;
+    fs::write(&hook_path, script)
+        .with_context(|| format!("Failed to write hook: {}", hook_path.display()))?;
+    make_executable(&hook_path)?;
+
+    Ok(hook_path)
+}
+
+#[cfg(unix)]
+fn make_executable(path: &std::path::Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .with_context(|| format!("Failed to make hook executable: {}", path.display()))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &std::path::Path) -> Result<()> {
+    Ok(())
+}
+
+/// An entry for a `.pre-commit-hooks.yaml`, limited to the extensions and file names the
+/// loaded rules support.
+pub fn pre_commit_config(rules: &SyntaxRules) -> String {
+    let mut extensions: Vec<String> = rules
+        .languages
+        .values()
+        .flat_map(|language| language.extensions.iter().map(|extension| extension.replace('.', "\\.")))
+        .collect();
+    extensions.sort_unstable();
+    extensions.dedup();
+    let mut filenames: Vec<String> = rules
+        .languages
+        .values()
+        .flat_map(|language| {
+            language.filenames.iter().map(|name| match name.strip_suffix('*') {
+                Some(prefix) => format!("{}.*", regex::escape(prefix)),
+                None => regex::escape(name),
+            })
+        })
+        .collect();
+    filenames.sort_unstable();
+    let files = if filenames.is_empty() {
+        format!("\\.({})", extensions.join("|"))
+    } else {
+        format!("(\\.({})|(^|/)({}))", extensions.join("|"), filenames.join("|"))
+    };
+
+    format!(
+        "-   id: comment-remover\n    \
+         name: comment-remover\n    \
+         description: Reject commits that contain removable comments\n    \
+         entry: comment_remover remove --check\n    \
+         language: system\n    \
+         files: {}$\n",
+        files
+    )
+}
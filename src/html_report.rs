@@ -0,0 +1,194 @@
+use similar::{DiffTag, TextDiff};
+use std::{fmt::Write, path::PathBuf};
+
+use crate::{help::BIN_NAME, patch::patch_path};
+
+/// Unchanged lines shown around each change
+const CONTEXT_LINES: usize = 3;
+
+const STYLE: &str = "
+body { font-family: system-ui, sans-serif; margin: 2em; color: #1f2328; }
+nav li { margin: 0.2em 0; }
+section { margin-top: 2em; }
+h2 { font-size: 1.1em; font-family: ui-monospace, monospace; }
+h2 span, .count { font-family: system-ui, sans-serif; font-weight: normal; color: #656d76; }
+table { width: 100%; border-collapse: collapse; table-layout: fixed; border: 1px solid #d0d7de; }
+td { font-family: ui-monospace, monospace; font-size: 0.85em; white-space: pre-wrap; word-break: break-all;
+     vertical-align: top; padding: 0 0.5em; }
+td.num { width: 4em; text-align: right; color: #656d76; user-select: none; }
+td.del { background: #ffebe9; }
+td.ins { background: #e6ffec; }
+td.del mark { background: #ffc1ba; }
+td.ins mark { background: #abf2bc; }
+tr.hunk td { background: #ddf4ff; color: #656d76; padding: 0.2em 0.5em; }
+";
+
+/// A file `remove` would change.
+pub struct FileChange {
+    pub path: PathBuf,
+    pub comments_removed: usize,
+    pub before: String,
+    pub after: String,
+}
+
+/// A standalone HTML page with the original and new text of every file side by side, for
+/// reviewing a cleanup without the tool. Everything is inline, so the page can be mailed or
+/// attached to a ticket as a single file.
+pub fn render(files: &[FileChange]) -> String {
+    let total: usize = files.iter().map(|file| file.comments_removed).sum();
+    let mut page = String::new();
+    page.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    page.push_str("<title>Proposed comment removal</title>\n");
+    let _ = writeln!(page, "<style>{}</style>\n</head>\n<body>", STYLE);
+    page.push_str("<h1>Proposed comment removal</h1>\n");
+    let _ = writeln!(
+        page,
+        "<p>{} comment(s) in {} file(s) would be removed. No file was modified.</p>",
+        total,
+        files.len()
+    );
+    let _ = writeln!(page, "<p class=\"count\">Generated by {} {}</p>", BIN_NAME, env!("CARGO_PKG_VERSION"));
+
+    if !files.is_empty() {
+        page.push_str("<nav>\n<ul>\n");
+        for (i, file) in files.iter().enumerate() {
+            let _ = writeln!(
+                page,
+                "<li><a href=\"#file-{}\">{}</a> <span class=\"count\">{} comment(s)</span></li>",
+                i,
+                escape(&patch_path(&file.path)),
+                file.comments_removed
+            );
+        }
+        page.push_str("</ul>\n</nav>\n");
+    }
+
+    for (i, file) in files.iter().enumerate() {
+        let _ = writeln!(
+            page,
+            "<section id=\"file-{}\">\n<h2>{} <span>{} comment(s)</span></h2>\n<table>",
+            i,
+            escape(&patch_path(&file.path)),
+            file.comments_removed
+        );
+        render_diff(&mut page, &file.before, &file.after);
+        page.push_str("</table>\n</section>\n");
+    }
+    page.push_str("</body>\n</html>\n");
+    page
+}
+
+fn render_diff(page: &mut String, before: &str, after: &str) {
+    let diff = TextDiff::from_lines(before, after);
+    let old: Vec<&str> = diff.iter_old_slices().collect();
+    let new: Vec<&str> = diff.iter_new_slices().collect();
+    for group in diff.grouped_ops(CONTEXT_LINES) {
+        let (Some(first), Some(last)) = (group.first(), group.last()) else {
+            continue;
+        };
+        let _ = writeln!(
+            page,
+            "<tr class=\"hunk\"><td colspan=\"4\">Lines {}-{}</td></tr>",
+            first.old_range().start + 1,
+            last.old_range().end
+        );
+        for op in &group {
+            let (tag, old_range, new_range) = op.as_tag_tuple();
+            match tag {
+                DiffTag::Equal => {
+                    for (o, n) in old_range.zip(new_range) {
+                        let text = escape(line(old[o]));
+                        row(page, Some((o, &text, "")), Some((n, &text, "")));
+                    }
+                }
+                DiffTag::Delete => {
+                    for o in old_range {
+                        row(page, Some((o, &escape(line(old[o])), "del")), None);
+                    }
+                }
+                DiffTag::Insert => {
+                    for n in new_range {
+                        row(page, None, Some((n, &escape(line(new[n])), "ins")));
+                    }
+                }
+                DiffTag::Replace => {
+                    let paired = old_range.len().max(new_range.len());
+                    for k in 0..paired {
+                        let o = Some(old_range.start + k).filter(|o| old_range.contains(o));
+                        let n = Some(new_range.start + k).filter(|n| new_range.contains(n));
+                        match (o, n) {
+                            (Some(o), Some(n)) => {
+                                let (left, right) = highlight(line(old[o]), line(new[n]));
+                                row(page, Some((o, &left, "del")), Some((n, &right, "ins")));
+                            }
+                            (Some(o), None) => row(page, Some((o, &escape(line(old[o])), "del")), None),
+                            (None, Some(n)) => row(page, None, Some((n, &escape(line(new[n])), "ins"))),
+                            (None, None) => {}
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// One table row; each side is the zero-based line number, the escaped text and its class.
+fn row(page: &mut String, left: Option<(usize, &str, &str)>, right: Option<(usize, &str, &str)>) {
+    page.push_str("<tr>");
+    for side in [left, right] {
+        match side {
+            Some((number, text, class)) => {
+                let _ = write!(page, "<td class=\"num\">{}</td><td class=\"{}\">{}</td>", number + 1, class, text);
+            }
+            None => page.push_str("<td class=\"num\"></td><td></td>"),
+        }
+    }
+    page.push_str("</tr>\n");
+}
+
+/// Both versions of a changed line with the characters that differ marked, e.g. the trailing
+/// comment that was cut from a line of code.
+fn highlight(old: &str, new: &str) -> (String, String) {
+    let diff = TextDiff::from_chars(old, new);
+    let old_chars: Vec<&str> = diff.iter_old_slices().collect();
+    let new_chars: Vec<&str> = diff.iter_new_slices().collect();
+    let (mut left, mut right) = (String::new(), String::new());
+    for op in diff.ops() {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        let old_text = escape(&old_chars[old_range].concat());
+        let new_text = escape(&new_chars[new_range].concat());
+        match tag {
+            DiffTag::Equal => {
+                left.push_str(&old_text);
+                right.push_str(&new_text);
+            }
+            _ => {
+                if !old_text.is_empty() {
+                    let _ = write!(left, "<mark>{}</mark>", old_text);
+                }
+                if !new_text.is_empty() {
+                    let _ = write!(right, "<mark>{}</mark>", new_text);
+                }
+            }
+        }
+    }
+    (left, right)
+}
+
+fn line(text: &str) -> &str {
+    text.trim_end_matches(['\n', '\r'])
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,102 @@
+use anyhow::{Context, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{atomic_write, cache::fingerprint, history, recording::normalize};
+
+const HEADER: &str = "# Comments the interactive prompt never asks about again: hash, file, first line\n";
+
+/// A comment answered with `i` at the prompt.
+pub struct Entry {
+    /// Hash of the comment text without its indentation
+    pub hash: String,
+    /// Path relative to the project, so runs from any directory agree
+    pub file: String,
+    pub preview: String,
+}
+
+/// The comments in `.comment-remover/ignore` that interactive runs keep without asking.
+pub struct IgnoreList {
+    path: PathBuf,
+    entries: Vec<Entry>,
+}
+
+impl IgnoreList {
+    /// Loads the list of the current project; a missing file is an empty list.
+    pub fn open() -> Result<Self> {
+        let path = history::state_dir()?.join("ignore");
+        let entries = match fs::read_to_string(&path) {
+            Ok(content) => content
+                .lines()
+                .filter(|line| !line.starts_with('#'))
+                .filter_map(|line| {
+                    let mut fields = line.splitn(3, '\t');
+                    Some(Entry {
+                        hash: fields.next()?.to_string(),
+                        file: fields.next()?.to_string(),
+                        preview: fields.next().unwrap_or_default().to_string(),
+                    })
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        Ok(IgnoreList { path, entries })
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn contains(&self, file: &Path, comment: &str) -> bool {
+        let file = self.key(file);
+        let hash = hash(comment);
+        self.entries.iter().any(|entry| entry.file == file && entry.hash == hash)
+    }
+
+    /// Adds `comment` in `file` and saves the list right away, so an interrupted run keeps it.
+    pub fn add(&mut self, file: &Path, comment: &str) -> Result<()> {
+        let preview = normalize(comment).lines().next().unwrap_or_default().chars().take(80).collect();
+        self.entries.push(Entry { hash: hash(comment), file: self.key(file), preview });
+        self.save()
+    }
+
+    /// Forgets every entry; returns how many there were.
+    pub fn clear(&mut self) -> Result<usize> {
+        let cleared = self.entries.len();
+        if self.path.exists() {
+            fs::remove_file(&self.path).with_context(|| format!("Failed to delete {}", self.path.display()))?;
+        }
+        self.entries.clear();
+        Ok(cleared)
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+        let mut content = HEADER.to_string();
+        for entry in &self.entries {
+            content.push_str(&format!("{}\t{}\t{}\n", entry.hash, entry.file, entry.preview));
+        }
+        atomic_write::write_atomic(&self.path, content.as_bytes())
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+
+    fn key(&self, file: &Path) -> String {
+        let project = self.path.parent().and_then(Path::parent).and_then(|project| fs::canonicalize(project).ok());
+        let relative = fs::canonicalize(file)
+            .ok()
+            .zip(project)
+            .and_then(|(file, project)| file.strip_prefix(&project).ok().map(Path::to_path_buf));
+        relative
+            .unwrap_or_else(|| file.to_path_buf())
+            .to_string_lossy()
+            .replace('\\', "/")
+    }
+}
+
+fn hash(comment: &str) -> String {
+    fingerprint(normalize(comment).as_bytes())
+}
@@ -0,0 +1,127 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::SyntaxRules;
+
+/// Longer lists of extensions and file names wrap onto further lines
+const MAX_EXTENSIONS_WIDTH: usize = 28;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum LanguagesFormat {
+    Table,
+    Json,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LanguageSummary {
+    pub key: String,
+    pub name: String,
+    pub extensions: Vec<String>,
+    pub filenames: Vec<String>,
+    pub single_line: Vec<String>,
+    pub multi_line: Vec<BlockMarkers>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BlockMarkers {
+    pub start: String,
+    pub end: String,
+}
+
+/// Summarises the loaded rules, sorted by display name.
+pub fn list(rules: &SyntaxRules) -> Vec<LanguageSummary> {
+    let mut languages: Vec<LanguageSummary> = rules
+        .languages
+        .iter()
+        .map(|(key, language)| LanguageSummary {
+            key: key.clone(),
+            name: language.name.clone(),
+            extensions: language.extensions.clone(),
+            filenames: language.filenames.clone(),
+            single_line: language.single_line.iter().map(|rule| rule.pattern.clone()).collect(),
+            multi_line: language
+                .multi_line
+                .iter()
+                .map(|rule| BlockMarkers { start: rule.start.clone(), end: rule.end.clone() })
+                .collect(),
+        })
+        .collect();
+    languages.sort_by_key(|language| language.name.to_lowercase());
+    languages
+}
+
+pub fn render(languages: &[LanguageSummary], format: LanguagesFormat) -> Result<String> {
+    match format {
+        LanguagesFormat::Json => Ok(serde_json::to_string_pretty(languages)? + "\n"),
+        LanguagesFormat::Table => {
+            let rows: Vec<[Vec<String>; 4]> = languages
+                .iter()
+                .map(|language| {
+                    let extensions: Vec<String> = language.extensions.iter().map(|ext| format!(".{}", ext)).collect();
+                    let extensions = [extensions.as_slice(), language.filenames.as_slice()].concat();
+                    let blocks: Vec<String> = language
+                        .multi_line
+                        .iter()
+                        .map(|block| format!("{} {}", block.start, block.end))
+                        .collect();
+                    [
+                        vec![language.name.clone()],
+                        wrap(&extensions, MAX_EXTENSIONS_WIDTH),
+                        vec![language.single_line.join(" ")],
+                        vec![blocks.join(", ")],
+                    ]
+                })
+                .collect();
+            let header = ["Language", "Extensions", "Line", "Block"].map(|title| vec![title.to_string()]);
+            // Every column but the last is as wide as its widest line
+            let mut widths = [0; 3];
+            for row in std::iter::once(&header).chain(&rows) {
+                for (width, cell) in widths.iter_mut().zip(row) {
+                    *width = cell.iter().map(|line| line.chars().count()).fold(*width, usize::max);
+                }
+            }
+
+            let mut output = String::new();
+            for row in std::iter::once(&header).chain(&rows) {
+                let height = row.iter().map(Vec::len).max().unwrap_or(1);
+                for index in 0..height {
+                    let cell = |column: usize| row[column].get(index).map_or("", String::as_str);
+                    let line = format!(
+                        "{:<name$} {:<extensions$} {:<line$} {}",
+                        cell(0),
+                        cell(1),
+                        cell(2),
+                        cell(3),
+                        name = widths[0],
+                        extensions = widths[1],
+                        line = widths[2]
+                    );
+                    output.push_str(line.trim_end());
+                    output.push('\n');
+                }
+            }
+            Ok(output)
+        }
+    }
+}
+
+/// `items` joined with commas into lines of at most `width` characters, unless one item alone
+/// is longer.
+fn wrap(items: &[String], width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        let item = if index + 1 < items.len() { format!("{},", item) } else { item.clone() };
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + 1 + item.chars().count() <= width => {
+                line.push(' ');
+                line.push_str(&item);
+            }
+            _ => lines.push(item),
+        }
+    }
+    if lines.is_empty() {
+        lines.push(String::new());
+    }
+    lines
+}
@@ -0,0 +1,2981 @@
+#[macro_use]
+mod output;
+
+mod add_language;
+mod age;
+mod annotations;
+mod archive;
+mod atomic_write;
+mod attached;
+mod c_family;
+mod backup;
+mod bench;
+mod budget;
+mod build_files;
+mod cache;
+mod config;
+mod convert;
+mod dead_code;
+mod directives;
+mod duplicates;
+mod encoding;
+mod exclude;
+mod extract;
+mod git;
+mod help;
+mod history;
+mod ignore;
+mod hook;
+mod html_report;
+mod languages;
+mod literals;
+mod lock;
+mod lsp;
+mod markdown;
+mod normalize;
+mod notebook;
+mod patch;
+mod paths;
+mod php;
+pub mod plugin;
+mod post_cmd;
+mod profile;
+mod python;
+mod readonly;
+mod recording;
+mod remote_rules;
+mod remove;
+pub mod result;
+mod review;
+mod run_log;
+mod rust;
+mod sarif;
+mod stats;
+mod syntax_tree;
+mod validate_rules;
+#[cfg(feature = "wasm")]
+mod wasm;
+mod watch;
+
+use anyhow::{Context, Result};
+use clap::{ArgAction, Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use colored::*;
+use indicatif::{ProgressBar, ProgressStyle};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    cmp::Reverse,
+    collections::{BTreeMap, HashSet},
+    env,
+    fs,
+    io::{self, IsTerminal, Write},
+    iter,
+    ops::Range,
+    path::{Path, PathBuf},
+    process,
+    sync::Arc,
+};
+
+use age::LineAges;
+use backup::BackupStrategy;
+use budget::CheckScope;
+use config::ProjectConfig;
+use convert::CommentStyle;
+use exclude::ExcludeSet;
+use extract::ExtractFormat;
+use bench::BenchFormat;
+use duplicates::DuplicatesFormat;
+use languages::LanguagesFormat;
+use output::{ErrorFormat, FileFailure};
+use patch::RemoveOutput;
+use plugin::{Action, CommentHandler, FileContext};
+use profile::Profile;
+use result::{CommentSpan, Outcome, Positions, RemovalResult};
+use stats::StatsFormat;
+
+/// Newest rules format this version reads; files without `"schema"` are schema 1, which
+/// schema 2 only extends with optional fields.
+const RULES_SCHEMA: u32 = 2;
+
+/// The syntax_rules.json shipped with this version, for embedding without a rules file
+const BUNDLED_RULES: &str = include_str!("../syntax_rules.json");
+
+/// What a rule's comments are, beyond being comments.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum RuleClass {
+    #[default]
+    Normal,
+    /// Documentation, the same as `"doc": true`
+    Doc,
+    /// Kept like a license header unless --keep-license-header false
+    License,
+    /// Kept like a linter/tool directive unless --strip-directives
+    Directive,
+}
+
+/// Whether a rule's marker counts inside string literals.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum InsideStrings {
+    /// A marker inside a string literal is part of the string
+    #[default]
+    Never,
+    /// The marker starts a comment even inside what looks like a string literal
+    Always,
+}
+
+#[derive(Debug, Deserialize)]
+struct SyntaxRule {
+    pattern: String,
+    description: String,
+    #[serde(default)]
+    doc: bool,
+    /// Text that makes the marker something else when it follows directly, e.g. `>` for the
+    /// Haskell operator `-->` or `[[` for a Lua block comment
+    #[serde(default)]
+    not_followed_by: Vec<String>,
+    /// The marker must not run into a word character, e.g. `REM` is not `REMOVE`
+    #[serde(default)]
+    word_boundary: bool,
+    /// Higher wins when rules could match at the same position, see `get_comment_patterns`
+    #[serde(default)]
+    priority: i32,
+    /// Inline regex flags for the rule, e.g. `i` for a marker in any case
+    #[serde(default)]
+    flags: String,
+    /// Only a comment that is the first thing on its line counts; without it a trailing
+    /// comment after code is found too
+    #[serde(default = "default_true")]
+    must_start_line: bool,
+    #[serde(default)]
+    inside_strings: InsideStrings,
+    #[serde(default)]
+    class: RuleClass,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl SyntaxRule {
+    /// The rest of `text` if it starts with this rule's marker in a context where it begins
+    /// a comment.
+    fn strip_marker<'t>(&self, text: &'t str) -> Option<&'t str> {
+        let rest = if self.flags.contains('i') {
+            let marker = text.get(..self.pattern.len()).filter(|marker| marker.eq_ignore_ascii_case(&self.pattern))?;
+            &text[marker.len()..]
+        } else {
+            text.strip_prefix(&self.pattern)?
+        };
+        let guarded = self.not_followed_by.iter().any(|text| !text.is_empty() && rest.starts_with(text.as_str()))
+            || (self.word_boundary && rest.starts_with(|c: char| c.is_alphanumeric() || c == '_'));
+        (!guarded).then_some(rest)
+    }
+
+    fn class(&self) -> RuleClass {
+        if self.doc { RuleClass::Doc } else { self.class }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct MultiLineRule {
+    start: String,
+    end: String,
+    description: String,
+    #[serde(default)]
+    doc: bool,
+    /// Both delimiters only count at the start of a line, like Ruby's `=begin`/`=end` or Perl's
+    /// POD, and the comment runs to the end of the closing line
+    #[serde(default)]
+    line_start: bool,
+    /// Higher wins when rules could match at the same position, see `get_comment_patterns`
+    #[serde(default)]
+    priority: i32,
+    /// Inline regex flags for the rule, e.g. `i` for delimiters in any case
+    #[serde(default)]
+    flags: String,
+    /// The start delimiter must be the first thing on its line, after indentation
+    #[serde(default)]
+    must_start_line: bool,
+    /// Comments inside the comment open a level of their own, as in Kotlin, Swift and Scala,
+    /// so it ends at the delimiter closing the outermost one
+    #[serde(default)]
+    nested: bool,
+    #[serde(default)]
+    inside_strings: InsideStrings,
+    #[serde(default)]
+    class: RuleClass,
+}
+
+impl MultiLineRule {
+    fn class(&self) -> RuleClass {
+        if self.doc { RuleClass::Doc } else { self.class }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct LanguageRules {
+    name: String,
+    extensions: Vec<String>,
+    /// File names recognised whatever their extension, like `Makefile`; a trailing `*` matches
+    /// any ending, as in `Dockerfile.*`
+    #[serde(default)]
+    filenames: Vec<String>,
+    single_line: Vec<SyntaxRule>,
+    multi_line: Vec<MultiLineRule>,
+    /// Keywords that typically start a statement, used to recognise commented-out code
+    #[serde(default)]
+    keywords: Vec<String>,
+    /// Words that start a function, type or other declaration, used by `--keep-attached`
+    #[serde(default)]
+    declarations: Vec<String>,
+    /// Higher wins when several languages claim the same extension or file name, see
+    /// `detect_file_type`
+    #[serde(default)]
+    priority: i32,
+}
+
+#[derive(Debug, Deserialize)]
+struct SyntaxRules {
+    /// Format version of the file, see `RULES_SCHEMA`
+    #[serde(default = "default_schema")]
+    schema: u32,
+    /// By key; ordered so every lookup that scans them sees the same order on each run
+    #[serde(flatten)]
+    languages: BTreeMap<String, LanguageRules>,
+    /// Suffixes the project config maps to a language key, checked before the rules
+    #[serde(skip)]
+    suffix_overrides: Vec<(String, String)>,
+}
+
+fn default_schema() -> u32 {
+    1
+}
+
+impl SyntaxRules {
+    /// Applies the project's `extensions` remapping, rejecting languages the rules do not have.
+    fn apply_config(&mut self, config: &ProjectConfig) -> Result<()> {
+        let mut overrides = Vec::new();
+        for (suffix, name) in &config.extensions {
+            let (key, _) = find_language_entry(name, self)?;
+            overrides.push((suffix.trim_start_matches('.').to_string(), key.clone()));
+        }
+        self.suffix_overrides = overrides;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommentKind {
+    Line,
+    Block,
+    Doc,
+}
+
+/// Every comment rule of a language combined into one regex, one capture group per rule,
+/// so a file is scanned once and comments come out in document order.
+struct CommentPatterns {
+    regex: Regex,
+    /// Kind of comment matched by each capture group, in rule order
+    kinds: Vec<CommentKind>,
+    /// Whether each capture group's rule also matches inside string literals
+    inside_strings: Vec<bool>,
+    /// The delimiters nesting levels are counted by for each capture group's rule, if it nests
+    nested: Vec<Option<(String, String)>>,
+}
+
+struct Comment {
+    kind: CommentKind,
+    range: Range<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Engine {
+    /// Language-agnostic regex patterns built from syntax_rules.json
+    Regex,
+    /// Real grammars where available (requires the `tree-sitter` feature)
+    TreeSitter,
+}
+
+/// What takes the place of a removed comment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum CommentAction {
+    /// Delete it
+    Remove,
+    /// Replace it with blank lines, the same as --preserve-lines
+    Blank,
+    /// Reduce a block comment spanning several lines to a one-line stub with its first
+    /// sentence, e.g. `/* Parses the header. */` or `/* … */`; other comments are deleted
+    Collapse,
+    /// Keep only the annotation lines of a block comment, such as `@param`, `@ts-expect-error`
+    /// or `SPDX-License-Identifier:`, inside its delimiters; other comments are deleted
+    Annotations,
+}
+
+/// Report of the comments `remove` found, printed to stdout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ReportFormat {
+    /// SARIF 2.1.0 for GitHub code scanning and other static analysis consumers
+    Sarif,
+    /// A standalone page with side-by-side diffs of every proposed change; no file is modified
+    Html,
+}
+
+#[derive(Args, Clone)]
+struct RemoveArgs {
+    /// Automatic mode (remove all comments without asking)
+    #[arg(short, long)]
+    auto: bool,
+
+    /// Force mode (overwrite without backup, same as --backup-strategy none)
+    #[arg(short, long)]
+    force: bool,
+
+    /// Write read-only files (and files in read-only directories) too, making them writable
+    /// for the write and restoring their permissions afterwards; they are skipped otherwise.
+    /// Files owned by another user or on a read-only mount are always skipped
+    #[arg(long)]
+    force_readonly: bool,
+
+    /// Save the answers given to the prompts to this file, for --replay
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["auto", "check"])]
+    record: Option<String>,
+
+    /// Answer the prompts from a file saved with --record; comments it has no answer for are
+    /// still asked about
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["auto", "check"])]
+    replay: Option<String>,
+
+    /// Follow symlinks to files and directories when walking directories; they are skipped
+    /// otherwise
+    #[arg(long)]
+    follow_symlinks: bool,
+
+    /// When a file to modify is a symlink, write the file it points to instead of replacing
+    /// the link with a regular file
+    #[arg(long)]
+    dereference: bool,
+
+    /// How backups are named before a file is modified
+    #[arg(long, value_enum, default_value_t = BackupStrategy::Suffix)]
+    backup_strategy: BackupStrategy,
+
+    /// Write backups to this directory, mirroring the source tree, instead of next to the files
+    #[arg(long, value_name = "DIR")]
+    backup_dir: Option<String>,
+
+    /// Write the cleaned files to this directory, mirroring the source tree, and leave the
+    /// originals alone; files without removable comments are copied as they are
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["check", "output", "report"])]
+    out_dir: Option<String>,
+
+    /// Lines of code shown above and below each comment when asking about it
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    context: usize,
+
+    #[command(flatten)]
+    strip: StripArgs,
+
+    /// Verbose mode (show detailed information)
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Character encoding of the file (e.g. latin1, shift_jis); defaults to the BOM or UTF-8
+    #[arg(long)]
+    encoding: Option<String>,
+
+    /// Only report files with removable comments, exiting with status 1 if there are any
+    #[arg(long)]
+    check: bool,
+
+    /// Only remove comments git blame shows were last changed longer ago than this, e.g. 180d,
+    /// 12w or 1y; uncommitted and untracked lines count as new
+    #[arg(long, value_name = "AGE", value_parser = age::parse_age)]
+    older_than: Option<u64>,
+
+    /// Modify files in place, or print a patch of the removals for `git apply` and leave them alone
+    #[arg(long, value_enum, default_value_t = RemoveOutput::Files, conflicts_with = "check")]
+    output: RemoveOutput,
+
+    /// Print a report of the removed comments (removable ones with --check) to stdout;
+    /// `html` only proposes the changes and leaves the files alone
+    #[arg(long, value_enum, value_name = "FORMAT", conflicts_with = "output")]
+    report: Option<ReportFormat>,
+
+    /// Run this shell command after each modified file is written, e.g. "rustfmt {file}";
+    /// the file is restored if it fails
+    #[arg(long, value_name = "COMMAND")]
+    post_cmd: Option<String>,
+
+    /// Run this shell command after each modified file is written and post-processed, e.g.
+    /// "python -m py_compile {file}" or "cargo check"; the file is restored and reported as
+    /// failed if it exits non-zero
+    #[arg(long, value_name = "COMMAND")]
+    verify_cmd: Option<String>,
+
+    /// Re-check every file instead of skipping ones found to have nothing to remove on an earlier run
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Do not record the modification in .comment-remover/history, so `undo` cannot roll it back
+    #[arg(long)]
+    no_history: bool,
+
+    /// Also process Markdown files (.md, .mdx), stripping comments inside fenced code blocks
+    #[arg(long)]
+    markdown: bool,
+
+    /// Skip files larger than this, e.g. 512K, 10M or 1G; 0 disables the limit
+    #[arg(long, value_name = "SIZE", default_value = "10M", value_parser = parse_size)]
+    max_size: u64,
+}
+
+/// The `remove` options `bench` times with; the rest only matter for real files.
+#[derive(Args)]
+struct BenchArgs {
+    /// Comment detection engine; tree-sitter falls back to regex for languages without a grammar
+    #[arg(long, value_enum, default_value_t = Engine::Regex)]
+    engine: Engine,
+
+    /// Preset for what is kept
+    #[arg(long, value_enum)]
+    profile: Option<Profile>,
+}
+
+/// The `remove` options that decide what is stripped from a text, shared by the commands and the
+/// embedding API that strip without modifying files.
+#[derive(Args, Clone)]
+struct StripArgs {
+    /// Fail on a file where comments overlap instead of keeping the one that starts first. The
+    /// scan itself reads `/* // */` as one comment, so only language handling that widens a
+    /// comment into the next can cause an overlap
+    #[arg(long)]
+    strict: bool,
+
+    /// Preset for what is kept; individual options and the project config override it
+    #[arg(long, value_enum)]
+    profile: Option<Profile>,
+
+    /// Keep the leading comment block if it looks like a license header (default: true,
+    /// false with --profile aggressive)
+    #[arg(long, value_name = "BOOL", action = ArgAction::Set)]
+    keep_license_header: Option<bool>,
+
+    /// Regex used to recognise a license header
+    #[arg(long, default_value = DEFAULT_LICENSE_PATTERN)]
+    license_pattern: String,
+
+    /// Comment detection engine; tree-sitter falls back to regex for languages without a grammar
+    #[arg(long, value_enum, default_value_t = Engine::Regex)]
+    engine: Engine,
+
+    /// Treat every file as this language (rules key or name, e.g. `rust` or `C++`) instead of detecting it
+    #[arg(long, value_name = "LANGUAGE")]
+    lang: Option<String>,
+
+    /// Only remove comments that look like commented-out code, keeping prose
+    #[arg(long)]
+    only_dead_code: bool,
+
+    /// Replace removed comments with blank lines (or spaces when code follows) so line numbers stay the same
+    #[arg(long)]
+    preserve_lines: bool,
+
+    /// What to do with a comment that is removed
+    #[arg(long, value_enum, default_value_t = CommentAction::Remove)]
+    action: CommentAction,
+
+    /// Also remove Python docstrings, which are kept by default
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    strip_docstrings: Option<bool>,
+
+    /// Also remove Rust crate and module documentation (`//!`, `/*! */`), which is kept by default
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    strip_inner_docs: Option<bool>,
+
+    /// Also remove comments inside Rust `macro_rules!` bodies, which are left alone by default
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    strip_macros: Option<bool>,
+
+    /// Also remove linter and tool directives such as `eslint-disable` or `# noqa`, which are kept by default
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    strip_directives: Option<bool>,
+
+    /// Also remove `#if 0` blocks in C and C++, keeping the `#else` branch if there is one; their
+    /// lines go entirely unless --preserve-lines
+    #[arg(long)]
+    strip_if0: bool,
+
+    /// Keep comments directly above a function, type or other declaration
+    #[arg(long)]
+    keep_attached: bool,
+
+    /// Only remove comments matching this regex (repeatable)
+    #[arg(long = "match", value_name = "REGEX")]
+    match_patterns: Vec<String>,
+
+    /// Never remove comments matching this regex (repeatable)
+    #[arg(long = "keep", value_name = "REGEX")]
+    keep_patterns: Vec<String>,
+}
+
+#[derive(Parser)]
+#[command(author, version, about = "A tool to remove comments from source code files", long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+
+    /// Quiet mode (only print errors and the command's output, no progress bar)
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Use the syntax rules at this URL, e.g. a team's shared rules.json; the download is
+    /// cached in .comment-remover/rules and used when offline
+    #[arg(long, global = true, value_name = "URL")]
+    rules_url: Option<String>,
+
+    /// How errors are printed on stderr
+    #[arg(long, global = true, value_enum, value_name = "FORMAT", default_value_t = ErrorFormat::Text)]
+    error_format: ErrorFormat,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Remove comments from source files
+    Remove {
+        /// Paths to source files or directories
+        #[arg(required_unless_present_any = ["staged", "changed", "resume"])]
+        files: Vec<String>,
+
+        /// Only process files staged in git
+        #[arg(long, conflicts_with = "changed")]
+        staged: bool,
+
+        /// Only process files changed in git relative to REF (e.g. main or HEAD~1)
+        #[arg(long, value_name = "REF")]
+        changed: Option<String>,
+
+        /// Skip paths matching this glob (repeatable), e.g. `vendor` or `**/*.min.js`
+        #[arg(long, value_name = "GLOB")]
+        exclude: Vec<String>,
+
+        /// Rewrite .zip and .tar(.gz) archives given as paths instead of writing a `.stripped`
+        /// copy next to them
+        #[arg(long, conflicts_with = "out_dir")]
+        in_place: bool,
+
+        /// Continue the last run that modifies files where it stopped, e.g. after a crash or
+        /// Ctrl-C, with the options it was started with; files it finished are not redone
+        #[arg(long, exclusive = true)]
+        resume: bool,
+
+        #[command(flatten)]
+        args: RemoveArgs,
+    },
+    /// Write the comments of a source file to stdout or a sidecar file
+    Extract {
+        /// Path to the source file
+        file: String,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = ExtractFormat::Markdown)]
+        format: ExtractFormat,
+
+        /// Write to this file instead of stdout
+        #[arg(short, long, conflicts_with = "sidecar")]
+        output: Option<String>,
+
+        /// Write next to the source file as <file>.comments.<md|json>
+        #[arg(long)]
+        sidecar: bool,
+
+        /// Character encoding of the file (e.g. latin1, shift_jis); defaults to the BOM or UTF-8
+        #[arg(long)]
+        encoding: Option<String>,
+
+        /// Comment detection engine; tree-sitter falls back to regex for languages without a grammar
+        #[arg(long, value_enum, default_value_t = Engine::Regex)]
+        engine: Engine,
+
+        /// Treat the file as this language (rules key or name, e.g. `rust` or `C++`) instead of detecting it
+        #[arg(long, value_name = "LANGUAGE")]
+        lang: Option<String>,
+    },
+    /// Write a file without its comments to stdout, leaving the file alone, e.g. to page
+    /// through it or compare it with the original
+    Print {
+        /// Path to the source file
+        file: String,
+
+        /// Character encoding of the file (e.g. latin1, shift_jis); defaults to the BOM or UTF-8
+        #[arg(long)]
+        encoding: Option<String>,
+
+        /// Refuse a file larger than this, e.g. 512K, 10M or 1G; 0 disables the limit
+        #[arg(long, value_name = "SIZE", default_value = "10M", value_parser = parse_size)]
+        max_size: u64,
+
+        /// Strip the fenced code blocks of a Markdown file (.md, .mdx)
+        #[arg(long)]
+        markdown: bool,
+
+        #[command(flatten)]
+        args: StripArgs,
+    },
+    /// Rewrite the comments of a file between line and block style
+    Convert {
+        /// Path to the source file
+        file: String,
+
+        /// Comment style to convert to
+        #[arg(long, value_enum)]
+        to: CommentStyle,
+
+        /// Print the result to stdout instead of modifying the file
+        #[arg(long)]
+        stdout: bool,
+
+        /// Skip creating a backup file before modifying the file
+        #[arg(short, long)]
+        force: bool,
+
+        /// Character encoding of the file (e.g. latin1, shift_jis); defaults to the BOM or UTF-8
+        #[arg(long)]
+        encoding: Option<String>,
+
+        /// Treat the file as this language (rules key or name, e.g. `rust` or `C++`) instead of detecting it
+        #[arg(long, value_name = "LANGUAGE")]
+        lang: Option<String>,
+    },
+    /// Tidy comments without removing them: the space after the marker, one-line block
+    /// comments, trailing whitespace and banners, as `normalize` in the config file says
+    Normalize {
+        /// Paths to source files or directories
+        #[arg(required = true)]
+        files: Vec<String>,
+
+        /// Only report files with comments to tidy, exiting with status 1 if there are any
+        #[arg(long)]
+        check: bool,
+
+        /// Skip creating backup files before modifying files
+        #[arg(short, long)]
+        force: bool,
+
+        /// Skip paths matching this glob (repeatable), e.g. `vendor` or `**/*.min.js`
+        #[arg(long, value_name = "GLOB")]
+        exclude: Vec<String>,
+
+        /// Character encoding of the files (e.g. latin1, shift_jis); defaults to the BOM or UTF-8
+        #[arg(long)]
+        encoding: Option<String>,
+    },
+    /// Show comment density and a breakdown per language for a file or directory
+    Stats {
+        /// File or directory to analyse
+        path: String,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = StatsFormat::Table)]
+        format: StatsFormat,
+
+        /// Also list every file in the table output
+        #[arg(long)]
+        files: bool,
+
+        /// Character encoding of the file (e.g. latin1, shift_jis); defaults to the BOM or UTF-8
+        #[arg(long)]
+        encoding: Option<String>,
+
+        /// Comment detection engine; tree-sitter falls back to regex for languages without a grammar
+        #[arg(long, value_enum, default_value_t = Engine::Regex)]
+        engine: Engine,
+
+        /// Skip files larger than this, e.g. 512K, 10M or 1G; 0 disables the limit
+        #[arg(long, value_name = "SIZE", default_value = "10M", value_parser = parse_size)]
+        max_size: u64,
+    },
+    /// Fail when files have a larger share of comment lines than allowed
+    Check {
+        /// File or directory to check
+        #[arg(default_value = ".")]
+        path: String,
+
+        /// Highest share of comment lines among non-blank lines, e.g. 0.25; `max_comment_ratio`
+        /// in the config file sets it per language
+        #[arg(long, value_name = "RATIO", value_parser = parse_ratio)]
+        max_comment_ratio: Option<f64>,
+
+        /// Whether each file or each directory has to stay within the limit
+        #[arg(long, value_enum, default_value_t = CheckScope::File)]
+        by: CheckScope,
+
+        /// Character encoding of the file (e.g. latin1, shift_jis); defaults to the BOM or UTF-8
+        #[arg(long)]
+        encoding: Option<String>,
+
+        /// Comment detection engine; tree-sitter falls back to regex for languages without a grammar
+        #[arg(long, value_enum, default_value_t = Engine::Regex)]
+        engine: Engine,
+
+        /// Skip files larger than this, e.g. 512K, 10M or 1G; 0 disables the limit
+        #[arg(long, value_name = "SIZE", default_value = "10M", value_parser = parse_size)]
+        max_size: u64,
+    },
+    /// Go through the removable comments in a full-screen list and remove the chosen ones
+    Review {
+        /// Paths to source files or directories
+        #[arg(required = true)]
+        files: Vec<String>,
+
+        /// Skip paths matching this glob (repeatable), e.g. `vendor` or `**/*.min.js`
+        #[arg(long, value_name = "GLOB")]
+        exclude: Vec<String>,
+
+        #[command(flatten)]
+        args: RemoveArgs,
+    },
+    /// Watch a directory and strip comments from files whenever they change (always automatic)
+    Watch {
+        /// Directory to watch recursively
+        dir: String,
+
+        /// Skip paths matching this glob (repeatable), e.g. `vendor` or `**/*.min.js`
+        #[arg(long, value_name = "GLOB")]
+        exclude: Vec<String>,
+
+        #[command(flatten)]
+        args: RemoveArgs,
+    },
+    /// Run a server that lets editors call the engine, honouring the `remove` options
+    Serve {
+        /// Speak the Language Server Protocol on stdin/stdout, offering code actions to remove
+        /// a comment or all of them and a formatter that removes them
+        #[arg(long, required = true)]
+        lsp: bool,
+
+        #[command(flatten)]
+        args: RemoveArgs,
+    },
+    /// List old backups, or delete them with --delete, keeping the newest ones of each file
+    CleanBackups {
+        /// Directory to search for backups (the backup directory when --backup-dir was used)
+        #[arg(default_value = ".")]
+        path: String,
+
+        /// Number of backups to keep per file; required with --delete
+        #[arg(long)]
+        keep: Option<usize>,
+
+        /// Delete the listed backups instead of only listing them
+        #[arg(long, requires = "keep")]
+        delete: bool,
+    },
+    /// Roll back the last recorded modifications of a file, even across several runs
+    Undo {
+        /// Path to the modified file
+        file: String,
+
+        /// Number of modifications to roll back
+        #[arg(long, default_value_t = 1)]
+        steps: usize,
+
+        /// List the recorded modifications instead of undoing any
+        #[arg(long)]
+        list: bool,
+    },
+    /// List the languages in the loaded syntax rules and their comment markers
+    Languages {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = LanguagesFormat::Table)]
+        format: LanguagesFormat,
+    },
+    /// Check a syntax rules file for mistakes
+    ValidateRules {
+        /// Rules file to check (defaults to the syntax_rules.json in use)
+        path: Option<String>,
+    },
+    /// Interactively add a language to the syntax rules file
+    AddLanguage {
+        /// Rules file to extend (defaults to the syntax_rules.json in use)
+        #[arg(long, value_name = "FILE")]
+        rules: Option<String>,
+    },
+    /// Install a git pre-commit hook that rejects commits containing removable comments
+    InstallHook {
+        /// Overwrite an existing pre-commit hook
+        #[arg(long)]
+        force: bool,
+
+        /// Print a .pre-commit-hooks.yaml entry for the pre-commit framework instead
+        #[arg(long)]
+        pre_commit_config: bool,
+    },
+    /// Print a shell completion script
+    ///
+    /// For example `comment_remover completions bash > /etc/bash_completion.d/comment_remover`
+    Completions {
+        /// Shell to generate completions for
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Look for patterns across the comments of a tree
+    Analyze {
+        #[command(subcommand)]
+        action: AnalyzeAction,
+    },
+    /// Manage the comments interactive runs were told never to ask about again
+    Ignore {
+        #[command(subcommand)]
+        action: IgnoreAction,
+    },
+    /// Time comment removal on generated corpora of several sizes and language mixes
+    Bench {
+        /// Size of the largest corpus of each mix in bytes; the others are 4 and 16 times smaller
+        #[arg(long, default_value_t = 4_000_000)]
+        size: usize,
+
+        /// Runs per corpus, of which the fastest counts
+        #[arg(long, default_value_t = 3)]
+        runs: usize,
+
+        /// Fail when the time per byte grows more than this many times from the smallest corpus
+        /// to the largest, the sign of work growing faster than the input
+        #[arg(long, value_name = "FACTOR")]
+        max_slowdown: Option<f64>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = BenchFormat::Table)]
+        format: BenchFormat,
+
+        #[command(flatten)]
+        args: BenchArgs,
+    },
+    /// Display detailed information about the tool
+    Info {
+        /// Print a roff man page instead, e.g. for packaging
+        #[arg(long)]
+        generate_man: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum AnalyzeAction {
+    /// Report comments found in several places, like copy-pasted banners; with --auto every
+    /// copy but the first is removed
+    Duplicates {
+        /// Paths to source files or directories
+        #[arg(required = true)]
+        files: Vec<String>,
+
+        /// Skip paths matching this glob (repeatable), e.g. `vendor` or `**/*.min.js`
+        #[arg(long, value_name = "GLOB")]
+        exclude: Vec<String>,
+
+        /// How alike copies must be, by the runs of words they share; 1 only finds comments
+        /// with the same words
+        #[arg(long, value_name = "RATIO", default_value_t = 0.9, value_parser = parse_ratio)]
+        similarity: f64,
+
+        /// Leave out comments with fewer words, which repeat without being copies
+        #[arg(long, default_value_t = 5)]
+        min_words: usize,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = DuplicatesFormat::Text)]
+        format: DuplicatesFormat,
+
+        #[command(flatten)]
+        args: RemoveArgs,
+    },
+}
+
+#[derive(Subcommand)]
+enum IgnoreAction {
+    /// List the ignored comments
+    List,
+    /// Forget all ignored comments, so they are asked about again
+    Clear,
+}
+
+#[derive(Debug, thiserror::Error)]
+enum Error {
+    #[error("Unsupported file type: {0}")]
+    UnsupportedFileType(String),
+    #[error("Failed to load syntax rules: {0}")]
+    SyntaxRules(String),
+    #[error("Invalid pattern: {0}")]
+    InvalidPattern(String),
+    #[error("Unknown encoding: {0}")]
+    UnknownEncoding(String),
+    #[error("File is not valid {0}, use --encoding to specify its encoding")]
+    Decode(String),
+    #[error("Content cannot be represented in {0}")]
+    Encode(String),
+    #[error("The {0} engine is not available, rebuild with `--features {0}`")]
+    EngineUnavailable(String),
+    #[cfg_attr(not(feature = "tree-sitter"), allow(dead_code))]
+    #[error("Syntax tree error: {0}")]
+    SyntaxTree(String),
+    #[error("Git error: {0}")]
+    Git(String),
+    #[error("Invalid language definition: {0}")]
+    InvalidLanguage(String),
+    #[error("{0} has no {1} comment syntax to convert to")]
+    UnsupportedConversion(String, String),
+    #[error("Not a text file")]
+    BinaryFile,
+    #[error("File is {0} bytes, larger than --max-size {1}")]
+    FileTooLarge(u64, u64),
+    #[error("Invalid notebook: {0}")]
+    Notebook(String),
+    #[error("Invalid config file {0}")]
+    Config(String),
+    #[error("A pre-commit hook already exists at {0}, use --force to replace it")]
+    HookExists(String),
+    #[error("The file or its directory is read-only, use --force-readonly to write it anyway")]
+    ReadOnly,
+    #[error("Not writable: {0}")]
+    NotWritable(&'static str),
+    #[error("{0} is being processed by another run")]
+    Locked(String),
+    #[error("{0} was changed by something else while comments were being removed, nothing was written")]
+    ConcurrentModification(String),
+    #[error("Unknown language '{0}', expected one of: {1}")]
+    UnknownLanguage(String, String),
+    #[error("Post command failed: {0}")]
+    PostCommand(String),
+    #[error("Verification failed: {0}")]
+    Verification(String),
+    #[error("Invalid options: {0}")]
+    InvalidOptions(String),
+    #[error("Comments overlap, not removing any with --strict: {0}")]
+    OverlappingComments(String),
+    #[error("The review needs an interactive terminal")]
+    NotATerminal,
+    #[error("Cannot undo {1} step(s) of {0}, only {2} recorded")]
+    NotEnoughHistory(String, usize, usize),
+    #[error("Performance regression: {0}")]
+    Regression(String),
+    #[error("Two files would be written to the same place: {0}")]
+    PathConflict(String),
+}
+
+const KEEP_DIRECTIVE: &str = "comment-remover:keep";
+const OFF_DIRECTIVE: &str = "comment-remover:off";
+const ON_DIRECTIVE: &str = "comment-remover:on";
+
+/// Exit status of `remove --check` and `normalize --check` when something would change, and of
+/// `check` when something is over its comment budget.
+const EXIT_COMMENTS_FOUND: i32 = 1;
+/// Exit status for invalid arguments, options or config, as for the usage errors clap reports.
+const EXIT_USAGE: i32 = 2;
+/// Exit status when some files could not be processed, the others still are, or the command
+/// failed as a whole.
+const EXIT_FILE_ERRORS: i32 = 3;
+/// Exit status when the syntax rules cannot be loaded or are invalid.
+const EXIT_RULES_ERROR: i32 = 4;
+
+impl Error {
+    /// Stable name of the error for `--error-format json`, so tools need not match messages.
+    fn code(&self) -> &'static str {
+        match self {
+            Error::UnsupportedFileType(_) => "unsupported_file_type",
+            Error::SyntaxRules(_) => "rules",
+            Error::InvalidPattern(_) => "invalid_pattern",
+            Error::UnknownEncoding(_) => "unknown_encoding",
+            Error::Decode(_) => "decode",
+            Error::Encode(_) => "encode",
+            Error::EngineUnavailable(_) => "engine_unavailable",
+            Error::SyntaxTree(_) => "syntax_tree",
+            Error::Git(_) => "git",
+            Error::InvalidLanguage(_) => "invalid_language",
+            Error::UnsupportedConversion(..) => "unsupported_conversion",
+            Error::BinaryFile => "binary_file",
+            Error::FileTooLarge(..) => "file_too_large",
+            Error::Notebook(_) => "notebook",
+            Error::Config(_) => "config",
+            Error::HookExists(_) => "hook_exists",
+            Error::ReadOnly => "read_only",
+            Error::NotWritable(_) => "not_writable",
+            Error::Locked(_) => "locked",
+            Error::ConcurrentModification(_) => "concurrent_modification",
+            Error::UnknownLanguage(..) => "unknown_language",
+            Error::PostCommand(_) => "post_command",
+            Error::Verification(_) => "verification",
+            Error::InvalidOptions(_) => "usage",
+            Error::OverlappingComments(_) => "overlapping_comments",
+            Error::NotATerminal => "not_a_terminal",
+            Error::NotEnoughHistory(..) => "not_enough_history",
+            Error::Regression(_) => "regression",
+            Error::PathConflict(_) => "path_conflict",
+        }
+    }
+}
+
+/// The code of the `Error` behind `error`; `io` for failed reads and writes, `error` otherwise.
+fn error_code(error: &anyhow::Error) -> &'static str {
+    match error.chain().find_map(|cause| cause.downcast_ref::<Error>()) {
+        Some(error) => error.code(),
+        None if error.chain().any(|cause| cause.is::<io::Error>()) => "io",
+        None => "error",
+    }
+}
+
+fn exit_code(error: &anyhow::Error) -> i32 {
+    match error.chain().find_map(|cause| cause.downcast_ref::<Error>()) {
+        Some(Error::SyntaxRules(_) | Error::InvalidLanguage(_)) => EXIT_RULES_ERROR,
+        Some(
+            Error::InvalidOptions(_)
+            | Error::InvalidPattern(_)
+            | Error::UnknownEncoding(_)
+            | Error::UnknownLanguage(..)
+            | Error::EngineUnavailable(_)
+            | Error::Config(_),
+        ) => EXIT_USAGE,
+        _ => EXIT_FILE_ERRORS,
+    }
+}
+
+/// A clap parse error as an `Error`, by the first line of its message.
+fn usage_error(error: clap::Error) -> Error {
+    let message = error.to_string();
+    let first_line = message.lines().next().unwrap_or_default();
+    Error::InvalidOptions(first_line.trim_start_matches("error: ").to_string())
+}
+
+/// Prints the error that stopped `run` and returns the exit status for it: 2 for usage
+/// errors, 3 when files could not be processed and 4 for broken syntax rules.
+pub fn report_error(error: &anyhow::Error) -> i32 {
+    output::fatal_error(error)
+}
+
+const DEFAULT_LICENSE_PATTERN: &str =
+    r"(?i)(copyright|licen[cs]e|spdx-license-identifier|all rights reserved)";
+
+/// Parses a share between 0 and 1.
+fn parse_ratio(value: &str) -> std::result::Result<f64, String> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|ratio| (0.0..=1.0).contains(ratio))
+        .ok_or_else(|| format!("'{}' is not a ratio between 0 and 1, e.g. 0.25", value))
+}
+
+/// Parses a byte count with an optional K, M or G suffix (powers of 1024).
+fn parse_size(value: &str) -> std::result::Result<u64, String> {
+    let value = value.trim();
+    let (number, multiplier) = match value.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&value[..i], 1 << 10),
+        Some((i, 'm' | 'M')) => (&value[..i], 1 << 20),
+        Some((i, 'g' | 'G')) => (&value[..i], 1 << 30),
+        _ => (value, 1),
+    };
+    number
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(multiplier))
+        .ok_or_else(|| format!("'{}' is not a size like 512K, 10M or 1G", value))
+}
+
+fn find_rules_file() -> Result<PathBuf> {
+    // Get the directory where the executable is located
+    let exe_path = env::current_exe()?;
+    let exe_dir = exe_path.parent()
+        .ok_or_else(|| Error::SyntaxRules("Could not get executable directory".to_string()))?;
+    
+    // Try to find syntax_rules.json in the executable directory
+    let rules_path = exe_dir.join("syntax_rules.json");
+    if rules_path.exists() {
+        return Ok(rules_path);
+    }
+
+    // If not found in executable directory, try the current directory
+    let current_dir = env::current_dir()?;
+    let current_rules_path = current_dir.join("syntax_rules.json");
+    if current_rules_path.exists() {
+        return Ok(current_rules_path);
+    }
+
+    Err(Error::SyntaxRules(
+        format!("Could not find syntax_rules.json in {} or {}", 
+            rules_path.display(), 
+            current_rules_path.display())
+    ).into())
+}
+
+/// The rules file to use: the cached download of `rules_url` if there is one, otherwise the
+/// local syntax_rules.json.
+fn rules_file(rules_url: Option<&str>) -> Result<PathBuf> {
+    match rules_url {
+        Some(url) => remote_rules::fetch(url),
+        None => find_rules_file(),
+    }
+}
+
+fn load_syntax_rules(rules_url: Option<&str>) -> Result<SyntaxRules> {
+    let rules_path = rules_file(rules_url)?;
+    let rules_content = fs::read_to_string(&rules_path)
+        .with_context(|| format!("Failed to read syntax rules from {}", rules_path.display()))?;
+    parse_syntax_rules(&rules_content, &rules_path.display().to_string())
+}
+
+/// Syntax rules read from `origin`, refusing a schema newer than this version reads.
+fn parse_syntax_rules(content: &str, origin: &str) -> Result<SyntaxRules> {
+    let rules: SyntaxRules = serde_json::from_str(content)
+        .map_err(|e| Error::SyntaxRules(format!("{} in {} (run `validate-rules` for details)", e, origin)))?;
+    if rules.schema > RULES_SCHEMA {
+        return Err(Error::SyntaxRules(format!(
+            "{} uses schema {}, this version reads up to schema {}",
+            origin, rules.schema, RULES_SCHEMA
+        ))
+        .into());
+    }
+    check_flags(&rules, origin)?;
+    Ok(rules)
+}
+
+/// Compiles every rule with `flags`, as `validate-rules` does, so a bad flag is reported when
+/// the rules are read rather than when a file in the language comes up. Markers are escaped,
+/// so a rule without flags always compiles.
+fn check_flags(rules: &SyntaxRules, origin: &str) -> Result<()> {
+    for (key, language) in &rules.languages {
+        let single_line = language.single_line.iter().enumerate().map(|(index, rule)| {
+            let pattern = single_line_pattern(&rule.pattern, &rule.not_followed_by, rule.word_boundary, rule.must_start_line);
+            (format!("single_line[{}]", index), pattern, &rule.flags)
+        });
+        let multi_line = language.multi_line.iter().enumerate().map(|(index, rule)| {
+            let pattern = multi_line_pattern(&rule.start, &rule.end, rule.line_start, rule.must_start_line);
+            (format!("multi_line[{}]", index), pattern, &rule.flags)
+        });
+        for (rule, pattern, flags) in single_line.chain(multi_line).filter(|(_, _, flags)| !flags.is_empty()) {
+            if let Err(e) = Regex::new(&with_flags(pattern, flags)) {
+                return Err(Error::SyntaxRules(format!(
+                    "'{}' {} in {} does not compile with flags '{}': {} (run `validate-rules` for details)",
+                    key, rule, origin, flags, e
+                ))
+                .into());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn detect_file_type<'a>(file_path: &str, rules: &'a SyntaxRules) -> Result<&'a LanguageRules> {
+    let path = Path::new(file_path);
+    if let Some(language) = path.file_name().and_then(|name| name.to_str()).and_then(|name| by_file_name(name, rules)) {
+        return Ok(language);
+    }
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .ok_or_else(|| Error::UnsupportedFileType("No file extension found".to_string()))?;
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or(file_path);
+    // `suffix` may be compound like `d.ts`; something has to come before it, so `.ts` alone is no match
+    let has_suffix = |suffix: &str| {
+        file_name.len() > suffix.len() + 1
+            && file_name.ends_with(suffix)
+            && file_name[..file_name.len() - suffix.len()].ends_with('.')
+    };
+
+    // The project config wins, then the longest suffix so `.blade.php` beats `.php`; a suffix
+    // claimed by two languages goes to the higher `priority`, then to the key sorting first
+    let remapped = rules
+        .suffix_overrides
+        .iter()
+        .filter(|(suffix, _)| has_suffix(suffix))
+        .max_by_key(|(suffix, _)| suffix.len())
+        .and_then(|(_, key)| rules.languages.get(key));
+    if let Some(language) = remapped {
+        return Ok(language);
+    }
+
+    rules
+        .languages
+        .iter()
+        .flat_map(|(key, language)| {
+            language
+                .extensions
+                .iter()
+                .filter(|suffix| has_suffix(suffix))
+                .map(move |suffix| (suffix.len(), language.priority, Reverse(key), language))
+        })
+        .max_by_key(|(length, priority, key, _)| (*length, *priority, *key))
+        .map(|(_, _, _, language)| language)
+        .ok_or_else(|| Error::UnsupportedFileType(extension.to_string()).into())
+}
+
+/// The language listing `file_name` in its `filenames`; an exact name beats a `*` pattern,
+/// then the longer pattern wins.
+fn by_file_name<'a>(file_name: &str, rules: &'a SyntaxRules) -> Option<&'a LanguageRules> {
+    rules
+        .languages
+        .iter()
+        .flat_map(|(key, language)| {
+            language.filenames.iter().filter_map(move |pattern| {
+                let rank = match pattern.strip_suffix('*') {
+                    Some(prefix) if file_name.starts_with(prefix) => prefix.len(),
+                    Some(_) => return None,
+                    None if file_name == pattern => usize::MAX,
+                    None => return None,
+                };
+                Some((rank, language.priority, Reverse(key), language))
+            })
+        })
+        .max_by_key(|(rank, priority, key, _)| (*rank, *priority, *key))
+        .map(|(_, _, _, language)| language)
+}
+
+/// Looks up a language by its rules key or display name, ignoring case, for `--lang`.
+fn find_language<'a>(name: &str, rules: &'a SyntaxRules) -> Result<&'a LanguageRules> {
+    find_language_entry(name, rules).map(|(_, language)| language)
+}
+
+fn find_language_entry<'a>(name: &str, rules: &'a SyntaxRules) -> Result<(&'a String, &'a LanguageRules)> {
+    rules
+        .languages
+        .iter()
+        .find(|(key, language)| key.eq_ignore_ascii_case(name) || language.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| {
+            let mut keys: Vec<&str> = rules.languages.keys().map(String::as_str).collect();
+            keys.sort();
+            Error::UnknownLanguage(name.to_string(), keys.join(", ")).into()
+        })
+}
+
+/// Lists the files below `path`, or `path` itself if it is a file. Symlinks inside it are
+/// skipped unless `follow_symlinks`, and then a directory reached a second time, e.g. through
+/// a link back to one of its parents, is not entered again.
+fn collect_files(path: &Path, follow_symlinks: bool) -> Result<Vec<PathBuf>> {
+    let mut visited = HashSet::new();
+    walk_files(path, follow_symlinks, &mut visited)
+}
+
+fn walk_files(path: &Path, follow_symlinks: bool, visited: &mut HashSet<PathBuf>) -> Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    if let Ok(canonical) = fs::canonicalize(path)
+        && !visited.insert(canonical.clone())
+    {
+        info!("Not following {}, {} was already visited", path.display(), paths::plain(&canonical).display());
+        return Ok(Vec::new());
+    }
+
+    let mut entries = fs::read_dir(path)
+        .with_context(|| format!("Failed to read directory: {}", path.display()))?
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.path());
+
+    let mut files = Vec::new();
+    for entry in entries {
+        // Skip hidden entries such as .git
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if !follow_symlinks && entry.file_type().is_ok_and(|file_type| file_type.is_symlink()) {
+            continue;
+        }
+        let entry_path = entry.path();
+        if entry_path.is_dir() {
+            files.extend(walk_files(&entry_path, follow_symlinks, visited)?);
+        } else {
+            files.push(entry_path);
+        }
+    }
+    Ok(files)
+}
+
+fn progress_bar(files: usize) -> ProgressBar {
+    let progress = ProgressBar::new(files as u64);
+    progress.set_style(
+        ProgressStyle::with_template("{bar:30.green} {pos}/{len} files, ETA {eta} {wide_msg}")
+            .unwrap()
+            .progress_chars("=> "),
+    );
+    progress
+}
+
+fn is_supported(path: &Path, rules: &SyntaxRules) -> bool {
+    notebook::is_notebook(path) || detect_file_type(&path.to_string_lossy(), rules).is_ok()
+}
+
+/// Expands directories into the supported files below them. Files named explicitly are
+/// kept as-is so that unsupported ones still produce an error.
+fn expand_paths(
+    paths: &[String],
+    follow_symlinks: bool,
+    supported: impl Fn(&Path) -> bool,
+    excludes: &ExcludeSet,
+) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        let path = paths::native(path);
+        let path = path.as_path();
+        if path.is_dir() {
+            files.extend(
+                collect_files(path, follow_symlinks)?
+                    .into_iter()
+                    .filter(|file| supported(file) && !excludes.is_excluded(file)),
+            );
+        } else {
+            files.push(path.to_path_buf());
+        }
+    }
+    Ok(files)
+}
+
+/// Indentation before a marker: spaces, tabs and the form feeds some files use as page breaks.
+/// `\s` would also cross line breaks and swallow the blank lines above a comment.
+const INDENT: &str = r"[ \t\x0C]*";
+
+/// A single-line comment with `marker`. A comment alone on its line takes the indentation
+/// along, so removing it leaves no stray whitespace; unless `must_start_line`, a comment after
+/// code is found too and starts at its marker, which must then begin a token: a word-like
+/// marker such as `REM` does not count at the end of a longer word.
+fn single_line_pattern(marker: &str, not_followed_by: &[String], word_boundary: bool, must_start_line: bool) -> String {
+    let guard = if not_followed_by.is_empty() && !word_boundary {
+        String::new()
+    } else {
+        let suffixes: Vec<&str> = not_followed_by.iter().map(String::as_str).filter(|text| !text.is_empty()).collect();
+        marker_guard(&suffixes, word_boundary)
+    };
+    let start = if must_start_line {
+        format!("^{}", INDENT)
+    } else if word_boundary && marker.starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+        format!(r"(?:^{})?\b", INDENT)
+    } else {
+        format!("(?:^{})?", INDENT)
+    };
+    // CRLF mode keeps `.` and `$` from consuming the `\r` of Windows line endings
+    format!(r"(?mR){}{}{}.*$", start, regex::escape(marker), guard)
+}
+
+/// A pattern for what may follow a marker: anything that does not start with one of
+/// `suffixes`, nor with a word character if `word_boundary`. The regex crate has no
+/// lookahead, so this spells out the allowed characters one position at a time.
+fn marker_guard(suffixes: &[&str], word_boundary: bool) -> String {
+    let mut next: BTreeMap<char, Vec<&str>> = BTreeMap::new();
+    for suffix in suffixes {
+        let mut chars = suffix.chars();
+        if let Some(first) = chars.next() {
+            next.entry(first).or_default().push(chars.as_str());
+        }
+    }
+
+    let excluded: String = next.keys().map(|c| regex::escape(&c.to_string())).collect();
+    let word = if word_boundary { r"\w" } else { "" };
+    let mut alternatives = vec!["$".to_string(), format!(r"[^{}{}\r\n]", excluded, word)];
+    for (c, rests) in next {
+        // A completed suffix, or a word character after a word-bounded marker, is never allowed
+        if rests.contains(&"") || (word_boundary && (c.is_alphanumeric() || c == '_')) {
+            continue;
+        }
+        alternatives.push(format!("{}{}", regex::escape(&c.to_string()), marker_guard(&rests, false)));
+    }
+    format!("(?:{})", alternatives.join("|"))
+}
+
+fn multi_line_pattern(start: &str, end: &str, line_start: bool, must_start_line: bool) -> String {
+    if line_start {
+        // The start marker is a whole word, so `=beginning` does not open a block
+        return format!(r"(?mR:^{}(?:[ \t].*)?$[\s\S]*?^{}.*$)", regex::escape(start), regex::escape(end));
+    }
+    let indent = if must_start_line { format!("(?m:^{})", INDENT) } else { String::new() };
+    format!(r"{}{}\s*[\s\S]*?\s*{}", indent, regex::escape(start), regex::escape(end))
+}
+
+/// `pattern` with a rule's inline regex `flags`.
+fn with_flags(pattern: String, flags: &str) -> String {
+    if flags.is_empty() { pattern } else { format!("(?{}:{})", flags, pattern) }
+}
+
+fn get_comment_patterns(language: &LanguageRules, verbose: bool) -> Result<CommentPatterns> {
+    if verbose {
+        info!("Detecting patterns for language: {}", language.name);
+    }
+    let mut rules = Vec::new();
+    for rule in &language.single_line {
+        let pattern = single_line_pattern(&rule.pattern, &rule.not_followed_by, rule.word_boundary, rule.must_start_line);
+        let kind = if rule.class() == RuleClass::Doc { CommentKind::Doc } else { CommentKind::Line };
+        let inside_strings = rule.inside_strings == InsideStrings::Always;
+        rules.push((rule.priority, &rule.description, with_flags(pattern, &rule.flags), kind, inside_strings, None));
+    }
+    for rule in &language.multi_line {
+        let pattern = multi_line_pattern(&rule.start, &rule.end, rule.line_start, rule.must_start_line);
+        let kind = if rule.class() == RuleClass::Doc { CommentKind::Doc } else { CommentKind::Block };
+        let inside_strings = rule.inside_strings == InsideStrings::Always;
+        let nested = rule.nested.then(|| (nesting_start(rule, language).to_string(), rule.end.clone()));
+        rules.push((rule.priority, &rule.description, with_flags(pattern, &rule.flags), kind, inside_strings, nested));
+    }
+    // At the same position the earliest alternative wins: the highest `priority`, then
+    // single-line rules before multi-line ones, each in the order listed. The sort is stable.
+    rules.sort_by_key(|(priority, ..)| Reverse(*priority));
+
+    let mut alternatives = Vec::new();
+    let mut kinds = Vec::new();
+    let mut inside_strings = Vec::new();
+    let mut nested = Vec::new();
+    for (_, description, pattern, kind, in_strings, nesting) in rules {
+        if verbose {
+            info!("Added pattern for {}: {}", description, pattern);
+        }
+        alternatives.push(format!("({})", pattern));
+        kinds.push(kind);
+        inside_strings.push(in_strings);
+        nested.push(nesting);
+    }
+
+    // A language without rules gets a regex that never matches
+    let combined = if alternatives.is_empty() { r"[^\s\S]".to_string() } else { alternatives.join("|") };
+    let regex = Regex::new(&combined)
+        .map_err(|e| Error::SyntaxRules(format!("The rules of {} do not compile together: {}", language.name, e)))?;
+    Ok(CommentPatterns { regex, kinds, inside_strings, nested })
+}
+
+/// What opens a nesting level inside a comment of `rule`: the shortest start of the nested
+/// rules it extends, so `/* */` inside a `/** */` doc comment counts.
+fn nesting_start<'r>(rule: &'r MultiLineRule, language: &'r LanguageRules) -> &'r str {
+    language
+        .multi_line
+        .iter()
+        .filter(|other| other.nested && other.end == rule.end && rule.start.starts_with(&other.start))
+        .map(|other| other.start.as_str())
+        .min_by_key(|start| start.len())
+        .unwrap_or(&rule.start)
+}
+
+/// End of the nested comment whose start delimiter is at `from`: just past the `end` closing
+/// it, or the end of `content` when it is never closed.
+fn nested_comment_end(content: &str, from: usize, start: &str, end: &str) -> usize {
+    let mut depth = 0;
+    let mut pos = from;
+    while pos < content.len() {
+        let rest = &content[pos..];
+        if rest.starts_with(start) {
+            depth += 1;
+            pos += start.len();
+        } else if rest.starts_with(end) {
+            depth -= 1;
+            pos += end.len();
+            if depth == 0 {
+                return pos;
+            }
+        } else {
+            pos += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    content.len()
+}
+
+/// Finds every comment without modifying the content, in document order.
+/// Matches whose marker sits inside a protected string literal are skipped.
+fn find_comments(content: &str, patterns: &CommentPatterns, protected: &[Range<usize>]) -> Vec<Comment> {
+    let mut comments = Vec::new();
+    let mut offset = 0;
+    while let Some(captures) = patterns.regex.captures_at(content, offset) {
+        let mat = captures.get(0).unwrap();
+        let rule = (1..captures.len()).find(|&group| captures.get(group).is_some()).unwrap();
+        // Look at the marker itself, single-line matches start with the indentation
+        let marker = mat.start() + (mat.as_str().len() - mat.as_str().trim_start().len());
+        let literal = protected.partition_point(|literal| literal.start <= marker);
+        if literal > 0 && protected[literal - 1].contains(&marker) && !patterns.inside_strings[rule - 1] {
+            offset = protected[literal - 1].end;
+            continue;
+        }
+        // The regex stops at the first end delimiter, which may close an inner comment
+        let end = match &patterns.nested[rule - 1] {
+            Some((start, end)) => nested_comment_end(content, marker, start, end),
+            None => mat.end(),
+        };
+        offset = end;
+
+        comments.push(Comment { kind: patterns.kinds[rule - 1], range: mat.start()..end });
+    }
+    comments
+}
+
+/// Comments that overlap, e.g. a match widened by a language fix-up into the next one, would
+/// have the same text cut twice. Of overlapping comments the one starting first is kept, and
+/// the longest of those starting together; with `strict` an overlap is an error instead.
+fn resolve_overlaps(content: &str, mut comments: Vec<Comment>, strict: bool) -> Result<Vec<Comment>> {
+    comments.sort_by_key(|comment| (comment.range.start, Reverse(comment.range.end)));
+    let mut resolved: Vec<Comment> = Vec::with_capacity(comments.len());
+    for comment in comments {
+        let Some(kept) = resolved.last().filter(|kept| comment.range.start < kept.range.end) else {
+            resolved.push(comment);
+            continue;
+        };
+        // The same comment found twice is no conflict
+        if strict && comment.range != kept.range {
+            let first_line = |range: &Range<usize>| content[range.clone()].lines().next().unwrap_or_default().to_string();
+            return Err(Error::OverlappingComments(format!(
+                "`{}` on line {} and `{}` on line {}",
+                first_line(&kept.range),
+                line_number(content, kept.range.start),
+                first_line(&comment.range),
+                line_number(content, comment.range.start)
+            ))
+            .into());
+        }
+    }
+    Ok(resolved)
+}
+
+/// Finds comments with the requested engine, falling back to the regex patterns
+/// when no grammar is available for the file.
+fn detect_comments(
+    content: &str,
+    file_path: &str,
+    language: &LanguageRules,
+    patterns: &CommentPatterns,
+    engine: Engine,
+) -> Result<Vec<Comment>> {
+    let literals = literals::scan(content, language, file_path);
+    if engine == Engine::TreeSitter
+        && let Some(comments) = syntax_tree::find_comments(content, file_path, language)?
+    {
+        return Ok(literals.widen_jsx_comments(content, comments));
+    }
+    let comments = find_comments(content, patterns, &literals.strings);
+    if php::is_php(language) {
+        return Ok(php::fix_comments(content, comments));
+    }
+    if python::is_python(language) {
+        return Ok(python::classify_strings(content, comments));
+    }
+    if c_family::is_c_family(language) {
+        return Ok(c_family::join_continuations(content, comments));
+    }
+    if build_files::is_dockerfile(language) {
+        return Ok(build_files::fix_dockerfile_comments(content, comments));
+    }
+    if build_files::is_makefile(language) {
+        return Ok(build_files::fix_makefile_comments(content, comments));
+    }
+    Ok(literals.widen_jsx_comments(content, comments))
+}
+
+fn check_engine(engine: Engine) -> Result<()> {
+    if engine == Engine::TreeSitter && !syntax_tree::AVAILABLE {
+        return Err(Error::EngineUnavailable("tree-sitter".to_string()).into());
+    }
+    Ok(())
+}
+
+fn detect_line_ending(content: &str) -> &'static str {
+    let crlf = content.matches("\r\n").count();
+    let lf = content.matches('\n').count() - crlf;
+    if crlf > lf { "\r\n" } else { "\n" }
+}
+
+/// Restores the presence or absence of a final line ending, which removing the last
+/// comment in a file can change.
+fn preserve_final_newline(original: &str, mut result: String) -> String {
+    let had_final_newline = original.ends_with('\n');
+    let has_final_newline = result.ends_with('\n');
+    if had_final_newline && !has_final_newline && !result.is_empty() {
+        result.push_str(detect_line_ending(original));
+    } else if !had_final_newline && has_final_newline {
+        result.pop();
+        if result.ends_with('\r') {
+            result.pop();
+        }
+    }
+    result
+}
+
+/// Trims the trailing whitespace a removal left behind, e.g. the spaces that aligned a trailing
+/// comment or the indentation before a block comment. Only lines touching one of the `replaced`
+/// ranges change, so every other line stays byte-identical.
+fn trim_modified_lines(content: String, replaced: &[Range<usize>]) -> String {
+    if replaced.is_empty() {
+        return content;
+    }
+    let mut trimmed = String::with_capacity(content.len());
+    let mut replaced = replaced.iter().peekable();
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let body = line.trim_end_matches(['\n', '\r']);
+        let body_end = offset + body.len();
+        // A replacement ending in a line break is over before this line starts
+        while replaced
+            .next_if(|range| range.end < offset || (range.end == offset && !range.is_empty()))
+            .is_some()
+        {}
+        if replaced.peek().is_some_and(|range| range.start <= body_end) {
+            trimmed.push_str(body.trim_end_matches([' ', '\t']));
+            trimmed.push_str(&line[body.len()..]);
+        } else {
+            trimmed.push_str(line);
+        }
+        offset += line.len();
+    }
+    trimmed
+}
+
+fn line_number(content: &str, offset: usize) -> usize {
+    content[..offset].matches('\n').count() + 1
+}
+
+fn find_license_header(content: &str, language: &LanguageRules, license_pattern: &Regex) -> Option<usize> {
+    // A shebang has to stay on the first line, so the header starts after it
+    let mut start = 0;
+    if content.starts_with("#!") && !content.starts_with("#![") {
+        start = content.find('\n').map_or(content.len(), |i| i + 1);
+    }
+    let rest = &content[start..];
+    let block_start = start + (rest.len() - rest.trim_start().len());
+    let block = &content[block_start..];
+
+    let block_end = if let Some(rule) = language.multi_line.iter().find(|rule| block.starts_with(&rule.start)) {
+        let body_start = rule.start.len();
+        body_start + block[body_start..].find(&rule.end)? + rule.end.len()
+    } else {
+        // Consecutive single-line comments form one block, ending at the first non-comment line
+        let mut end = 0;
+        let mut offset = 0;
+        for line in block.split_inclusive('\n') {
+            let trimmed = line.trim_start();
+            if !language.single_line.iter().any(|rule| rule.strip_marker(trimmed).is_some()) {
+                break;
+            }
+            end = offset + line.trim_end_matches(['\r', '\n']).len();
+            offset += line.len();
+        }
+        end
+    };
+
+    if block_end > 0 && license_pattern.is_match(&block[..block_end]) {
+        Some(block_start + block_end)
+    } else {
+        None
+    }
+}
+
+fn find_disabled_regions(content: &str) -> Vec<Range<usize>> {
+    // Each region spans from the line holding `off` to the end of the line holding the next `on`,
+    // so the directive comments themselves are never removed
+    let mut regions = Vec::new();
+    let mut search_from = 0;
+    while let Some(pos) = content[search_from..].find(OFF_DIRECTIVE) {
+        let off_pos = search_from + pos;
+        let start = content[..off_pos].rfind('\n').map_or(0, |i| i + 1);
+        let after_off = off_pos + OFF_DIRECTIVE.len();
+        let end = match content[after_off..].find(ON_DIRECTIVE) {
+            Some(on) => {
+                let on_end = after_off + on + ON_DIRECTIVE.len();
+                content[on_end..].find('\n').map_or(content.len(), |i| on_end + i)
+            }
+            None => content.len(),
+        };
+        regions.push(start..end);
+        search_from = end;
+    }
+    regions
+}
+
+/// An answer to the interactive prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PromptAnswer {
+    Remove,
+    Keep,
+    /// Keep it and never ask about it again
+    Ignore,
+    /// Go back to the previous comment of the file
+    Back,
+    /// Leave the whole file unchanged and go on with the next one
+    SkipFile,
+}
+
+/// Shows a comment with the code around it, see `--context`, and asks whether to remove it;
+/// `position` says where it is in the queue, like "file 3/17, comment 5/12".
+fn ask_about_comment(candidate: &review::Candidate, rules: &SyntaxRules, position: &str) -> PromptAnswer {
+    let keywords = review::keywords(&candidate.language, rules);
+    let code = |text: &str| {
+        review::split_keywords(text, &keywords)
+            .into_iter()
+            .map(|(piece, keyword)| if keyword { piece.magenta().bold().to_string() } else { piece.to_string() })
+            .collect::<String>()
+    };
+    // Prompts go to stderr as well so they never end up in redirected output
+    eprintln!("\nFound comment in {}:{} ({}):", candidate.file, candidate.line, position);
+    for (number, before, comment, after) in candidate.lines() {
+        eprintln!("{} {}{}{}", format!("{:>5}", number).dimmed(), code(before), comment.yellow(), code(after));
+    }
+    eprint!("Remove this comment? (y/n, i = keep and never ask again, b = back, s = skip this file): ");
+    io::stderr().flush().unwrap();
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+    match input.trim().to_lowercase().as_str() {
+        "y" => PromptAnswer::Remove,
+        "i" => PromptAnswer::Ignore,
+        "b" => PromptAnswer::Back,
+        "s" => PromptAnswer::SkipFile,
+        _ => PromptAnswer::Keep,
+    }
+}
+
+/// `--match`/`--keep` regexes from the command line and the project config.
+#[derive(Default)]
+struct CommentFilters {
+    match_patterns: Vec<Regex>,
+    keep_patterns: Vec<Regex>,
+}
+
+impl CommentFilters {
+    fn new(match_patterns: &[String], keep_patterns: &[String]) -> Result<Self> {
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|pattern| Regex::new(pattern).map_err(|e| Error::InvalidPattern(e.to_string()).into()))
+                .collect::<Result<Vec<_>>>()
+        };
+        Ok(CommentFilters {
+            match_patterns: compile(match_patterns)?,
+            keep_patterns: compile(keep_patterns)?,
+        })
+    }
+
+    fn allows(&self, comment: &str) -> bool {
+        if self.keep_patterns.iter().any(|pattern| pattern.is_match(comment)) {
+            return false;
+        }
+        self.match_patterns.is_empty() || self.match_patterns.iter().any(|pattern| pattern.is_match(comment))
+    }
+}
+
+/// Per-file decisions about which comments may be removed at all and what replaces them,
+/// independent of the mode.
+struct RemovalPolicy<'a> {
+    file_path: &'a str,
+    language: &'a LanguageRules,
+    license_header_end: Option<usize>,
+    /// Comments of `license` rules are kept too
+    keep_license: bool,
+    only_dead_code: bool,
+    preserve_lines: bool,
+    /// Block comments become a one-line stub instead of nothing
+    collapse: bool,
+    /// Block comments keep their annotation lines
+    annotations: bool,
+    keep_docstrings: bool,
+    keep_inner_docs: bool,
+    keep_directives: bool,
+    keep_attached: bool,
+    filters: &'a CommentFilters,
+    /// With `--older-than`: the file's line ages, the line of the file the text starts on and
+    /// the cutoff timestamp
+    age_limit: Option<(&'a LineAges, usize, i64)>,
+    handlers: &'a [Arc<dyn CommentHandler>],
+}
+
+impl RemovalPolicy<'_> {
+    /// Text that takes the place of the removed comment at `range` in `content`.
+    fn replacement(&self, content: &str, range: &Range<usize>) -> String {
+        let text = &content[range.clone()];
+        let stub = if self.collapse {
+            self.collapsed(text)
+        } else if self.annotations {
+            match annotations::strip_prose(text, self.language) {
+                annotations::Stripped::Rebuilt(rebuilt) => Some(rebuilt),
+                _ => None,
+            }
+        } else {
+            None
+        };
+        if !self.preserve_lines {
+            return stub.unwrap_or_default();
+        }
+        if let Some(stub) = stub {
+            // Blank lines make up for the ones the stub no longer has
+            let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+            let lost = text.matches('\n').count() - stub.matches('\n').count();
+            return stub + &newline.repeat(lost);
+        }
+        // Keep the line breaks so nothing moves up; an inline comment with code after it
+        // becomes spaces so that code also keeps its column
+        let line_end = content[range.end..].find('\n').map_or(content.len(), |i| range.end + i);
+        let code_follows = !content[range.end..line_end].trim().is_empty();
+        text.chars()
+            .filter_map(|c| match c {
+                '\n' | '\r' => Some(c),
+                _ if code_follows => Some(' '),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The one-line stub `--action collapse` leaves of a block comment spanning several lines:
+    /// its delimiters around its first sentence, or around an ellipsis if it has no text.
+    fn collapsed(&self, text: &str) -> Option<String> {
+        if !text.contains('\n') {
+            return None;
+        }
+        let rule = self
+            .language
+            .multi_line
+            .iter()
+            .filter(|rule| {
+                text.len() >= rule.start.len() + rule.end.len()
+                    && text.starts_with(&rule.start)
+                    && text.ends_with(&rule.end)
+            })
+            .max_by_key(|rule| rule.start.len())?;
+        let body = &text[rule.start.len()..text.len() - rule.end.len()];
+        let lines: Vec<&str> = body
+            .lines()
+            .map(|line| line.trim().trim_start_matches('*').trim())
+            .filter(|line| !line.is_empty())
+            .collect();
+        let joined = lines.join(" ");
+        let sentence = match joined.find(". ") {
+            Some(end) => &joined[..=end],
+            None => joined.as_str(),
+        };
+        let summary = if sentence.is_empty() { "…" } else { sentence };
+        Some(format!("{} {} {}", rule.start, summary, rule.end))
+    }
+
+    /// Why the comment must stay, whatever the options and handlers say.
+    fn protection(
+        &self,
+        content: &str,
+        span: &CommentSpan,
+        disabled_regions: &[Range<usize>],
+    ) -> Option<(&'static str, &'static str)> {
+        let range = &span.range;
+        // The header sits at the start of the file, so removals never shift it
+        if self.license_header_end.is_some_and(|end| range.start < end)
+            || (self.keep_license && rule_class(&span.text, self.language) == RuleClass::License)
+        {
+            return Some(("license header", "Keeping license header comment"));
+        }
+        if span.text.contains(KEEP_DIRECTIVE)
+            || disabled_regions.iter().any(|region| range.start < region.end && region.start < range.end)
+        {
+            return Some(("keep directive", "Keeping comment protected by a comment-remover directive"));
+        }
+        if directives::is_compiler_directive(content, range, self.language) {
+            return Some(("compiler directive", "Keeping compiler directive"));
+        }
+        None
+    }
+
+    /// Why the options keep the comment, if they do.
+    fn option_keeping(&self, content: &str, span: &CommentSpan) -> Option<(&'static str, &'static str)> {
+        let comment = span.text.as_str();
+        if self.keep_directives
+            && (directives::is_directive(comment, self.language)
+                || rule_class(comment, self.language) == RuleClass::Directive)
+        {
+            return Some(("tool directive", "Keeping linter/tool directive, use --strip-directives to remove it"));
+        }
+        if self.keep_docstrings && span.kind == CommentKind::Doc {
+            return Some(("docstring", "Keeping docstring, use --strip-docstrings to remove it"));
+        }
+        if self.keep_attached && attached::precedes_declaration(content, &span.range, self.language) {
+            return Some(("attached", "Keeping comment attached to a declaration"));
+        }
+        if self.keep_inner_docs && rust::is_inner_doc(comment) {
+            return Some(("inner doc", "Keeping crate/module documentation, use --strip-inner-docs to remove it"));
+        }
+        if !self.filters.allows(comment) {
+            return Some(("filter", "Keeping comment excluded by --match/--keep filters"));
+        }
+        if let Some((ages, first_line, cutoff)) = self.age_limit
+            && !ages.older_than(span, first_line, cutoff)
+        {
+            return Some(("recent", "Keeping comment changed more recently than --older-than"));
+        }
+        if self.only_dead_code && !dead_code::is_commented_out_code(comment, self.language) {
+            return Some(("not dead code", "Keeping comment that does not look like commented-out code"));
+        }
+        if self.annotations
+            && matches!(annotations::strip_prose(comment, self.language), annotations::Stripped::Annotations)
+        {
+            return Some(("annotations", "Keeping comment made only of annotations"));
+        }
+        None
+    }
+
+    /// The reason a comment is kept, if it is. Handlers see the outcome the options would
+    /// give in `span` and may override it, but not the protections.
+    fn keeps(
+        &self,
+        content: &str,
+        span: &mut CommentSpan,
+        disabled_regions: &[Range<usize>],
+        verbose: bool,
+    ) -> Option<String> {
+        if let Some((reason, message)) = self.protection(content, span, disabled_regions) {
+            if verbose {
+                info!("{}", message);
+            }
+            return Some(reason.to_string());
+        }
+
+        let option = self.option_keeping(content, span);
+        if !self.handlers.is_empty() {
+            span.outcome = option.map_or(Outcome::Removed, |(reason, _)| Outcome::Kept(reason.to_string()));
+            let context = FileContext { path: self.file_path, language: &self.language.name, content };
+            for handler in self.handlers {
+                match handler.classify(span, &context) {
+                    Action::Keep => {
+                        if verbose {
+                            info!("Keeping comment as decided by the {} handler", handler.name());
+                        }
+                        return Some(format!("handler {}", handler.name()));
+                    }
+                    Action::Remove => return None,
+                    Action::Default => {}
+                }
+            }
+        }
+
+        let (reason, message) = option?;
+        if verbose {
+            info!("{}", message);
+        }
+        Some(reason.to_string())
+    }
+}
+
+/// The rule a comment was found by: the markers of the longest matching rule, or the
+/// construct for comments that come from elsewhere.
+fn comment_rule(text: &str, kind: CommentKind, language: &LanguageRules) -> String {
+    let single = language
+        .single_line
+        .iter()
+        .filter(|rule| rule.strip_marker(text).is_some())
+        .map(|rule| (rule.pattern.len(), rule.pattern.clone()));
+    let multi = language
+        .multi_line
+        .iter()
+        .filter(|rule| text.starts_with(&rule.start))
+        .map(|rule| (rule.start.len(), format!("{} {}", rule.start, rule.end)));
+    if let Some((_, rule)) = single.chain(multi).max_by_key(|(length, _)| *length) {
+        return rule;
+    }
+    match kind {
+        CommentKind::Doc => "docstring".to_string(),
+        _ if text.starts_with('#') => "#if 0".to_string(),
+        _ => format!("{:?}", kind).to_lowercase(),
+    }
+}
+
+/// The class of the longest rule whose marker starts `text`, like `comment_rule`.
+fn rule_class(text: &str, language: &LanguageRules) -> RuleClass {
+    let text = text.trim_start();
+    let single = language
+        .single_line
+        .iter()
+        .filter(|rule| rule.strip_marker(text).is_some())
+        .map(|rule| (rule.pattern.len(), rule.class()));
+    let multi = language
+        .multi_line
+        .iter()
+        .filter(|rule| text.starts_with(&rule.start))
+        .map(|rule| (rule.start.len(), rule.class()));
+    single.chain(multi).max_by_key(|(length, _)| *length).map_or(RuleClass::Normal, |(_, class)| class)
+}
+
+/// Removes the comments the policy allows and `decide` agrees to in a single pass over
+/// `content`: comment positions are found up front, so the output is built once and the work
+/// stays linear in the file size.
+fn remove_comments(
+    content: &str,
+    comments: &[Comment],
+    policy: &RemovalPolicy,
+    decide: &mut dyn FnMut(&Range<usize>) -> bool,
+    verbose: bool,
+) -> RemovalResult {
+    if verbose {
+        let line_ending = if detect_line_ending(content) == "\r\n" { "CRLF" } else { "LF" };
+        info!("Detected line endings: {}", line_ending);
+        info!("Original content preview:\n{}", content.lines().take(5).collect::<Vec<_>>().join("\n"));
+    }
+
+    let disabled_regions = find_disabled_regions(content);
+    let mut result = String::with_capacity(content.len());
+    let mut last_end = 0;
+    let mut positions = Positions::new(content);
+    let mut spans = Vec::with_capacity(comments.len());
+    // Where the replacements ended up in `result`
+    let mut replaced = Vec::new();
+
+    for comment in comments {
+        let text = &content[comment.range.clone()];
+        if verbose {
+            info!("Found comment at position {}: {}", comment.range.start, text);
+        }
+
+        let mut span = CommentSpan {
+            range: comment.range.clone(),
+            start: positions.at(comment.range.start),
+            end: positions.at(comment.range.end),
+            kind: comment.kind,
+            rule: comment_rule(text, comment.kind, policy.language),
+            text: text.to_string(),
+            outcome: Outcome::Removed,
+        };
+        span.outcome = if let Some(reason) = policy.keeps(content, &mut span, &disabled_regions, verbose) {
+            Outcome::Kept(reason)
+        } else if decide(&comment.range) {
+            result.push_str(&content[last_end..comment.range.start]);
+            let start = result.len();
+            result.push_str(&policy.replacement(content, &comment.range));
+            replaced.push(start..result.len());
+            last_end = comment.range.end;
+            Outcome::Removed
+        } else {
+            Outcome::Declined
+        };
+        spans.push(span);
+    }
+    result.push_str(&content[last_end..]);
+    let result = trim_modified_lines(result, &replaced);
+    let result = RemovalResult { content: preserve_final_newline(content, result), comments: spans };
+
+    if verbose {
+        if result.found() == 0 {
+            info!("No comments were found in the file");
+            info!("Content preview after processing:\n{}", result.content.lines().take(5).collect::<Vec<_>>().join("\n"));
+        } else {
+            info!("Found {} comments, removed {} comments", result.found(), result.removed());
+        }
+    }
+
+    result
+}
+
+/// Runs `remover` over the supported files inside `archive`, then packs the result into a new
+/// archive, or over the old one with `in_place`, if anything changed and the run writes. With
+/// `--out-dir` the new archive goes there instead.
+fn remove_in_archive(
+    remover: &remove::Remover,
+    args: &RemoveArgs,
+    archive: &Path,
+    in_place: bool,
+    excludes: &ExcludeSet,
+    summary: &mut remove::RunSummary,
+    progress: &ProgressBar,
+) -> Result<()> {
+    let unpacked = archive::Unpacked::new(archive)?;
+    let members = expand_paths(&[unpacked.dir.to_string_lossy().into_owned()], false, |file| remover.supports(file), excludes)?;
+    progress.inc_length(members.len() as u64);
+    remover.set_scratch(true);
+    let mut changed = false;
+    for (index, member) in members.iter().enumerate() {
+        progress.set_message(member.display().to_string());
+        remover.set_position(index + 1, members.len());
+        let result = remover.process_file(member);
+        changed |= result.as_ref().is_ok_and(|report| report.changed);
+        if let Some((failure, e)) = summary.record(result) {
+            progress.suspend(|| output::file_error(failure, member.display(), &e));
+        }
+        progress.inc(1);
+    }
+    remover.set_scratch(false);
+    if args.check || (!changed && args.out_dir.is_none()) {
+        return Ok(());
+    }
+    let output = match &args.out_dir {
+        Some(out_dir) => {
+            let output = Path::new(out_dir).join(backup::mirrored_path(archive, &env::current_dir()?)?);
+            remover.claim_copy(&output, archive)?;
+            if let Some(parent) = output.parent() {
+                fs::create_dir_all(parent).with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+            }
+            output
+        }
+        None => unpacked.output_path(in_place),
+    };
+    unpacked.pack(&output)?;
+    info!("Wrote {}", output.display().to_string().green());
+    Ok(())
+}
+
+/// Normalizes the comments of `path`, writing the result with a backup and a history entry
+/// unless `writer` is `None`, and returns how many comments changed.
+fn normalize_file(
+    path: &Path,
+    rules: &SyntaxRules,
+    config: &ProjectConfig,
+    forced_encoding: Option<&'static encoding_rs::Encoding>,
+    writer: Option<&(backup::BackupPolicy, history::Journal)>,
+) -> Result<usize> {
+    let source = encoding::read_source(path, forced_encoding)?;
+    let file = path.to_string_lossy();
+    let language = detect_file_type(&file, rules)?;
+    let patterns = get_comment_patterns(language, false)?;
+    let comments = detect_comments(&source.text, &file, language, &patterns, Engine::Regex)?;
+    let comments = resolve_overlaps(&source.text, comments, false)?;
+    let normalization = normalize::normalize(&source.text, &comments, language, &config.normalize);
+    let Some((backups, journal)) = writer.filter(|_| normalization.normalized > 0) else {
+        return Ok(normalization.normalized);
+    };
+    let encoded = encoding::encode(&normalization.content, &source)?;
+    if let Some(backup_path) = backups.create(path)? {
+        info!("Created backup file: {}", backup_path.display().to_string().blue());
+    }
+    journal.record(path, &source.text, &normalization.content)?;
+    atomic_write::write_atomic(path, &encoded).with_context(|| format!("Failed to write modified file: {}", file))?;
+    Ok(normalization.normalized)
+}
+
+/// Options parsed like the command line outside of `Cli`.
+#[derive(Parser)]
+#[command(no_binary_name = true)]
+struct Options<T: Args> {
+    #[command(flatten)]
+    args: T,
+}
+
+impl RemoveArgs {
+    /// Options that strip as `strip` says and nothing else: no prompt, cache or history.
+    fn stripping(strip: StripArgs) -> Self {
+        let mut args = Options::<RemoveArgs>::parse_from(["--auto", "--no-cache", "--no-history"]).args;
+        args.strip = strip;
+        args
+    }
+}
+
+/// Runs `remover` over the `targets` some of whose `candidates` are chosen for removal,
+/// removing just those.
+fn remove_chosen(remover: &mut remove::Remover, targets: &[PathBuf], candidates: &[review::Candidate]) -> remove::RunSummary {
+    let chosen = review::decisions(candidates);
+    let chosen_targets: Vec<&PathBuf> = targets
+        .iter()
+        .filter(|target| chosen.get(&*target.to_string_lossy()).is_some_and(|file| file.contains(&true)))
+        .collect();
+    remover.set_decisions(remove::Decisions::Chosen(RefCell::new(chosen)));
+    let mut summary = remove::RunSummary::default();
+    for target in chosen_targets {
+        if let Some((failure, e)) = summary.record(remover.process_file(target)) {
+            output::file_error(failure, target.display(), &e);
+        }
+    }
+    summary
+}
+
+/// Syntax rules for a `Stripper`, parsed once and shared by any number of them.
+pub struct Rules(SyntaxRules);
+
+impl Rules {
+    /// The syntax_rules.json this library was built with.
+    pub fn bundled() -> Self {
+        Rules(serde_json::from_str(BUNDLED_RULES).expect("the bundled syntax rules parse"))
+    }
+
+    /// Rules in the format of syntax_rules.json, such as a project's own.
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(Rules(parse_syntax_rules(json, "the given rules")?))
+    }
+}
+
+/// Removes comments from source text without touching the disk or the network, for embedding
+/// the engine. The options are parsed and each language's rules compiled once, however many
+/// texts are stripped; the project config and ignore list of the command line play no part.
+pub struct Stripper<'r> {
+    rules: &'r SyntaxRules,
+    remover: remove::Remover<'r>,
+}
+
+impl<'r> Stripper<'r> {
+    /// `options` are the `remove` flags deciding what is stripped, such as `--strip-docstrings`
+    /// or `--profile aggressive`.
+    pub fn new(rules: &'r Rules, options: &[&str]) -> Result<Self> {
+        let strip = Options::<StripArgs>::try_parse_from(options).map_err(usage_error)?.args;
+        let remover = remove::Remover::new(&rules.0, &RemoveArgs::stripping(strip), &ProjectConfig::default())?;
+        Ok(Stripper { rules: &rules.0, remover })
+    }
+
+    /// Removes comments from `source` in `language` (rules key or name).
+    pub fn strip(&self, source: &str, language: &str) -> Result<RemovalResult> {
+        let language = find_language(language, self.rules)?;
+        self.remover.strip_text(source, "", language)
+    }
+}
+
+/// Whether the command line asks for `format` before it could be parsed.
+fn error_format_requested(format: ErrorFormat) -> bool {
+    let Some(name) = format.to_possible_value().map(|value| value.get_name().to_string()) else {
+        return false;
+    };
+    let args: Vec<String> = env::args().collect();
+    args.iter().any(|arg| *arg == format!("--error-format={}", name))
+        || args.windows(2).any(|pair| pair[0] == "--error-format" && pair[1] == name)
+}
+
+/// Runs the command line tool.
+pub fn run() -> Result<()> {
+    let mut cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        // Help and version go to stdout, and without --error-format json usage errors are
+        // printed and exit with 2 the way clap does it
+        Err(e) if !e.use_stderr() || !error_format_requested(ErrorFormat::Json) => e.exit(),
+        Err(e) => {
+            output::set_error_format(ErrorFormat::Json);
+            return Err(usage_error(e).into());
+        }
+    };
+    output::set_error_format(cli.error_format);
+    if let Commands::Remove { resume: true, .. } = cli.command {
+        // Run again as started, over the files it did not get to
+        let interrupted = run_log::interrupted()?;
+        let quiet = cli.quiet;
+        let error_format = cli.error_format;
+        cli = Cli::try_parse_from(iter::once(help::BIN_NAME.to_string()).chain(interrupted.args)).map_err(usage_error)?;
+        cli.quiet |= quiet;
+        cli.error_format = error_format;
+        if let Commands::Remove { files, staged, changed, resume, .. } = &mut cli.command {
+            *files = interrupted.pending.iter().map(|file| file.to_string_lossy().into_owned()).collect();
+            *staged = false;
+            *changed = None;
+            *resume = true;
+        }
+    }
+    output::set_quiet(cli.quiet);
+
+    if let Commands::Completions { shell } = cli.command {
+        clap_complete::generate(shell, &mut Cli::command(), help::BIN_NAME, &mut io::stdout());
+        return Ok(());
+    }
+
+    // Runs before the rules are loaded, since a broken file would stop the tool right here
+    if let Commands::ValidateRules { path } = &cli.command {
+        let rules_path = match path {
+            Some(path) => PathBuf::from(path),
+            None => {
+                let config_url = config::load().ok().and_then(|config| config.rules_url);
+                rules_file(cli.rules_url.as_deref().or(config_url.as_deref()))?
+            }
+        };
+        let diagnostics = validate_rules::check_file(&rules_path)?;
+        for diagnostic in &diagnostics {
+            println!("{}:{}", rules_path.display(), diagnostic);
+        }
+        let errors = diagnostics.iter().filter(|d| d.severity == validate_rules::Severity::Error).count();
+        if errors > 0 {
+            info!("{} error(s), {} warning(s) in {}", errors, diagnostics.len() - errors, rules_path.display());
+            process::exit(EXIT_RULES_ERROR);
+        }
+        info!("{} {} is valid ({} warning(s))", "✓".green(), rules_path.display(), diagnostics.len());
+        return Ok(());
+    }
+
+    let project_config = config::load()?;
+    let mut syntax_rules = load_syntax_rules(cli.rules_url.as_deref().or(project_config.rules_url.as_deref()))?;
+    syntax_rules.apply_config(&project_config)?;
+
+    match cli.command {
+        Commands::Remove { files, staged, changed, exclude, in_place, resume, args } => {
+            let excludes = ExcludeSet::new(&exclude)?;
+            let (archives, files): (Vec<String>, Vec<String>) =
+                files.into_iter().partition(|file| archive::is_archive(Path::new(file)));
+            if !archives.is_empty() && (args.output == RemoveOutput::Patch || args.report.is_some()) {
+                return Err(Error::InvalidOptions(
+                    "--output patch and --report cannot describe files inside archives".to_string(),
+                )
+                .into());
+            }
+            let mut remover = remove::Remover::new(&syntax_rules, &args, &project_config)?;
+            let targets = if staged || changed.is_some() {
+                let git_files = match &changed {
+                    Some(reference) => git::changed_files(reference)?,
+                    None => git::staged_files()?,
+                };
+                // Paths given alongside the git options narrow the selection down
+                let roots = files
+                    .iter()
+                    .map(|path| fs::canonicalize(path).with_context(|| format!("Failed to resolve path: {}", path)))
+                    .collect::<Result<Vec<_>>>()?;
+                git_files
+                    .into_iter()
+                    .filter(|file| remover.supports(file) && !excludes.is_excluded(file))
+                    .filter(|file| {
+                        roots.is_empty()
+                            || fs::canonicalize(file).is_ok_and(|file| roots.iter().any(|root| file.starts_with(root)))
+                    })
+                    .collect()
+            } else {
+                expand_paths(&files, args.follow_symlinks, |file| remover.supports(file), &excludes)?
+            };
+            // Copies from an earlier run must not be picked up as sources; an output directory
+            // holding the working directory is left to the overwrite check instead
+            let out_root = args
+                .out_dir
+                .as_deref()
+                .and_then(|dir| fs::canonicalize(dir).ok())
+                .filter(|root| !env::current_dir().is_ok_and(|cwd| cwd.starts_with(root)));
+            let targets: Vec<PathBuf> = match &out_root {
+                Some(out_root) => targets
+                    .into_iter()
+                    .filter(|target| !fs::canonicalize(target).is_ok_and(|target| target.starts_with(out_root)))
+                    .collect(),
+                None => targets,
+            };
+
+            if targets.is_empty() && archives.is_empty() {
+                info!("No matching files to process");
+            }
+
+            // Prompts and verbose output need the terminal to themselves
+            let show_progress = targets.len() + archives.len() > 1
+                && (args.auto || args.check)
+                && !output::is_quiet()
+                && !args.verbose
+                && io::stderr().is_terminal();
+            let progress = if show_progress { progress_bar(targets.len()) } else { ProgressBar::hidden() };
+            remover.set_progress(progress.clone());
+
+            // A run that modifies files can be continued with --resume if it stops halfway
+            let modifies = !args.check && args.output == RemoveOutput::Files && args.report != Some(ReportFormat::Html);
+            let mut run_log = if !modifies {
+                None
+            } else if resume {
+                Some(run_log::RunLog::resume()?)
+            } else {
+                let command_line: Vec<String> = env::args_os().skip(1).map(|arg| arg.to_string_lossy().into_owned()).collect();
+                let paths: Vec<PathBuf> = targets.iter().cloned().chain(archives.iter().map(PathBuf::from)).collect();
+                Some(run_log::RunLog::start(&command_line, &paths)?)
+            };
+
+            let mut summary = remove::RunSummary::default();
+            let mut patch = String::new();
+            let mut findings = Vec::new();
+            let mut changes = Vec::new();
+            for (index, target) in targets.iter().enumerate() {
+                progress.set_message(target.display().to_string());
+                remover.set_position(index + 1, targets.len());
+                let mut result = remover.process_file(target);
+                if let Ok(report) = &mut result {
+                    if let Some(diff) = &report.patch {
+                        patch.push_str(diff);
+                    }
+                    if let Some((before, after)) = report.contents.take() {
+                        changes.push(html_report::FileChange {
+                            path: target.clone(),
+                            comments_removed: report.comments_removed,
+                            before,
+                            after,
+                        });
+                    }
+                    if args.report == Some(ReportFormat::Sarif) {
+                        findings.push((target.clone(), std::mem::take(&mut report.comments)));
+                    }
+                }
+                if let Some((failure, e)) = summary.record(result) {
+                    progress.suspend(|| output::file_error(failure, target.display(), &e));
+                }
+                progress.inc(1);
+                if let Some(run_log) = &mut run_log {
+                    run_log.finished(target)?;
+                }
+            }
+            for archive in &archives {
+                let result =
+                    remove_in_archive(&remover, &args, Path::new(archive), in_place, &excludes, &mut summary, &progress);
+                if let Err(e) = result
+                    && let Some((failure, e)) = summary.record(Err(e))
+                {
+                    progress.suspend(|| output::file_error(failure, archive, &e));
+                }
+                if let Some(run_log) = &mut run_log {
+                    run_log.finished(Path::new(archive))?;
+                }
+            }
+            if let Some(run_log) = run_log {
+                run_log.complete()?;
+            }
+            progress.finish_and_clear();
+            remover.save_cache()?;
+            remover.save_recording()?;
+            print!("{}", patch);
+            match args.report {
+                Some(ReportFormat::Sarif) => print!("{}", sarif::render(&findings, args.check)?),
+                Some(ReportFormat::Html) => print!("{}", html_report::render(&changes)),
+                None => {}
+            }
+
+            let preview = args.check || args.output == RemoveOutput::Patch || args.report == Some(ReportFormat::Html);
+            if targets.len() > 1 || summary.errored + summary.skipped > 0 {
+                info!("{}\n{}", "Summary:".bold(), summary.render(preview));
+            }
+            if summary.errored > 0 {
+                process::exit(EXIT_FILE_ERRORS);
+            }
+            if args.check && summary.modified > 0 {
+                info!("{} file(s) contain removable comments", summary.modified);
+                process::exit(EXIT_COMMENTS_FOUND);
+            }
+        }
+        Commands::Extract { file, format, output, sidecar, encoding, engine, lang } => {
+            check_engine(engine)?;
+            let file_path = &file;
+            let forced_encoding = encoding.as_deref().map(encoding::parse_encoding).transpose()?;
+            let content = encoding::read_source(Path::new(file_path), forced_encoding)?.text;
+            let language = match &lang {
+                Some(name) => find_language(name, &syntax_rules)?,
+                None => detect_file_type(file_path, &syntax_rules)?,
+            };
+            let patterns = get_comment_patterns(language, false)?;
+
+            let comments = detect_comments(&content, file_path, language, &patterns, engine)?;
+            let comments = extract::extract_comments(file_path, &content, &comments);
+            let rendered = extract::render(&comments, file_path, language, format)?;
+
+            let output_path = if sidecar {
+                Some(format!("{}.comments.{}", file_path, format.extension()))
+            } else {
+                output
+            };
+            match output_path {
+                Some(path) => {
+                    fs::write(&path, rendered)
+                        .with_context(|| format!("Failed to write extracted comments: {}", path))?;
+                    info!("Extracted {} comments to: {}", comments.len(), path.green());
+                }
+                None => print!("{}", rendered),
+            }
+        }
+        Commands::Print { file, encoding, max_size, markdown, args } => {
+            // Nothing is asked or written, the result only goes to stdout
+            check_engine(args.engine)?;
+            let mut args = RemoveArgs::stripping(args);
+            args.encoding = encoding;
+            args.max_size = max_size;
+            args.markdown = markdown;
+            let remover = remove::Remover::new(&syntax_rules, &args, &project_config)?;
+            let stripped = remover.strip_path(Path::new(&file))?;
+            match io::stdout().write_all(&stripped) {
+                // The reader, e.g. `head`, has seen enough
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+                result => result.context("Failed to write to stdout")?,
+            }
+        }
+        Commands::Convert { file, to, stdout, force, encoding, lang } => {
+            let path = Path::new(&file);
+            let forced_encoding = encoding.as_deref().map(encoding::parse_encoding).transpose()?;
+            let source = encoding::read_source(path, forced_encoding)?;
+            let language = match &lang {
+                Some(name) => find_language(name, &syntax_rules)?,
+                None => detect_file_type(&file, &syntax_rules)?,
+            };
+            let patterns = get_comment_patterns(language, false)?;
+            let comments = detect_comments(&source.text, &file, language, &patterns, Engine::Regex)?;
+            let comments = resolve_overlaps(&source.text, comments, false)?;
+            let conversion = convert::convert(&source.text, &comments, language, to)?;
+
+            if stdout {
+                print!("{}", conversion.content);
+            } else if conversion.content != source.text {
+                let encoded = encoding::encode(&conversion.content, &source)?;
+                let strategy = if force { BackupStrategy::None } else { BackupStrategy::Suffix };
+                if let Some(backup_path) = backup::BackupPolicy::new(strategy, None).create(path)? {
+                    info!("Created backup file: {}", backup_path.display().to_string().blue());
+                }
+                history::Journal::open()?.record(path, &source.text, &conversion.content)?;
+                atomic_write::write_atomic(path, &encoded)
+                    .with_context(|| format!("Failed to write modified file: {}", file))?;
+            }
+            info!("Converted {} comments in: {}", conversion.converted, file.green());
+            if conversion.skipped > 0 {
+                info!("Left {} comments unchanged that cannot be converted safely", conversion.skipped);
+            }
+        }
+        Commands::Normalize { files, check, force, exclude, encoding } => {
+            let excludes = ExcludeSet::new(&exclude)?;
+            let forced_encoding = encoding.as_deref().map(encoding::parse_encoding).transpose()?;
+            let targets = expand_paths(
+                &files,
+                false,
+                |file| detect_file_type(&file.to_string_lossy(), &syntax_rules).is_ok(),
+                &excludes,
+            )?;
+            let strategy = if force { BackupStrategy::None } else { BackupStrategy::Suffix };
+            let writer = if check { None } else { Some((backup::BackupPolicy::new(strategy, None), history::Journal::open()?)) };
+            let (mut changed, mut errored) = (0, 0);
+            for target in &targets {
+                let result = normalize_file(target, &syntax_rules, &project_config, forced_encoding, writer.as_ref());
+                match result {
+                    Ok(0) => {}
+                    Ok(normalized) => {
+                        changed += 1;
+                        let verb = if check { "Would normalize" } else { "Normalized" };
+                        info!("{} {} comments in: {}", verb, normalized, target.display().to_string().green());
+                    }
+                    Err(e) => {
+                        errored += 1;
+                        output::file_error(FileFailure::Errored, target.display(), &e);
+                    }
+                }
+            }
+            info!("{} of {} file(s) {}", changed, targets.len(), if check { "need normalizing" } else { "normalized" });
+            if errored > 0 {
+                process::exit(EXIT_FILE_ERRORS);
+            }
+            if check && changed > 0 {
+                process::exit(EXIT_COMMENTS_FOUND);
+            }
+        }
+        Commands::Stats { path, format, files, encoding, engine, max_size } => {
+            check_engine(engine)?;
+            let forced_encoding = encoding.as_deref().map(encoding::parse_encoding).transpose()?;
+            let report = stats::collect(Path::new(&path), &syntax_rules, forced_encoding, engine, max_size)?;
+            print!("{}", stats::render(&report, format, files)?);
+            if report.skipped > 0 {
+                info!("Skipped {} file(s) that could not be read", report.skipped);
+            }
+        }
+        Commands::Check { path, max_comment_ratio, by, encoding, engine, max_size } => {
+            check_engine(engine)?;
+            let budget = budget::Budget::new(max_comment_ratio, &project_config, &syntax_rules)?;
+            let forced_encoding = encoding.as_deref().map(encoding::parse_encoding).transpose()?;
+            let report = stats::collect(Path::new(&path), &syntax_rules, forced_encoding, engine, max_size)?;
+            if report.skipped > 0 {
+                info!("Skipped {} file(s) that could not be read, they are not checked", report.skipped);
+            }
+            let offenders = budget::offenders(&report, &budget, by);
+            if offenders.is_empty() {
+                info!("All {} file(s) are within their comment budget", report.files.len());
+            } else {
+                print!("{}", budget::render(&offenders));
+                info!("{} {}(s) over their comment budget", offenders.len(), format!("{:?}", by).to_lowercase());
+                process::exit(EXIT_COMMENTS_FOUND);
+            }
+        }
+        Commands::Review { files, exclude, mut args } => {
+            // Decisions come from the review, and only what it chose is written
+            args.auto = false;
+            args.check = false;
+            args.output = RemoveOutput::Files;
+            args.report = None;
+            args.no_cache = true;
+            let excludes = ExcludeSet::new(&exclude)?;
+            let mut remover = remove::Remover::new(&syntax_rules, &args, &project_config)?;
+            let targets = expand_paths(&files, args.follow_symlinks, |file| remover.supports(file), &excludes)?;
+
+            remover.set_decisions(remove::Decisions::Collect(RefCell::default()));
+            let mut summary = remove::RunSummary::default();
+            for target in &targets {
+                if let Some((failure, e)) = summary.record(remover.process_file(target)) {
+                    output::file_error(failure, target.display(), &e);
+                }
+            }
+            let mut candidates = remover.take_candidates();
+            if candidates.is_empty() {
+                info!("No removable comments found");
+            } else if !review::run(&mut candidates, &syntax_rules)? {
+                info!("Review cancelled, no files were modified");
+            } else {
+                summary = remove_chosen(&mut remover, &targets, &candidates);
+                info!("{}\n{}", "Summary:".bold(), summary.render(false));
+            }
+            if summary.errored > 0 {
+                process::exit(EXIT_FILE_ERRORS);
+            }
+        }
+        Commands::Watch { dir, exclude, mut args } => {
+            // Nobody is around to answer prompts while watching
+            args.auto = true;
+            args.check = false;
+            args.output = RemoveOutput::Files;
+            args.report = None;
+            args.no_cache = true;
+            let excludes = ExcludeSet::new(&exclude)?;
+            let remover = remove::Remover::new(&syntax_rules, &args, &project_config)?;
+            watch::run(Path::new(&dir), &remover, &excludes)?;
+        }
+        Commands::Serve { lsp: _, mut args } => {
+            // The editor applies the edits, so nothing is prompted for or written here
+            args.auto = true;
+            args.check = false;
+            args.no_cache = true;
+            args.no_history = true;
+            let remover = remove::Remover::new(&syntax_rules, &args, &project_config)?;
+            lsp::serve(&remover)?;
+        }
+        Commands::CleanBackups { path, keep, delete } => {
+            // Any *.bak file matches, including ones the tool did not make, so nothing goes without --delete
+            let removed = backup::clean(Path::new(&path), keep.unwrap_or(0), !delete)?;
+            // The affected paths are the command's output, one per line for scripting
+            for backup in &removed {
+                println!("{}", backup.display());
+            }
+            if delete {
+                info!("{} backup(s) deleted", removed.len());
+            } else {
+                info!("{} backup(s) to delete, run again with --delete to delete them", removed.len());
+            }
+        }
+        Commands::Undo { file, steps, list } => {
+            let journal = history::Journal::open()?;
+            let path = Path::new(&file);
+            if list {
+                // Newest first, numbered by how many steps undo needs to get back before it
+                for (step, entry) in journal.entries(path)?.iter().rev().enumerate() {
+                    println!("{:>3}  {}  {}", step + 1, entry.timestamp, entry.diff.display());
+                }
+            } else {
+                let undone = journal.undo(path, steps)?;
+                for entry in &undone {
+                    info!("Undid modification from {}", entry.timestamp);
+                }
+                info!("Restored {} to its state before {} modification(s)", file.green(), undone.len());
+            }
+        }
+        Commands::Analyze { action: AnalyzeAction::Duplicates { files, exclude, similarity, min_words, format, mut args } } => {
+            // Copies are only removed with --auto, without asking about each
+            let remove_copies = args.auto;
+            args.output = RemoveOutput::Files;
+            args.report = None;
+            args.no_cache = true;
+            check_engine(args.strip.engine)?;
+            let forced_encoding = args.encoding.as_deref().map(encoding::parse_encoding).transpose()?;
+            let excludes = ExcludeSet::new(&exclude)?;
+            let mut remover = remove::Remover::new(&syntax_rules, &args, &project_config)?;
+            let targets = expand_paths(&files, args.follow_symlinks, |file| remover.supports(file), &excludes)?;
+            let groups = duplicates::find(&targets, &remover, forced_encoding, args.strip.engine, min_words, similarity)?;
+            print!("{}", duplicates::render(&groups, format)?);
+            let copies: usize = groups.iter().map(|group| group.occurrences.len()).sum();
+            info!("{} comment(s) found in {} place(s)", groups.len(), copies);
+
+            if remove_copies && !groups.is_empty() {
+                let later = duplicates::later_copies(&groups);
+                let with_copies: Vec<PathBuf> =
+                    targets.into_iter().filter(|target| later.contains_key(&*target.to_string_lossy())).collect();
+                // Only comments the `remove` options allow go, so a kept license header stays
+                remover.set_decisions(remove::Decisions::Collect(RefCell::default()));
+                let mut summary = remove::RunSummary::default();
+                for target in &with_copies {
+                    if let Some((failure, e)) = summary.record(remover.process_file(target)) {
+                        output::file_error(failure, target.display(), &e);
+                    }
+                }
+                let mut candidates = remover.take_candidates();
+                for candidate in &mut candidates {
+                    candidate.remove = later
+                        .get(&candidate.file)
+                        .is_some_and(|lines| lines.iter().any(|lines| lines.contains(&candidate.line)));
+                }
+                if summary.errored == 0 {
+                    summary = remove_chosen(&mut remover, &with_copies, &candidates);
+                }
+                info!("{}\n{}", "Summary:".bold(), summary.render(args.check));
+                if summary.errored > 0 {
+                    process::exit(EXIT_FILE_ERRORS);
+                }
+            }
+        }
+        Commands::Ignore { action } => {
+            let mut ignored = ignore::IgnoreList::open()?;
+            match action {
+                IgnoreAction::List => {
+                    for entry in ignored.entries() {
+                        println!("{}  {}  {}", entry.hash, entry.file, entry.preview);
+                    }
+                    if ignored.entries().is_empty() {
+                        info!("No ignored comments");
+                    }
+                }
+                IgnoreAction::Clear => {
+                    let cleared = ignored.clear()?;
+                    info!("Forgot {} ignored comment(s)", cleared);
+                }
+            }
+        }
+        Commands::AddLanguage { rules } => {
+            let rules_path = match rules {
+                Some(path) => PathBuf::from(path),
+                None => find_rules_file()?,
+            };
+            if let Some(key) = add_language::run(&rules_path, &syntax_rules)? {
+                info!("Added language '{}' to {}", key.green(), rules_path.display());
+            }
+        }
+        Commands::InstallHook { force, pre_commit_config } => {
+            if pre_commit_config {
+                print!("{}", hook::pre_commit_config(&syntax_rules));
+            } else {
+                let hook_path = hook::install(force)?;
+                info!("Installed pre-commit hook: {}", hook_path.display().to_string().green());
+            }
+        }
+        Commands::Languages { format } => {
+            print!("{}", languages::render(&languages::list(&syntax_rules), format)?);
+        }
+        Commands::ValidateRules { .. } | Commands::Completions { .. } => {
+            unreachable!("handled before the rules are loaded")
+        }
+        Commands::Bench { size, runs, max_slowdown, format, args } => {
+            // Only the engine is timed, nothing is read, prompted for or written
+            check_engine(args.engine)?;
+            let mut strip = Options::<StripArgs>::parse_from::<_, &str>([]).args;
+            strip.engine = args.engine;
+            strip.profile = args.profile;
+            let remover = remove::Remover::new(&syntax_rules, &RemoveArgs::stripping(strip), &project_config)?;
+            let report = bench::run(&remover, &syntax_rules, size, runs)?;
+            print!("{}", bench::render(&report, format)?);
+            if let Some(max_slowdown) = max_slowdown {
+                bench::check(&report, max_slowdown)?;
+            }
+        }
+        Commands::Info { generate_man } => {
+            let command = Cli::command().name(help::BIN_NAME);
+            if generate_man {
+                io::stdout().write_all(&help::render_man(command, &syntax_rules)?)?;
+            } else {
+                print!("{}", help::render_info(&command, &syntax_rules));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The rules shipped in syntax_rules.json, for tests.
+#[cfg(test)]
+fn bundled_rules() -> SyntaxRules {
+    Rules::bundled().0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn language(single_line: serde_json::Value) -> LanguageRules {
+        serde_json::from_value(json!({
+            "name": "Test",
+            "extensions": ["test"],
+            "single_line": single_line,
+            "multi_line": [],
+        }))
+        .unwrap()
+    }
+
+    /// The text of every comment found in `content`.
+    fn found(language: &LanguageRules, content: &str) -> Vec<String> {
+        let patterns = get_comment_patterns(language, false).unwrap();
+        find_comments(content, &patterns, &[]).into_iter().map(|comment| content[comment.range].to_string()).collect()
+    }
+
+    #[test]
+    fn line_start_comments_take_their_indentation() {
+        let language = language(json!([{ "pattern": "//", "description": "line" }]));
+        for (content, expected) in [
+            ("// c\n", "// c"),
+            ("    // c\n", "    // c"),
+            ("\t// c\n", "\t// c"),
+            ("\t  \t// c\n", "\t  \t// c"),
+            (" \t \t  // c\n", " \t \t  // c"),
+            ("\x0C// c\n", "\x0C// c"),
+            ("// c", "// c"),
+        ] {
+            assert_eq!(found(&language, content), [expected], "in {:?}", content);
+        }
+    }
+
+    #[test]
+    fn line_start_comments_keep_trailing_whitespace_but_not_line_breaks() {
+        let language = language(json!([{ "pattern": "//", "description": "line" }]));
+        for (content, expected) in [
+            ("// c  \n", "// c  "),
+            ("// c\t\n", "// c\t"),
+            ("  // c \t \n", "  // c \t "),
+            ("// c\r\n", "// c"),
+            ("\t// c  \r\n", "\t// c  "),
+        ] {
+            assert_eq!(found(&language, content), [expected], "in {:?}", content);
+        }
+    }
+
+    #[test]
+    fn line_start_comments_do_not_reach_into_other_lines() {
+        let language = language(json!([{ "pattern": "//", "description": "line" }]));
+        assert_eq!(found(&language, "\n\n  // c\n"), ["  // c"]);
+        assert_eq!(found(&language, "\r\n\t\r\n\t// c\r\n"), ["\t// c"]);
+        assert_eq!(found(&language, "// a\n\t// b\n"), ["// a", "\t// b"]);
+    }
+
+    #[test]
+    fn line_start_rules_skip_comments_after_code() {
+        let language = language(json!([{ "pattern": "//", "description": "line" }]));
+        for content in ["x; // c\n", "x;\t// c\n", "\tx;  // c  \n"] {
+            assert!(found(&language, content).is_empty(), "in {:?}", content);
+        }
+    }
+
+    #[test]
+    fn trailing_comments_start_at_the_marker() {
+        let language = language(json!([{ "pattern": "#", "description": "line", "must_start_line": false }]));
+        for (content, expected) in [
+            ("x = 1 # c\n", "# c"),
+            ("x = 1\t# c\n", "# c"),
+            ("\tx = 1  \t# c  \n", "# c  "),
+            ("x = 1# c\r\n", "# c"),
+            ("  # c\n", "  # c"),
+            ("\t# c\n", "\t# c"),
+        ] {
+            assert_eq!(found(&language, content), [expected], "in {:?}", content);
+        }
+    }
+
+    #[test]
+    fn word_markers_begin_a_token() {
+        let language = language(json!([
+            { "pattern": "REM", "description": "line", "word_boundary": true, "must_start_line": false }
+        ]));
+        for (content, expected) in [
+            ("REM c\n", vec!["REM c"]),
+            ("\tREM c\n", vec!["\tREM c"]),
+            ("echo x REM c\n", vec!["REM c"]),
+            ("echo x\tREM\n", vec!["REM"]),
+            ("XREM c\n", vec![]),
+            ("echo PREM c\n", vec![]),
+            ("REMOVE c\n", vec![]),
+        ] {
+            assert_eq!(found(&language, content), expected, "in {:?}", content);
+        }
+    }
+
+    #[test]
+    fn stripper_reuses_its_options_for_every_text() {
+        let rules = Rules::bundled();
+        let stripper = Stripper::new(&rules, &["--preserve-lines"]).unwrap();
+        assert_eq!(stripper.strip("let x = 1;\n// one\nlet y = 2;\n", "rust").unwrap().content, "let x = 1;\n\nlet y = 2;\n");
+        assert_eq!(stripper.strip("# one\nx = 1\n", "Python").unwrap().content, "\nx = 1\n");
+        assert_eq!(stripper.strip("int x; /* a */\n", "c").unwrap().content, "int x;\n");
+        assert!(stripper.strip("x\n", "no such language").is_err());
+    }
+
+    #[test]
+    fn rules_with_a_bad_flag_are_refused_when_read() {
+        let rules = |flags: &str| {
+            json!({
+                "schema": 2,
+                "x": { "name": "X", "extensions": ["x"], "single_line": [{ "pattern": "#", "description": "line", "flags": flags }], "multi_line": [] }
+            })
+            .to_string()
+        };
+        Rules::from_json(&rules("i")).unwrap();
+        let error = Rules::from_json(&rules("z")).err().unwrap();
+        assert!(matches!(error.downcast_ref::<Error>(), Some(Error::SyntaxRules(_))), "{:#}", error);
+        assert!(error.to_string().contains("'x' single_line[0]"), "{:#}", error);
+    }
+
+    #[test]
+    fn stripper_only_takes_stripping_options() {
+        let rules = Rules::bundled();
+        assert!(Stripper::new(&rules, &["--strip-docstrings", "--profile", "aggressive"]).is_ok());
+        for options in [&["--backup-dir", "b"][..], &["--out-dir", "o"], &["--older-than", "1y"], &["--replay", "r"]] {
+            assert!(Stripper::new(&rules, options).is_err(), "{:?}", options);
+        }
+    }
+
+    #[test]
+    fn nested_block_comments_end_at_the_outermost_close() {
+        let rules = Rules::bundled();
+        let stripper = Stripper::new(&rules, &[]).unwrap();
+        for language in ["kotlin", "swift", "scala"] {
+            for (source, expected) in [
+                ("/* a /* b */ c */\nx\n", "\nx\n"),
+                ("/* a /* b /* c */ d */ e */\nx\n", "\nx\n"),
+                ("/** a /* b */ c */\nx\n", "\nx\n"),
+                ("/* a */\nx\n/* b */\n", "\nx\n\n"),
+            ] {
+                assert_eq!(stripper.strip(source, language).unwrap().content, expected, "{} in {:?}", language, source);
+            }
+        }
+        // C# comments do not nest
+        assert_eq!(stripper.strip("/* a /* b */ c */\nx\n", "csharp").unwrap().content, " c */\nx\n");
+    }
+
+    #[test]
+    fn final_newline_follows_the_original() {
+        for (original, result, expected) in [
+            ("a\n", "a", "a\n"),
+            ("a\r\n", "a", "a\r\n"),
+            ("a\n", "a\n", "a\n"),
+            ("a", "a\n", "a"),
+            ("a", "a\r\n", "a"),
+            ("a", "a", "a"),
+            ("// c\n", "", ""),
+        ] {
+            assert_eq!(preserve_final_newline(original, result.to_string()), expected, "{:?} to {:?}", original, result);
+        }
+    }
+
+    #[test]
+    fn line_endings_and_the_final_newline_survive_stripping() {
+        let rules = Rules::bundled();
+        let stripper = Stripper::new(&rules, &[]).unwrap();
+        for (source, expected) in [
+            ("int a;\r\n// c\r\nint b;\r\n", "int a;\r\n\r\nint b;\r\n"),
+            ("int a; /* c */\r\nint b;\r\n", "int a;\r\nint b;\r\n"),
+            ("/* a\r\n   b */\r\nint a;\r\n", "\r\nint a;\r\n"),
+            ("int a;\r\n/* c */", "int a;"),
+            ("int a; /* c */", "int a;"),
+            ("int a; /* c */\n", "int a;\n"),
+            ("int a;\n// c\n", "int a;\n\n"),
+        ] {
+            assert_eq!(stripper.strip(source, "c").unwrap().content, expected, "in {:?}", source);
+        }
+    }
+
+    #[test]
+    fn only_modified_lines_are_trimmed() {
+        let content = "a;   \nb; \t\r\nc;  \n".to_string();
+        assert_eq!(trim_modified_lines(content.clone(), &[]), content);
+        // Replacements at the end of the first line and inside the third
+        assert_eq!(trim_modified_lines(content.clone(), &[5..5, 15..15]), "a;\nb; \t\r\nc;\n");
+        // A replacement ending in the first line's break does not reach the second line
+        assert_eq!(trim_modified_lines(content, std::slice::from_ref(&(3..6))), "a;\nb; \t\r\nc;  \n");
+    }
+
+    #[test]
+    fn removing_trailing_comments_leaves_no_trailing_whitespace() {
+        let rules = Rules::bundled();
+        let stripper = Stripper::new(&rules, &[]).unwrap();
+        let source = "int a;    /* c */\nint bb;   /* d */ \nint c;  \n";
+        assert_eq!(stripper.strip(source, "c").unwrap().content, "int a;\nint bb;\nint c;  \n");
+    }
+
+    #[test]
+    fn overlapping_comments_keep_the_first_or_fail_with_strict() {
+        let content = "/* a // b */\nx\n";
+        let comments = || {
+            vec![
+                Comment { kind: CommentKind::Line, range: 5..12 },
+                Comment { kind: CommentKind::Block, range: 0..12 },
+                Comment { kind: CommentKind::Block, range: 0..12 },
+            ]
+        };
+        let resolved = resolve_overlaps(content, comments(), false).unwrap();
+        assert_eq!(resolved.iter().map(|comment| comment.range.clone()).collect::<Vec<_>>(), vec![0..12]);
+
+        let error = resolve_overlaps(content, comments(), true).err().unwrap();
+        assert!(matches!(error.downcast_ref::<Error>(), Some(Error::OverlappingComments(_))));
+        assert!(error.to_string().contains("`/* a // b */` on line 1 and `// b */` on line 1"), "{}", error);
+        // The same comment found twice is no conflict
+        assert!(resolve_overlaps(content, comments().split_off(1), true).is_ok());
+    }
+
+    #[test]
+    fn line_markers_inside_block_comments_are_no_overlap() {
+        let rules = Rules::bundled();
+        let stripper = Stripper::new(&rules, &["--strict"]).unwrap();
+        let result = stripper.strip("/* a // b */\nx;\n/* c\n// d */ y;\n", "c").unwrap();
+        assert_eq!(result.content, "\nx;\n y;\n");
+        assert_eq!(result.comments.iter().map(|comment| comment.text.as_str()).collect::<Vec<_>>(), ["/* a // b */", "/* c\n// d */"]);
+    }
+}
@@ -0,0 +1,1058 @@
+use std::{ops::Range, path::Path};
+
+use crate::{Comment, LanguageRules};
+
+/// Regions found by lexing a file.
+#[derive(Default)]
+pub struct Literals {
+    /// Byte ranges of string literals, raw strings, template literal text, JSX text and
+    /// the HTML around PHP tags, plus a leading shebang line.
+    /// Comment markers starting inside these ranges are part of the string.
+    pub strings: Vec<Range<usize>>,
+    /// JSX expression containers (`{...}` between tags), including the braces
+    pub jsx_expressions: Vec<Range<usize>>,
+}
+
+impl Literals {
+    /// Widens comments that are all a JSX expression container holds to the whole `{/* ... */}`,
+    /// since removing only the comment would leave an empty `{}` behind.
+    pub fn widen_jsx_comments(&self, content: &str, comments: Vec<Comment>) -> Vec<Comment> {
+        if self.jsx_expressions.is_empty() {
+            return comments;
+        }
+        comments
+            .into_iter()
+            .map(|comment| {
+                let container = self.jsx_expressions.iter().find(|container| {
+                    let inner = container.start + 1..container.end - 1;
+                    inner.start <= comment.range.start
+                        && comment.range.end <= inner.end
+                        && content[inner.start..comment.range.start].trim().is_empty()
+                        && content[comment.range.end..inner.end].trim().is_empty()
+                });
+                match container {
+                    Some(container) => Comment { range: container.clone(), ..comment },
+                    None => comment,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Lexes `content` when `language` has a lexer; JSX is recognised in `.jsx` and `.tsx` files.
+pub fn scan(content: &str, language: &LanguageRules, file_path: &str) -> Literals {
+    let extension = Path::new(file_path).extension().and_then(|ext| ext.to_str());
+    let mut lexer = Lexer {
+        text: content,
+        bytes: content.as_bytes(),
+        pos: 0,
+        jsx: matches!(extension, Some("jsx" | "tsx")),
+        literals: Literals::default(),
+    };
+    match language.name.as_str() {
+        "Rust" => lexer.rust(),
+        "JavaScript" | "TypeScript" => lexer.javascript(false),
+        "PHP" => lexer.php(),
+        "CSS" => lexer.stylesheet(false),
+        "SCSS" | "Sass" | "Less" => lexer.stylesheet(true),
+        "Ruby" => lexer.ruby_or_perl(false),
+        "Perl" => lexer.ruby_or_perl(true),
+        "Kotlin" => lexer.curly(Dialect::Kotlin),
+        "Swift" => lexer.curly(Dialect::Swift),
+        "Scala" => lexer.curly(Dialect::Scala),
+        "C#" => lexer.curly(Dialect::CSharp),
+        "Zig" => lexer.curly(Dialect::Zig),
+        _ => {}
+    }
+    // A shebang looks like a `#` comment but has to stay
+    if content.starts_with("#!") && !content.starts_with("#![") {
+        lexer.literals.strings.push(0..content.find('\n').unwrap_or(content.len()));
+    }
+    // Lookups binary search by start
+    lexer.literals.strings.sort_by_key(|literal| literal.start);
+    lexer.literals
+}
+
+/// The languages `Lexer::curly` handles, which share C-style comments and strings.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Dialect {
+    Kotlin,
+    Swift,
+    Scala,
+    CSharp,
+    Zig,
+}
+
+// Every delimiter is ASCII, so scanning bytes never splits a UTF-8 character
+struct Lexer<'a> {
+    text: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+    jsx: bool,
+    literals: Literals,
+}
+
+impl Lexer<'_> {
+    fn peek(&self, offset: usize) -> Option<u8> {
+        self.bytes.get(self.pos + offset).copied()
+    }
+
+    fn starts_with(&self, text: &str) -> bool {
+        self.bytes[self.pos..].starts_with(text.as_bytes())
+    }
+
+    fn skip_line(&mut self) {
+        while self.pos < self.bytes.len() && self.bytes[self.pos] != b'\n' {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_block_comment(&mut self, nested: bool) {
+        let mut depth = 0;
+        while self.pos < self.bytes.len() {
+            if self.starts_with("/*") && (nested || depth == 0) {
+                depth += 1;
+                self.pos += 2;
+            } else if self.starts_with("*/") {
+                depth -= 1;
+                self.pos += 2;
+                if depth == 0 {
+                    return;
+                }
+            } else {
+                self.pos += 1;
+            }
+        }
+    }
+
+    /// Skips a quoted string with backslash escapes, starting at the opening quote.
+    fn quoted(&mut self, quote: u8, stop_at_newline: bool) {
+        let start = self.pos;
+        self.pos += 1;
+        while let Some(byte) = self.peek(0) {
+            self.pos += 1;
+            match byte {
+                b'\\' => self.pos += 1,
+                b'\n' if stop_at_newline => break,
+                _ if byte == quote => break,
+                _ => {}
+            }
+        }
+        self.pos = self.pos.min(self.bytes.len());
+        self.literals.strings.push(start..self.pos);
+    }
+
+    fn rust(&mut self) {
+        while let Some(byte) = self.peek(0) {
+            match byte {
+                b'/' if self.peek(1) == Some(b'/') => self.skip_line(),
+                b'/' if self.peek(1) == Some(b'*') => self.skip_block_comment(true),
+                b'"' => self.quoted(b'"', false),
+                b'\'' => self.char_literal(),
+                _ if byte.is_ascii_alphabetic() || byte == b'_' => self.rust_identifier(),
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    fn rust_identifier(&mut self) {
+        let start = self.pos;
+        while self.peek(0).is_some_and(|byte| byte.is_ascii_alphanumeric() || byte == b'_') {
+            self.pos += 1;
+        }
+        let is_raw_prefix = matches!(&self.bytes[start..self.pos], b"r" | b"br" | b"cr");
+        if !is_raw_prefix || !matches!(self.peek(0), Some(b'"' | b'#')) {
+            return;
+        }
+
+        // r#"..."# ends at a quote followed by the same number of hashes
+        let mut hashes = 0;
+        while self.peek(hashes) == Some(b'#') {
+            hashes += 1;
+        }
+        if self.peek(hashes) != Some(b'"') {
+            return;
+        }
+        let mut terminator = vec![b'"'];
+        terminator.extend(std::iter::repeat_n(b'#', hashes));
+        let body = self.pos + hashes + 1;
+        self.pos = self.bytes[body..]
+            .windows(terminator.len())
+            .position(|window| window == terminator.as_slice())
+            .map_or(self.bytes.len(), |i| body + i + terminator.len());
+        self.literals.strings.push(start..self.pos);
+    }
+
+    fn char_literal(&mut self) {
+        // 'a' and '\n' are characters, 'a on its own is a Rust lifetime or label or a Scala symbol
+        if self.peek(1) == Some(b'\\') {
+            self.quoted(b'\'', true);
+            return;
+        }
+        let char_len = self.text[self.pos + 1..].chars().next().map_or(1, char::len_utf8);
+        if self.peek(1 + char_len) == Some(b'\'') {
+            // Recorded so bracket matching over Rust source can skip '{' and friends
+            self.literals.strings.push(self.pos..self.pos + char_len + 2);
+            self.pos += char_len + 2;
+        } else {
+            self.pos += 1;
+        }
+    }
+
+    /// Lexes JavaScript until the end of input, or until the `}` closing a `${` when `interpolation` is set.
+    fn javascript(&mut self, interpolation: bool) {
+        let mut braces: usize = 0;
+        // Whether the next token is an operand: there a `/` starts a regex and a `<` an element,
+        // after an operand they divide and compare
+        let mut expects_operand = true;
+        while let Some(byte) = self.peek(0) {
+            match byte {
+                b'/' if self.peek(1) == Some(b'/') => {
+                    self.skip_line();
+                    continue;
+                }
+                b'/' if self.peek(1) == Some(b'*') => {
+                    self.skip_block_comment(false);
+                    continue;
+                }
+                _ if byte.is_ascii_whitespace() => {
+                    self.pos += 1;
+                    continue;
+                }
+                b'/' if expects_operand => self.regex_literal(),
+                b'<' if self.jsx && expects_operand && self.peek(1).is_some_and(|next| next.is_ascii_alphabetic() || next == b'>') => {
+                    self.jsx_element();
+                }
+                b'"' | b'\'' => self.quoted(byte, true),
+                b'`' => self.template(),
+                b'{' => {
+                    braces += 1;
+                    self.pos += 1;
+                }
+                b'}' => {
+                    if interpolation && braces == 0 {
+                        return;
+                    }
+                    braces = braces.saturating_sub(1);
+                    self.pos += 1;
+                }
+                _ if byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'$' => {
+                    let start = self.pos;
+                    while self.peek(0).is_some_and(|byte| byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'$') {
+                        self.pos += 1;
+                    }
+                    // Keywords that end a statement or start an expression are not operands
+                    let word = &self.bytes[start..self.pos];
+                    let keyword = [&b"return"[..], b"yield", b"await", b"case", b"default", b"else", b"typeof", b"in", b"of"]
+                        .contains(&word);
+                    expects_operand = keyword;
+                    continue;
+                }
+                _ => self.pos += 1,
+            }
+            // Literals, elements and closing brackets are operands
+            expects_operand = !b"\"'`/<)]}".contains(&byte);
+        }
+    }
+
+    /// Lexes a JSX element starting at its `<`, including its children and closing tag.
+    fn jsx_element(&mut self) {
+        self.pos += 1;
+        while let Some(byte) = self.peek(0) {
+            match byte {
+                b'/' if self.peek(1) == Some(b'>') => {
+                    self.pos += 2;
+                    return;
+                }
+                b'/' if self.peek(1) == Some(b'/') => self.skip_line(),
+                b'/' if self.peek(1) == Some(b'*') => self.skip_block_comment(false),
+                b'>' => {
+                    self.pos += 1;
+                    self.jsx_children();
+                    return;
+                }
+                // Attribute strings may span lines and have no escapes
+                b'"' | b'\'' => {
+                    let start = self.pos;
+                    self.pos += 1;
+                    while self.peek(0).is_some_and(|next| next != byte) {
+                        self.pos += 1;
+                    }
+                    self.pos = (self.pos + 1).min(self.bytes.len());
+                    self.literals.strings.push(start..self.pos);
+                }
+                b'{' => {
+                    self.pos += 1;
+                    self.javascript(true);
+                    self.pos = (self.pos + 1).min(self.bytes.len());
+                }
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    /// Lexes element children up to and including the closing tag. Text is protected,
+    /// `{...}` containers are code in which comments are real.
+    fn jsx_children(&mut self) {
+        let mut text_start = self.pos;
+        while let Some(byte) = self.peek(0) {
+            match byte {
+                b'{' => {
+                    self.push_text(text_start);
+                    let start = self.pos;
+                    self.pos += 1;
+                    self.javascript(true);
+                    self.pos = (self.pos + 1).min(self.bytes.len());
+                    self.literals.jsx_expressions.push(start..self.pos);
+                    text_start = self.pos;
+                }
+                b'<' if self.peek(1) == Some(b'/') => {
+                    self.push_text(text_start);
+                    while self.peek(0).is_some_and(|next| next != b'>') {
+                        self.pos += 1;
+                    }
+                    self.pos = (self.pos + 1).min(self.bytes.len());
+                    return;
+                }
+                b'<' => {
+                    self.push_text(text_start);
+                    self.jsx_element();
+                    text_start = self.pos;
+                }
+                _ => self.pos += 1,
+            }
+        }
+        self.push_text(text_start);
+    }
+
+    /// HTML outside `<?php ... ?>` is protected, except for its `<!-- -->` comments.
+    fn php(&mut self) {
+        let mut text_start = self.pos;
+        while self.pos < self.bytes.len() {
+            if self.starts_with("<!--") {
+                self.push_text(text_start);
+                self.pos = self.find_from(self.pos + 4, "-->").map_or(self.bytes.len(), |end| end + 3);
+                text_start = self.pos;
+            } else if self.starts_with("<?") {
+                self.pos += 2;
+                if self.starts_with("php") {
+                    self.pos += 3;
+                } else if self.starts_with("=") {
+                    self.pos += 1;
+                }
+                self.push_text(text_start);
+                self.php_code();
+                text_start = self.pos;
+            } else {
+                self.pos += 1;
+            }
+        }
+        self.push_text(text_start);
+    }
+
+    /// Lexes PHP code up to, but not including, the closing `?>`.
+    fn php_code(&mut self) {
+        while let Some(byte) = self.peek(0) {
+            match byte {
+                b'?' if self.peek(1) == Some(b'>') => return,
+                // Line comments end at the closing tag as well as at the end of the line
+                b'/' if self.peek(1) == Some(b'/') => self.skip_php_line_comment(),
+                b'#' if self.peek(1) != Some(b'[') => self.skip_php_line_comment(),
+                b'/' if self.peek(1) == Some(b'*') => self.skip_block_comment(false),
+                b'"' | b'\'' => self.quoted(byte, false),
+                b'<' if self.starts_with("<<<") => self.heredoc(),
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    fn skip_php_line_comment(&mut self) {
+        while self.peek(0).is_some_and(|byte| byte != b'\n') && !self.starts_with("?>") {
+            self.pos += 1;
+        }
+    }
+
+    /// Strings and unquoted `url(...)` values, which can hold `/*` or `//` in a data URI or a
+    /// protocol-relative address; `//` only starts a comment when `line_comments` is set.
+    fn stylesheet(&mut self, line_comments: bool) {
+        while let Some(byte) = self.peek(0) {
+            match byte {
+                b'/' if self.peek(1) == Some(b'*') => self.skip_block_comment(false),
+                b'/' if line_comments && self.peek(1) == Some(b'/') => self.skip_line(),
+                b'"' | b'\'' => self.quoted(byte, true),
+                b'u' | b'U' if self.text[self.pos..].get(..4).is_some_and(|name| name.eq_ignore_ascii_case("url(")) => {
+                    self.pos += 4;
+                    let value = self.pos + self.text[self.pos..].len() - self.text[self.pos..].trim_start().len();
+                    // A quoted URL is an ordinary string
+                    if !matches!(self.bytes.get(value), Some(b'"' | b'\'')) {
+                        let end = self.find_from(self.pos, ")").unwrap_or(self.bytes.len());
+                        self.literals.strings.push(self.pos..end);
+                        self.pos = end;
+                    }
+                }
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    /// Lexes Kotlin, Swift, Scala, C# or Zig: strings including the raw, verbatim and multi-line
+    /// kinds, characters and backquoted names. Quotes inside an interpolation end the string and
+    /// start another, which still covers all of it but the code in between.
+    fn curly(&mut self, dialect: Dialect) {
+        while let Some(byte) = self.peek(0) {
+            match byte {
+                b'/' if self.peek(1) == Some(b'/') => self.skip_line(),
+                // Zig has no block comments
+                b'/' if self.peek(1) == Some(b'*') && dialect != Dialect::Zig => {
+                    self.skip_block_comment(dialect != Dialect::CSharp);
+                }
+                b'"' => self.curly_string(self.pos, 0, dialect),
+                // $"..." interpolates, @"..." is verbatim and $@"..." both
+                b'$' | b'@' if dialect == Dialect::CSharp => {
+                    let start = self.pos;
+                    while matches!(self.peek(0), Some(b'$' | b'@')) {
+                        self.pos += 1;
+                    }
+                    if self.peek(0) == Some(b'"') {
+                        if self.bytes[start..self.pos].contains(&b'@') {
+                            self.verbatim_string(start);
+                        } else {
+                            self.curly_string(start, 0, dialect);
+                        }
+                    }
+                }
+                // #"..."# is a raw string, `#if` and friends are not
+                b'#' if dialect == Dialect::Swift => {
+                    let start = self.pos;
+                    while self.peek(0) == Some(b'#') {
+                        self.pos += 1;
+                    }
+                    if self.peek(0) == Some(b'"') {
+                        self.curly_string(start, self.pos - start, dialect);
+                    }
+                }
+                // A `\\` line is one line of a multi-line string
+                b'\\' if dialect == Dialect::Zig && self.peek(1) == Some(b'\\') => {
+                    let start = self.pos;
+                    self.skip_line();
+                    self.literals.strings.push(start..self.pos);
+                }
+                b'\'' if dialect != Dialect::Swift => self.char_literal(),
+                b'`' if matches!(dialect, Dialect::Kotlin | Dialect::Swift | Dialect::Scala) => self.quoted(b'`', true),
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    /// A string from `start`, where any prefix begins, whose quotes are at the current position:
+    /// `"..."`, or `"""..."""` spanning lines. A Swift raw string opened with `hashes` `#` needs
+    /// as many after its closing quotes and after the backslash of an escape.
+    fn curly_string(&mut self, start: usize, hashes: usize, dialect: Dialect) {
+        let quotes = self.bytes[self.pos..].iter().take_while(|&&byte| byte == b'"').count();
+        // `""` is an empty string, three or more quotes open a multi-line one
+        let quotes = if quotes >= 3 { quotes } else { 1 };
+        let mut terminator = "\"".repeat(quotes);
+        terminator.push_str(&"#".repeat(hashes));
+        let escape = format!("\\{}", "#".repeat(hashes));
+        // Multi-line strings are raw except in Swift
+        let escapes = quotes == 1 || dialect == Dialect::Swift;
+        self.pos += quotes;
+        while self.pos < self.bytes.len() {
+            if escapes && self.starts_with(&escape) {
+                self.pos += escape.len() + 1;
+            } else if self.starts_with(&terminator) {
+                self.pos += terminator.len();
+                // Quotes right before the closing ones belong to the string
+                while quotes >= 3 && self.peek(0) == Some(b'"') {
+                    self.pos += 1;
+                }
+                break;
+            } else if quotes == 1 && self.peek(0) == Some(b'\n') {
+                break;
+            } else {
+                self.pos += 1;
+            }
+        }
+        self.pos = self.pos.min(self.bytes.len());
+        self.literals.strings.push(start..self.pos);
+    }
+
+    /// A C# verbatim string `@"..."` from `start`, which spans lines and doubles quotes instead
+    /// of escaping them.
+    fn verbatim_string(&mut self, start: usize) {
+        self.pos += 1;
+        while let Some(byte) = self.peek(0) {
+            self.pos += 1;
+            if byte == b'"' {
+                if self.peek(0) != Some(b'"') {
+                    break;
+                }
+                self.pos += 1;
+            }
+        }
+        self.literals.strings.push(start..self.pos);
+    }
+
+    /// Lexes Ruby, or Perl with `perl`: strings, regexes, heredoc bodies, quote-like literals
+    /// such as `%q{}` or `qw()`, and everything after `__END__`. Embedded documentation is
+    /// skipped so quotes in it are not taken for strings.
+    fn ruby_or_perl(&mut self, perl: bool) {
+        // Heredocs started on the current line, whose bodies begin on the next one
+        let mut heredocs: Vec<(String, bool)> = Vec::new();
+        let mut expects_operand = true;
+        while let Some(byte) = self.peek(0) {
+            if self.pos == 0 || self.bytes[self.pos - 1] == b'\n' {
+                if self.starts_with("__END__") || (perl && self.starts_with("__DATA__")) {
+                    self.literals.strings.push(self.pos..self.bytes.len());
+                    return;
+                }
+                if self.embedded_docs(perl) {
+                    continue;
+                }
+            }
+            match byte {
+                b'\n' => {
+                    self.pos += 1;
+                    for (terminator, indented) in std::mem::take(&mut heredocs) {
+                        self.heredoc_body(&terminator, indented);
+                    }
+                    expects_operand = true;
+                    continue;
+                }
+                b'#' => {
+                    self.skip_line();
+                    continue;
+                }
+                _ if byte.is_ascii_whitespace() => {
+                    self.pos += 1;
+                    continue;
+                }
+                // Special variables like $" and $# are not quotes or comments
+                b'$' if self.peek(1).is_some_and(|next| next.is_ascii_punctuation()) => self.pos += 2,
+                b'"' | b'\'' | b'`' => self.quoted(byte, false),
+                b'/' if expects_operand => self.regex_literal(),
+                b'<' if self.starts_with("<<") => match self.heredoc_start() {
+                    Some(heredoc) => heredocs.push(heredoc),
+                    None => self.pos += 2,
+                },
+                b'%' if !perl => self.percent_literal(expects_operand),
+                _ if byte.is_ascii_alphanumeric() || byte == b'_' => {
+                    let start = self.pos;
+                    while self.peek(0).is_some_and(|byte| byte.is_ascii_alphanumeric() || byte == b'_') {
+                        self.pos += 1;
+                    }
+                    let word = &self.bytes[start..self.pos];
+                    if perl && self.perl_quote_like(start, word) {
+                        expects_operand = false;
+                        continue;
+                    }
+                    let keyword = [
+                        &b"if"[..], b"unless", b"while", b"until", b"and", b"or", b"not", b"return", b"when",
+                        b"split", b"grep", b"map", b"join", b"push", b"print", b"puts",
+                    ]
+                    .contains(&word);
+                    expects_operand = keyword;
+                    continue;
+                }
+                _ => self.pos += 1,
+            }
+            expects_operand = !b"\"'`/)]}".contains(&byte);
+        }
+    }
+
+    /// Skips Ruby `=begin ... =end` or a Perl POD block starting at the current line start.
+    fn embedded_docs(&mut self, perl: bool) -> bool {
+        let end_marker = if perl {
+            if !(self.peek(0) == Some(b'=') && self.peek(1).is_some_and(|byte| byte.is_ascii_alphabetic())) {
+                return false;
+            }
+            "\n=cut"
+        } else {
+            if !self.starts_with("=begin") || self.peek(6).is_some_and(|byte| !byte.is_ascii_whitespace()) {
+                return false;
+            }
+            "\n=end"
+        };
+        self.pos = self.find_from(self.pos, end_marker).map_or(self.bytes.len(), |end| end + end_marker.len());
+        self.skip_line();
+        true
+    }
+
+    /// Reads `<<ID`, `<<~ID`, `<<-ID` or a quoted form, leaving the position after it. A bare
+    /// identifier has to start with a capital letter, so `x <<y` stays a shift.
+    fn heredoc_start(&mut self) -> Option<(String, bool)> {
+        let mut at = self.pos + 2;
+        let indented = matches!(self.bytes.get(at), Some(b'~' | b'-'));
+        if indented {
+            at += 1;
+        }
+        let quote = self.bytes.get(at).copied().filter(|byte| matches!(byte, b'"' | b'\'' | b'`'));
+        if quote.is_some() {
+            at += 1;
+        }
+        let start = at;
+        while self.bytes.get(at).is_some_and(|byte| byte.is_ascii_alphanumeric() || *byte == b'_') {
+            at += 1;
+        }
+        let identifier = &self.text[start..at];
+        let first = identifier.bytes().next()?;
+        if first.is_ascii_digit() || (quote.is_none() && !indented && !(first.is_ascii_uppercase() || first == b'_')) {
+            return None;
+        }
+        if let Some(quote) = quote {
+            if self.bytes.get(at) != Some(&quote) {
+                return None;
+            }
+            at += 1;
+        }
+        self.pos = at;
+        Some((identifier.to_string(), indented))
+    }
+
+    /// Protects the lines up to the one holding only `terminator`, starting at a line start.
+    fn heredoc_body(&mut self, terminator: &str, indented: bool) {
+        let start = self.pos;
+        while self.pos < self.bytes.len() {
+            let line_end = self.find_from(self.pos, "\n").unwrap_or(self.bytes.len());
+            let line = self.text[self.pos..line_end].trim_end_matches('\r');
+            let line = if indented { line.trim_start() } else { line };
+            if line == terminator {
+                self.literals.strings.push(start..self.pos);
+                self.pos = line_end;
+                return;
+            }
+            self.pos = (line_end + 1).min(self.bytes.len());
+        }
+        self.literals.strings.push(start..self.pos);
+    }
+
+    /// Ruby `%q{...}`, `%w[...]`, `%(...)` and friends; a bare `%` is only a literal where an
+    /// operand is expected, elsewhere it is the modulo operator.
+    fn percent_literal(&mut self, expects_operand: bool) {
+        let start = self.pos;
+        let opener = match (self.peek(1), self.peek(2)) {
+            (Some(b'q' | b'Q' | b'w' | b'W' | b'i' | b'I' | b'r' | b's' | b'x'), Some(delimiter))
+                if delimiter.is_ascii_punctuation() =>
+            {
+                self.pos + 2
+            }
+            (Some(b'(' | b'[' | b'{' | b'<' | b'|' | b'!' | b'/' | b'^'), _) if expects_operand => self.pos + 1,
+            _ => {
+                self.pos += 1;
+                return;
+            }
+        };
+        self.pos = opener;
+        self.delimited();
+        self.literals.strings.push(start..self.pos);
+    }
+
+    /// Perl's `q()`, `qq{}`, `qw//`, `qr##`, `m{}`, `s{}{}`, `tr///` and `y///`, for the word
+    /// just read starting at `start`. Returns false when the word is something else, like a
+    /// hash key or a sub named `s`.
+    fn perl_quote_like(&mut self, start: usize, word: &[u8]) -> bool {
+        let parts = match word {
+            b"q" | b"qq" | b"qw" | b"qr" | b"m" => 1,
+            b"s" | b"tr" | b"y" => 2,
+            _ => return false,
+        };
+        let sigil = start > 0 && matches!(self.bytes[start - 1], b'$' | b'@' | b'%' | b'&' | b'>' | b':' | b'{');
+        let opens = self.peek(0).is_some_and(|byte| {
+            byte.is_ascii_punctuation() && !matches!(byte, b'=' | b',' | b';' | b')' | b']' | b'}' | b'>' | b'_')
+        });
+        if sigil || !opens {
+            return false;
+        }
+        let bracketed = matches!(self.peek(0), Some(b'(' | b'[' | b'{' | b'<'));
+        self.delimited();
+        if parts == 2 {
+            if bracketed {
+                while self.peek(0).is_some_and(|byte| byte.is_ascii_whitespace()) {
+                    self.pos += 1;
+                }
+                if self.peek(0).is_some() {
+                    self.delimited();
+                }
+            } else {
+                // The replacement reuses the closing delimiter as its own
+                self.pos -= 1;
+                self.delimited();
+            }
+        }
+        self.literals.strings.push(start..self.pos);
+        true
+    }
+
+    /// Skips from an opening delimiter past its closing one; brackets nest.
+    fn delimited(&mut self) {
+        let Some(open) = self.peek(0) else { return };
+        let close = match open {
+            b'(' => b')',
+            b'[' => b']',
+            b'{' => b'}',
+            b'<' => b'>',
+            _ => open,
+        };
+        let mut depth = 0;
+        self.pos += 1;
+        while let Some(byte) = self.peek(0) {
+            self.pos += 1;
+            match byte {
+                b'\\' => self.pos += 1,
+                _ if byte == close && depth == 0 => break,
+                _ if byte == close => depth -= 1,
+                _ if byte == open => depth += 1,
+                _ => {}
+            }
+        }
+        self.pos = self.pos.min(self.bytes.len());
+    }
+
+    /// `<<<ID ... ID` and `<<<'ID' ... ID`, where the closing identifier starts a line.
+    fn heredoc(&mut self) {
+        let start = self.pos;
+        let line_end = self.find_from(self.pos, "\n").unwrap_or(self.bytes.len());
+        let identifier = self.text[self.pos + 3..line_end].trim().trim_matches(['\'', '"']);
+        if identifier.is_empty() {
+            self.pos += 3;
+            return;
+        }
+        let mut search = line_end;
+        self.pos = loop {
+            let Some(found) = self.find_from(search, identifier) else {
+                break self.bytes.len();
+            };
+            let line_start = self.text[..found].rfind('\n').map_or(0, |i| i + 1);
+            if self.text[line_start..found].trim().is_empty() {
+                break found + identifier.len();
+            }
+            search = found + identifier.len();
+        };
+        self.literals.strings.push(start..self.pos);
+    }
+
+    fn find_from(&self, from: usize, needle: &str) -> Option<usize> {
+        self.text[from..].find(needle).map(|i| from + i)
+    }
+
+    fn push_text(&mut self, start: usize) {
+        if start < self.pos {
+            self.literals.strings.push(start..self.pos);
+        }
+    }
+
+    fn regex_literal(&mut self) {
+        let start = self.pos;
+        self.pos += 1;
+        let mut in_class = false;
+        while let Some(byte) = self.peek(0) {
+            self.pos += 1;
+            match byte {
+                b'\\' => self.pos += 1,
+                b'[' => in_class = true,
+                b']' => in_class = false,
+                b'/' if !in_class => break,
+                b'\n' => break,
+                _ => {}
+            }
+        }
+        self.pos = self.pos.min(self.bytes.len());
+        self.literals.strings.push(start..self.pos);
+    }
+
+    /// Template text is protected; `${...}` interpolations are code and lexed as such.
+    fn template(&mut self) {
+        let mut start = self.pos;
+        self.pos += 1;
+        while let Some(byte) = self.peek(0) {
+            match byte {
+                b'\\' => self.pos += 2,
+                b'`' => {
+                    self.pos += 1;
+                    break;
+                }
+                b'$' if self.peek(1) == Some(b'{') => {
+                    self.pos += 2;
+                    self.literals.strings.push(start..self.pos);
+                    self.javascript(true);
+                    start = self.pos;
+                    self.pos += 1;
+                }
+                _ => self.pos += 1,
+            }
+        }
+        self.pos = self.pos.min(self.bytes.len());
+        self.literals.strings.push(start..self.pos);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::scan;
+    use crate::{bundled_rules, detect_file_type, find_language, Rules, Stripper};
+
+    fn strip(language: &str, source: &str) -> String {
+        let rules = Rules::bundled();
+        Stripper::new(&rules, &[]).unwrap().strip(source, language).unwrap().content
+    }
+
+    fn strip_jsx(source: &str) -> String {
+        let rules = Rules::bundled();
+        let stripper = Stripper::new(&rules, &[]).unwrap();
+        let language = find_language("javascript", stripper.rules).unwrap();
+        stripper.remover.strip_text(source, "a.jsx", language).unwrap().content
+    }
+
+    /// The text of every literal the lexer protects in `source`.
+    fn literals<'a>(language: &str, source: &'a str) -> Vec<&'a str> {
+        let rules = bundled_rules();
+        let language = find_language(language, &rules).unwrap();
+        scan(source, language, "").strings.into_iter().map(|literal| &source[literal]).collect()
+    }
+
+    /// The text of every comment found in `source`, kept or removed.
+    fn comments(language: &str, source: &str) -> Vec<String> {
+        let rules = Rules::bundled();
+        let result = Stripper::new(&rules, &[]).unwrap().strip(source, language).unwrap();
+        result.comments.into_iter().map(|comment| comment.text).collect()
+    }
+
+    #[test]
+    fn stylesheets_are_detected_by_extension() {
+        let rules = bundled_rules();
+        for (file, name) in [("a.css", "CSS"), ("a.scss", "SCSS"), ("a.sass", "Sass"), ("a.less", "Less")] {
+            assert_eq!(detect_file_type(file, &rules).unwrap().name, name);
+        }
+    }
+
+    #[test]
+    fn css_keeps_comment_markers_in_urls_and_strings() {
+        for (source, expected) in [
+            ("/* c */\na { color: red; }\n", "\na { color: red; }\n"),
+            ("a { color: red; } /* c */\n", "a { color: red; }\n"),
+            ("a { b: url(data:image/svg+xml;utf8,<svg>/*x*/</svg>); }\n", "a { b: url(data:image/svg+xml;utf8,<svg>/*x*/</svg>); }\n"),
+            ("a { b: url( http://example.com/*.png ); }\n", "a { b: url( http://example.com/*.png ); }\n"),
+            ("a { b: URL(\"a/*b.png\"); }\n", "a { b: URL(\"a/*b.png\"); }\n"),
+            ("a::before { content: \"/* not */\"; }\n", "a::before { content: \"/* not */\"; }\n"),
+            ("a { content: '/*'; } /* c */\n", "a { content: '/*'; }\n"),
+        ] {
+            assert_eq!(strip("css", source), expected, "in {:?}", source);
+        }
+    }
+
+    #[test]
+    fn css_has_no_line_comments() {
+        assert_eq!(strip("css", "// c\na { b: c; }\n"), "// c\na { b: c; }\n");
+    }
+
+    #[test]
+    fn scss_sass_and_less_add_line_comments() {
+        for language in ["scss", "sass", "less"] {
+            for (source, expected) in [
+                ("// c\na { b: c; }\n", "\na { b: c; }\n"),
+                ("a {\n  // c\n  b: c;\n}\n", "a {\n\n  b: c;\n}\n"),
+                ("/* c */\na { b: c; }\n", "\na { b: c; }\n"),
+                ("$cdn: \"//cdn.example.com\";\n", "$cdn: \"//cdn.example.com\";\n"),
+                ("a { b: url(//cdn.example.com/a.png); }\n", "a { b: url(//cdn.example.com/a.png); }\n"),
+                ("a { b: url(data:text/plain,/*//*/); }\n", "a { b: url(data:text/plain,/*//*/); }\n"),
+                ("a { content: \"/* // */\"; }\n", "a { content: \"/* // */\"; }\n"),
+            ] {
+                assert_eq!(strip(language, source), expected, "{} in {:?}", language, source);
+            }
+        }
+    }
+
+    #[test]
+    fn kotlin_and_scala_strings_keep_comment_markers() {
+        for language in ["kotlin", "scala"] {
+            for source in [
+                "val s = \"/* not */ // not\"\n",
+                "val s = \"\"\"\n  // not a comment\n  /* nor this */\n\"\"\"\n",
+                "val s = \"\"\"// not /* a comment */\"\"\"\n",
+            ] {
+                assert_eq!(strip(language, source), source, "{} in {:?}", language, source);
+            }
+            assert_eq!(strip(language, "// c\nval a = 1\n"), "\nval a = 1\n", "{}", language);
+        }
+    }
+
+    #[test]
+    fn swift_multi_line_and_raw_strings_keep_comment_markers() {
+        for source in [
+            "let s = \"\"\"\n  // not a comment\n  /* nor this */\n  \"\"\"\n",
+            "let s = \"\"\"\n  /* a\n  \"\"\"\n",
+            "let r = #\"/* raw */ \"// still raw\"#\n",
+            "let q = \"\\(a) // no\"\n",
+        ] {
+            assert_eq!(strip("swift", source), source, "in {:?}", source);
+        }
+        assert_eq!(strip("swift", "let s = \"\"\"\n  //\n  \"\"\"\n// c\n"), "let s = \"\"\"\n  //\n  \"\"\"\n\n");
+    }
+
+    #[test]
+    fn csharp_verbatim_interpolated_and_raw_strings_keep_comment_markers() {
+        for source in [
+            "var s = @\"C:\\path // not\";\n",
+            "var s = @\"say \"\"/* no */\"\"\";\n",
+            "var v = $\"{a} /* no */\";\n",
+            "var r = \"\"\"\n  // not\n  \"\"\";\n",
+        ] {
+            assert_eq!(strip("csharp", source), source, "in {:?}", source);
+        }
+        assert_eq!(strip("csharp", "/* c */\n// c\nvar a = 1;\n"), "\n\nvar a = 1;\n");
+    }
+
+    #[test]
+    fn zig_multi_line_strings_keep_comment_markers() {
+        let source = "const m =\n    \\\\ // not a comment\n    \\\\ line two\n;\nconst s = \"// not\";\n";
+        assert_eq!(strip("zig", source), source);
+        assert_eq!(strip("zig", "// c\nconst a = 1;\n"), "\nconst a = 1;\n");
+    }
+
+    #[test]
+    fn rust_raw_strings_and_char_literals_keep_comment_markers() {
+        for (source, expected) in [
+            ("let s = r#\"// x\"#; /* c */\n", vec!["/* c */"]),
+            ("let s = r##\"\"# /* x */\"##; /* c */\n", vec!["/* c */"]),
+            ("let b = br\"// x\"; /* c */\n", vec!["/* c */"]),
+            ("let c = '\"'; /* c */ let s = \"// x\";\n", vec!["/* c */"]),
+            ("let c = b'\\''; /* c */ let s = \"/* x */\";\n", vec!["/* c */"]),
+            ("fn f<'a>(s: &'a str) -> &'a str { /* c */ \"// x\" }\n", vec!["/* c */"]),
+            ("'outer: loop { break 'outer; } /* c */ \"// x\"\n", vec!["/* c */"]),
+        ] {
+            assert_eq!(comments("rust", source), expected, "in {:?}", source);
+        }
+    }
+
+    #[test]
+    fn javascript_template_interpolations_are_code() {
+        for (source, expected) in [
+            ("const s = `a ${ `// x` } b`; /* c */\n", vec!["/* c */"]),
+            ("const s = `${ a /* c */ } // x`;\n", vec!["/* c */"]),
+            ("const s = `${ f(`${ `/* x */` }`) }`;\n", vec![]),
+            ("const s = `${ { a: \"}\" }.a } // x`; /* c */\n", vec!["/* c */"]),
+        ] {
+            assert_eq!(comments("javascript", source), expected, "in {:?}", source);
+        }
+    }
+
+    #[test]
+    fn javascript_tells_regexes_from_division() {
+        for (source, expected) in [
+            ("x = /\\/\\//; // c\n", vec!["/\\/\\//"]),
+            ("x = /[/*]/g; /* c */\n", vec!["/[/*]/"]),
+            ("return /\\/\\/ x/.test(s); // c\n", vec!["/\\/\\/ x/"]),
+            ("x = a / b // c\n", vec![]),
+            ("x = a / b / c; // d\n", vec![]),
+            ("x = (a) / 2 / (b); // c\n", vec![]),
+            ("x = a[0] / b / c;\n", vec![]),
+            ("x = y // c /z/\n", vec![]),
+        ] {
+            assert_eq!(literals("javascript", source), expected, "in {:?}", source);
+        }
+    }
+
+    #[test]
+    fn jsx_comment_containers_are_removed_whole() {
+        for (source, expected) in [
+            ("const a = <div>\n  {/* c */}\n  <b />\n</div>;\n", "const a = <div>\n\n  <b />\n</div>;\n"),
+            ("const a = <div>{/* c */}text</div>;\n", "const a = <div>text</div>;\n"),
+            ("const a = <div>{ /* c */ }</div>;\n", "const a = <div></div>;\n"),
+            ("const a = <div>{value /* c */}</div>;\n", "const a = <div>{value }</div>;\n"),
+        ] {
+            assert_eq!(strip_jsx(source), expected, "in {:?}", source);
+        }
+    }
+
+    #[test]
+    fn jsx_attribute_strings_and_text_keep_comment_markers() {
+        for source in [
+            "const a = <a href=\"http://example.com\">link</a>;\n",
+            "const a = <a href='//cdn.example.com/*'>link</a>;\n",
+            "const a = <p>see http://example.com // not a comment</p>;\n",
+            "const a = <p>\n  // shown as text\n  /* also text */\n</p>;\n",
+            "const a = <Icon label=\"/* x */\" />;\n",
+        ] {
+            assert_eq!(strip_jsx(source), source);
+        }
+    }
+
+    #[test]
+    fn php_line_comments_end_at_the_closing_tag() {
+        for (source, expected) in [
+            ("<?php\n// x ?>\n<p>after</p>\n", "<?php\n ?>\n<p>after</p>\n"),
+            ("<?php\n# x ?><p>// after</p>\n", "<?php\n ?><p>// after</p>\n"),
+            ("<?php\n/* x ?> */\n$a = 1;\n", "<?php\n\n$a = 1;\n"),
+        ] {
+            assert_eq!(strip("php", source), expected, "in {:?}", source);
+        }
+    }
+
+    #[test]
+    fn php_attributes_are_not_comments() {
+        for source in ["<?php\n#[Attr]\nfunction f() {}\n", "<?php\n#[Route('/a', methods: ['GET'])]\nclass A {}\n"] {
+            assert_eq!(strip("php", source), source);
+        }
+        assert_eq!(strip("php", "<?php\n# c\n#[Attr]\nfunction f() {}\n"), "<?php\n\n#[Attr]\nfunction f() {}\n");
+    }
+
+    #[test]
+    fn php_comment_syntax_only_applies_in_its_region() {
+        for (source, expected) in [
+            ("<!-- c -->\n<p>x</p>\n", "\n<p>x</p>\n"),
+            ("<p>// not php # nor this</p>\n<?php\n// c\n?>\n", "<p>// not php # nor this</p>\n<?php\n\n?>\n"),
+            ("<p>/* text */</p>\n", "<p>/* text */</p>\n"),
+            ("<?php\n$a = '<!-- x -->';\n?>\n", "<?php\n$a = '<!-- x -->';\n?>\n"),
+            ("<?php $a = 1; ?>\n<!-- c -->\n<?= $a ?>\n", "<?php $a = 1; ?>\n\n<?= $a ?>\n"),
+        ] {
+            assert_eq!(strip("php", source), expected, "in {:?}", source);
+        }
+    }
+
+    #[test]
+    fn ruby_heredocs_and_percent_literals_keep_hashes() {
+        for (source, expected) in [
+            ("s = <<~EOS\n  # not a comment\nEOS\n# c\nx = 1\n", "s = <<~EOS\n  # not a comment\nEOS\n\nx = 1\n"),
+            ("s = <<-EOS.strip\n# kept\n  EOS\n", "s = <<-EOS.strip\n# kept\n  EOS\n"),
+            ("f(<<A, <<B)\n# a\nA\n# b\nB\n# c\n", "f(<<A, <<B)\n# a\nA\n# b\nB\n\n"),
+            ("s = %q{# x {nested}}\n# c\n", "s = %q{# x {nested}}\n\n"),
+            ("w = %w[# x]\n", "w = %w[# x]\n"),
+            ("y = x % 3\n# c\n", "y = x % 3\n\n"),
+        ] {
+            assert_eq!(strip("ruby", source), expected, "in {:?}", source);
+        }
+    }
+
+    #[test]
+    fn ruby_embedded_documents_and_data_section() {
+        for (source, expected) in [
+            ("x = 1\n=begin\n# doc\n=end\ny = 2\n", "x = 1\n\ny = 2\n"),
+            ("x = 1\n__END__\n# data\n=begin\n", "x = 1\n__END__\n# data\n=begin\n"),
+        ] {
+            assert_eq!(strip("ruby", source), expected, "in {:?}", source);
+        }
+    }
+
+    #[test]
+    fn perl_quote_like_operators_keep_hashes() {
+        for (source, expected) in [
+            ("my @w = qw(# x);\n# c\n", "my @w = qw(# x);\n\n"),
+            ("my $s = q{# x};\n", "my $s = q{# x};\n"),
+            ("$s =~ s{#}{x}g;\n# c\n", "$s =~ s{#}{x}g;\n\n"),
+            ("$s =~ tr/#/x/;\n", "$s =~ tr/#/x/;\n"),
+            ("print <<\"EOT\";\n# text\nEOT\n", "print <<\"EOT\";\n# text\nEOT\n"),
+            ("my $n = $#list;\n", "my $n = $#list;\n"),
+        ] {
+            assert_eq!(strip("perl", source), expected, "in {:?}", source);
+        }
+    }
+
+    #[test]
+    fn perl_pod_and_data_section() {
+        let pod = "my $x = 1;\n=pod\n\n# in pod\n\n=cut\nmy $y = 2;\n";
+        assert_eq!(comments("perl", pod), vec!["=pod\n\n# in pod\n\n=cut"]);
+        for source in ["my $x = 1;\n__END__\n# data\n", "my $x = 1;\n__DATA__\n# data\n=pod\n"] {
+            assert_eq!(strip("perl", source), source);
+        }
+    }
+}
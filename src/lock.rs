@@ -0,0 +1,134 @@
+use anyhow::{Context, Result};
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs::{self, File},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::Error;
+
+/// Holds a file for one run and remembers what it looked like when it was read, so a second
+/// run or an editor changing it in the meantime is reported instead of being overwritten.
+pub struct FileLock {
+    path: PathBuf,
+    // Keeps the advisory lock until the lock is dropped
+    _file: File,
+    len: u64,
+    modified: Option<SystemTime>,
+    hash: u64,
+}
+
+impl FileLock {
+    /// Locks `path` and reads it, failing right away if another run already holds it.
+    pub fn acquire(path: &Path) -> Result<(FileLock, Vec<u8>)> {
+        let file = File::open(path).with_context(|| format!("Failed to read file: {}", path.display()))?;
+        lock(&file, path)?;
+        let metadata = file.metadata().with_context(|| format!("Failed to read file: {}", path.display()))?;
+        let bytes = fs::read(path).with_context(|| format!("Failed to read file: {}", path.display()))?;
+        let lock = FileLock {
+            path: path.to_path_buf(),
+            _file: file,
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+            hash: hash(&bytes),
+        };
+        Ok((lock, bytes))
+    }
+
+    /// Fails if the file no longer holds what was read; call right before writing it.
+    pub fn check_unchanged(&self) -> Result<()> {
+        let metadata = fs::metadata(&self.path).with_context(|| format!("Failed to read file: {}", self.path.display()))?;
+        if metadata.len() == self.len && metadata.modified().ok() == self.modified {
+            return Ok(());
+        }
+        // Only touched, e.g. by a build tool, is fine as long as the contents are the same
+        let bytes = fs::read(&self.path).with_context(|| format!("Failed to read file: {}", self.path.display()))?;
+        if bytes.len() as u64 == self.len && hash(&bytes) == self.hash {
+            return Ok(());
+        }
+        Err(Error::ConcurrentModification(self.path.display().to_string()).into())
+    }
+}
+
+fn hash(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(unix)]
+fn lock(file: &File, path: &Path) -> Result<()> {
+    match file.try_lock() {
+        Ok(()) => Ok(()),
+        Err(fs::TryLockError::WouldBlock) => Err(Error::Locked(path.display().to_string()).into()),
+        Err(fs::TryLockError::Error(e)) => {
+            Err(e).with_context(|| format!("Failed to lock file: {}", path.display()))
+        }
+    }
+}
+
+// Windows locks are mandatory and would make our own backup and snapshot reads fail, so
+// only the modification check applies there
+#[cfg(not(unix))]
+fn lock(_file: &File, _path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{process, time::Duration};
+
+    fn file(name: &str, contents: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("comment-remover-lock-{}-{}", process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.c");
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn set_modified(path: &Path, modified: SystemTime) {
+        File::options().write(true).open(path).unwrap().set_modified(modified).unwrap();
+    }
+
+    #[test]
+    fn a_file_changed_after_reading_is_not_written() {
+        for (name, replacement) in [("longer", "// changed elsewhere\nint a;\n"), ("same-length", "int b;\n")] {
+            let path = file(name, "int a;\n");
+            let (lock, bytes) = FileLock::acquire(&path).unwrap();
+            assert_eq!(bytes, b"int a;\n");
+
+            fs::write(&path, replacement).unwrap();
+            set_modified(&path, SystemTime::now() + Duration::from_secs(5));
+            let error = lock.check_unchanged().unwrap_err();
+            assert!(matches!(error.downcast_ref::<Error>(), Some(Error::ConcurrentModification(_))), "{}", name);
+            drop(lock);
+            fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        }
+    }
+
+    #[test]
+    fn a_file_only_touched_after_reading_is_unchanged() {
+        let path = file("touched", "int a;\n");
+        let (lock, _) = FileLock::acquire(&path).unwrap();
+        lock.check_unchanged().unwrap();
+        set_modified(&path, SystemTime::now() + Duration::from_secs(5));
+        lock.check_unchanged().unwrap();
+        drop(lock);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_locked_file_is_reported() {
+        let path = file("locked", "int a;\n");
+        let (lock, _) = FileLock::acquire(&path).unwrap();
+        let error = FileLock::acquire(&path).err().unwrap();
+        assert!(matches!(error.downcast_ref::<Error>(), Some(Error::Locked(_))));
+        drop(lock);
+        FileLock::acquire(&path).unwrap();
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
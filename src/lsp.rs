@@ -0,0 +1,259 @@
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+    ops::Range,
+};
+
+use crate::{help::BIN_NAME, remove::Remover};
+
+/// JSON-RPC error codes used by the server
+const METHOD_NOT_FOUND: i64 = -32601;
+const INTERNAL_ERROR: i64 = -32603;
+
+/// An open editor buffer.
+struct Document {
+    text: String,
+    language_id: String,
+}
+
+/// Runs a Language Server on stdin/stdout offering code actions to remove one comment or all
+/// of them, and document formatting that removes all of them, with the `remove` options given.
+pub fn serve(remover: &Remover) -> Result<()> {
+    let mut input = io::stdin().lock();
+    let mut output = io::stdout().lock();
+    let mut documents: HashMap<String, Document> = HashMap::new();
+
+    info!("{} language server listening on stdin", BIN_NAME);
+    while let Some(message) = read_message(&mut input)? {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let id = message.get("id").cloned();
+        let result = match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    // Full text on every change
+                    "textDocumentSync": 1,
+                    "codeActionProvider": true,
+                    "documentFormattingProvider": true,
+                },
+                "serverInfo": { "name": BIN_NAME, "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "shutdown" => Ok(Value::Null),
+            "exit" => break,
+            "textDocument/didOpen" => {
+                let document = &params["textDocument"];
+                documents.insert(
+                    uri(params).to_string(),
+                    Document {
+                        text: document["text"].as_str().unwrap_or_default().to_string(),
+                        language_id: document["languageId"].as_str().unwrap_or_default().to_string(),
+                    },
+                );
+                continue;
+            }
+            "textDocument/didChange" => {
+                if let Some(document) = documents.get_mut(uri(params))
+                    && let Some(text) = params["contentChanges"]
+                        .as_array()
+                        .and_then(|changes| changes.last())
+                        .and_then(|change| change["text"].as_str())
+                {
+                    document.text = text.to_string();
+                }
+                continue;
+            }
+            "textDocument/didClose" => {
+                documents.remove(uri(params));
+                continue;
+            }
+            "textDocument/codeAction" => code_actions(remover, &documents, params),
+            "textDocument/formatting" => formatting(remover, &documents, params),
+            _ if id.is_none() => continue,
+            _ => {
+                send(&mut output, &json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": { "code": METHOD_NOT_FOUND, "message": format!("Unsupported method: {}", method) },
+                }))?;
+                continue;
+            }
+        };
+        let response = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": INTERNAL_ERROR, "message": format!("{:#}", e) },
+            }),
+        };
+        send(&mut output, &response)?;
+    }
+    Ok(())
+}
+
+/// "Remove this comment" for each removable comment touching the requested range, and
+/// "Remove all comments" for the file.
+fn code_actions(remover: &Remover, documents: &HashMap<String, Document>, params: &Value) -> Result<Value> {
+    let uri = uri(params);
+    let Some(document) = documents.get(uri) else {
+        return Ok(json!([]));
+    };
+    let text = &document.text;
+    let file_path = uri_to_path(uri);
+    let language = remover.language_of(&file_path, Some(&document.language_id))?;
+    let removable = remover.removable(text, &file_path, language)?;
+    if removable.is_empty() {
+        return Ok(json!([]));
+    }
+
+    let start = offset(text, &params["range"]["start"]);
+    let end = offset(text, &params["range"]["end"]);
+    let mut actions = Vec::new();
+    for range in removable.iter().filter(|range| range.start <= end && start <= range.end) {
+        let updated = remover.remove_one(text, &file_path, language, range)?;
+        actions.push(json!({
+            "title": "Remove this comment",
+            "kind": "quickfix",
+            "edit": { "changes": { uri: [text_edit(text, &updated)] } },
+        }));
+    }
+    let updated = remover.strip_text(text, &file_path, language)?.content;
+    actions.push(json!({
+        "title": format!("Remove all {} comments in file", removable.len()),
+        "kind": "source",
+        "edit": { "changes": { uri: [text_edit(text, &updated)] } },
+    }));
+    Ok(Value::Array(actions))
+}
+
+fn formatting(remover: &Remover, documents: &HashMap<String, Document>, params: &Value) -> Result<Value> {
+    let uri = uri(params);
+    let Some(document) = documents.get(uri) else {
+        return Ok(json!([]));
+    };
+    let file_path = uri_to_path(uri);
+    let language = remover.language_of(&file_path, Some(&document.language_id))?;
+    let updated = remover.strip_text(&document.text, &file_path, language)?.content;
+    if updated == document.text {
+        return Ok(json!([]));
+    }
+    Ok(json!([text_edit(&document.text, &updated)]))
+}
+
+fn uri(params: &Value) -> &str {
+    params["textDocument"]["uri"].as_str().unwrap_or_default()
+}
+
+/// The file path of a `file://` URI, used to detect the language from the extension.
+fn uri_to_path(uri: &str) -> String {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = (byte == b'%')
+            .then(|| tail.get(..2))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(decoded) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    let path = String::from_utf8_lossy(&bytes).into_owned();
+    // `file:///C:/src` on Windows
+    match path.as_bytes() {
+        [b'/', _, b':', ..] => path[1..].to_string(),
+        _ => path,
+    }
+}
+
+/// The smallest edit turning `old` into `new`: everything between their common prefix and suffix.
+fn text_edit(old: &str, new: &str) -> Value {
+    let mut prefix = old.bytes().zip(new.bytes()).take_while(|(a, b)| a == b).count();
+    while !old.is_char_boundary(prefix) || !new.is_char_boundary(prefix) {
+        prefix -= 1;
+    }
+    let mut suffix = old[prefix..]
+        .bytes()
+        .rev()
+        .zip(new[prefix..].bytes().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !old.is_char_boundary(old.len() - suffix) || !new.is_char_boundary(new.len() - suffix) {
+        suffix -= 1;
+    }
+    let replaced: Range<usize> = prefix..old.len() - suffix;
+    json!({
+        "range": { "start": position(old, replaced.start), "end": position(old, replaced.end) },
+        "newText": &new[prefix..new.len() - suffix],
+    })
+}
+
+/// LSP position of a byte offset; columns count UTF-16 code units.
+fn position(text: &str, offset: usize) -> Value {
+    let line_start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+    json!({
+        "line": text[..offset].matches('\n').count(),
+        "character": text[line_start..offset].encode_utf16().count(),
+    })
+}
+
+/// Byte offset of an LSP position, clamped to the text.
+fn offset(text: &str, position: &Value) -> usize {
+    let line = position["line"].as_u64().unwrap_or(0) as usize;
+    let character = position["character"].as_u64().unwrap_or(0) as usize;
+    let Some(line_start) = (if line == 0 {
+        Some(0)
+    } else {
+        text.match_indices('\n').nth(line - 1).map(|(i, _)| i + 1)
+    }) else {
+        return text.len();
+    };
+    let line_end = text[line_start..].find('\n').map_or(text.len(), |i| line_start + i);
+    let mut units = 0;
+    for (i, c) in text[line_start..line_end].char_indices() {
+        if units >= character {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+    line_end
+}
+
+/// Reads one `Content-Length` framed message; `None` once the client closes the stream.
+fn read_message(input: &mut impl BufRead) -> Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header).context("Failed to read from the client")? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let length = length.context("Message from the client has no Content-Length")?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body).context("Failed to read from the client")?;
+    Ok(Some(serde_json::from_slice(&body).context("Message from the client is not valid JSON")?))
+}
+
+fn send(output: &mut impl Write, message: &Value) -> Result<()> {
+    let body = serde_json::to_string(message)?;
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush().context("Failed to write to the client")
+}
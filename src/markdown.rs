@@ -0,0 +1,120 @@
+use anyhow::Result;
+use std::{cmp::Reverse, path::Path};
+
+use crate::{line_number, result::RemovalResult, LanguageRules, SyntaxRules};
+
+pub fn is_markdown(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "md" || ext == "mdx")
+}
+
+/// Runs `strip` over the code and first line of every fenced block whose info string names a known language
+/// and returns the rewritten document with the comments of all blocks, positioned within the
+/// document. Prose is never touched.
+pub fn strip_blocks(
+    content: &str,
+    rules: &SyntaxRules,
+    mut strip: impl FnMut(&str, &LanguageRules, usize) -> Result<RemovalResult>,
+) -> Result<RemovalResult> {
+    let mut result = String::with_capacity(content.len());
+    let mut comments = Vec::new();
+    let mut last_end = 0;
+
+    for block in fenced_blocks(content) {
+        let Some(language) = language_for(block.info, rules) else {
+            continue;
+        };
+        let code = &content[block.code.clone()];
+        let lines_before = line_number(content, block.code.start) - 1;
+        let stripped = strip(code, language, lines_before + 1)?;
+        comments.extend(stripped.comments.into_iter().map(|mut comment| {
+            comment.shift(block.code.start, lines_before);
+            comment
+        }));
+
+        result.push_str(&content[last_end..block.code.start]);
+        result.push_str(&stripped.content);
+        last_end = block.code.end;
+    }
+    result.push_str(&content[last_end..]);
+
+    Ok(RemovalResult { content: result, comments })
+}
+
+struct FencedBlock<'a> {
+    info: &'a str,
+    /// The lines between the fences, including the final line break
+    code: std::ops::Range<usize>,
+}
+
+/// Finds ``` and ~~~ fences as CommonMark does: the closing fence uses the same character,
+/// is at least as long, and an unclosed block runs to the end of the document.
+fn fenced_blocks(content: &str) -> Vec<FencedBlock<'_>> {
+    let mut blocks = Vec::new();
+    let mut lines = line_spans(content).into_iter();
+
+    while let Some((start, end)) = lines.next() {
+        let Some((fence, info)) = opening_fence(&content[start..end]) else {
+            continue;
+        };
+        let code_start = end;
+        let mut code_end = content.len();
+        for (line_start, line_end) in lines.by_ref() {
+            if is_closing_fence(&content[line_start..line_end], fence) {
+                code_end = line_start;
+                break;
+            }
+        }
+        blocks.push(FencedBlock { info, code: code_start..code_end });
+    }
+    blocks
+}
+
+/// Start and end (after the line break) of every line.
+fn line_spans(content: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut start = 0;
+    for line in content.split_inclusive('\n') {
+        spans.push((start, start + line.len()));
+        start += line.len();
+    }
+    spans
+}
+
+fn opening_fence(line: &str) -> Option<(&str, &str)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let marker = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let length = trimmed.len() - trimmed.trim_start_matches(marker).len();
+    if length < 3 {
+        return None;
+    }
+    let info = trimmed[length..].trim();
+    // Backtick fences cannot have backticks in their info string
+    if marker == '`' && info.contains('`') {
+        return None;
+    }
+    Some((&trimmed[..length], info))
+}
+
+fn is_closing_fence(line: &str, fence: &str) -> bool {
+    let trimmed = line.trim_start_matches(' ');
+    let marker = &fence[..1];
+    let length = trimmed.len() - trimmed.trim_start_matches(marker).len();
+    line.len() - trimmed.len() <= 3 && length >= fence.len() && trimmed[length..].trim().is_empty()
+}
+
+/// Matches the first word of the info string against language keys, names and extensions,
+/// so `rust`, `Rust` and `rs` all work.
+fn language_for<'a>(info: &str, rules: &'a SyntaxRules) -> Option<&'a LanguageRules> {
+    let tag = info.split_whitespace().next()?.trim_start_matches('{').trim_end_matches('}').to_lowercase();
+    rules
+        .languages
+        .iter()
+        .filter(|(key, language)| {
+            **key == tag || language.name.to_lowercase() == tag || language.extensions.contains(&tag)
+        })
+        .max_by_key(|(key, language)| (language.priority, Reverse(*key)))
+        .map(|(_, language)| language)
+}
@@ -0,0 +1,145 @@
+use serde::Deserialize;
+use std::ops::Range;
+
+use crate::{directives, Comment, CommentKind, LanguageRules, RuleClass};
+
+/// Banners need at least this many fill characters to tell them from punctuation like `// --`
+const MIN_BANNER: usize = 3;
+
+/// What `normalize` changes, from `normalize` in .comment-remover.json.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NormalizeRules {
+    /// A space between a line comment's marker and its text, so `//x` becomes `// x`
+    pub space_after_marker: bool,
+    /// Block comments on one line with no code after them become line comments, so `/* x */`
+    /// becomes `// x`
+    pub block_to_line: bool,
+    /// Spaces and tabs at the end of comment lines are dropped
+    pub trim_trailing: bool,
+    /// The character banner lines such as `//////////` or `# ========` are redrawn with,
+    /// keeping their width; null leaves banners as they are
+    pub banner: Option<char>,
+}
+
+impl Default for NormalizeRules {
+    fn default() -> Self {
+        NormalizeRules { space_after_marker: true, block_to_line: true, trim_trailing: true, banner: Some('-') }
+    }
+}
+
+/// Outcome of normalizing a file.
+pub struct Normalization {
+    pub content: String,
+    pub normalized: usize,
+}
+
+/// Tidies the plain (non-doc) comments in `content` the way `rules` say, without removing any.
+/// Documentation comments and directives are left alone since their exact syntax carries meaning.
+pub fn normalize(content: &str, comments: &[Comment], language: &LanguageRules, rules: &NormalizeRules) -> Normalization {
+    let mut normalization = Normalization { content: String::with_capacity(content.len()), normalized: 0 };
+    let mut last_end = 0;
+    for comment in comments.iter().filter(|comment| comment.kind != CommentKind::Doc) {
+        let range = trimmed(content, &comment.range);
+        let text = &content[range.clone()];
+        if text.is_empty() || is_directive(content, &range, language) {
+            continue;
+        }
+        let replacement = match comment.kind {
+            CommentKind::Line => line_comment(text, language, rules),
+            _ => block_comment(content, &range, language, rules),
+        };
+        if let Some(replacement) = replacement.filter(|replacement| replacement != text) {
+            normalization.content.push_str(&content[last_end..range.start]);
+            normalization.content.push_str(&replacement);
+            last_end = range.end;
+            normalization.normalized += 1;
+        }
+    }
+    normalization.content.push_str(&content[last_end..]);
+    normalization
+}
+
+/// The comment without the indentation and line break the regex patterns pick up; trailing
+/// spaces stay so they can be trimmed.
+fn trimmed(content: &str, range: &Range<usize>) -> Range<usize> {
+    let text = &content[range.clone()];
+    let start = range.start + (text.len() - text.trim_start().len());
+    let end = range.start + text.trim_end_matches(['\r', '\n']).len();
+    start..end.max(start)
+}
+
+fn is_directive(content: &str, range: &Range<usize>, language: &LanguageRules) -> bool {
+    let text = &content[range.clone()];
+    (range.start == 0 && text.starts_with("#!"))
+        || directives::is_compiler_directive(content, range, language)
+        || directives::is_directive(text, language)
+}
+
+fn line_comment(text: &str, language: &LanguageRules, rules: &NormalizeRules) -> Option<String> {
+    let rest = language.single_line.iter().filter(|rule| rule.class() == RuleClass::Normal).find_map(|rule| rule.strip_marker(text))?;
+    let marker = &text[..text.len() - rest.len()];
+    Some(line_text(marker, rest, rules))
+}
+
+/// A line comment with `marker` and `body`, the text after the marker.
+fn line_text(marker: &str, body: &str, rules: &NormalizeRules) -> String {
+    let body = if rules.trim_trailing { body.trim_end_matches([' ', '\t']) } else { body };
+    if let Some(banner) = rules.banner
+        && is_banner(body)
+    {
+        // Same width as before, so banners still line up with the code around them
+        let width = marker.chars().count() + body.chars().count();
+        let fill = width.saturating_sub(marker.chars().count() + 1).max(MIN_BANNER);
+        return format!("{} {}", marker, banner.to_string().repeat(fill));
+    }
+    // `##` headings and the like are a style of their own
+    let repeated = marker.chars().last().is_some_and(|last| body.starts_with(last));
+    if rules.space_after_marker && !repeated && body.starts_with(|c: char| !c.is_whitespace()) {
+        return format!("{} {}", marker, body);
+    }
+    format!("{}{}", marker, body)
+}
+
+/// A comment that is nothing but one punctuation character repeated, like `=====`.
+fn is_banner(body: &str) -> bool {
+    let body = body.trim();
+    let mut chars = body.chars();
+    chars.next().is_some_and(|first| {
+        first.is_ascii_punctuation() && body.chars().count() >= MIN_BANNER && chars.all(|c| c == first)
+    })
+}
+
+fn block_comment(content: &str, range: &Range<usize>, language: &LanguageRules, rules: &NormalizeRules) -> Option<String> {
+    let text = &content[range.clone()];
+    let block = language.multi_line.iter().filter(|rule| rule.class() == RuleClass::Normal).find(|rule| {
+        text.len() >= rule.start.len() + rule.end.len() && text.starts_with(&rule.start) && text.ends_with(&rule.end)
+    })?;
+    let inner = &text[block.start.len()..text.len() - block.end.len()];
+    let line_end = content[range.end..].find('\n').map_or(content.len(), |i| range.end + i);
+    let code_after = !content[range.end..line_end].trim().is_empty();
+    let line_marker = language.single_line.iter().find(|rule| rule.class() == RuleClass::Normal);
+
+    // A line comment would swallow code after it, and cannot hold more than one line
+    if rules.block_to_line
+        && !code_after
+        && !inner.contains('\n')
+        && let Some(line) = line_marker
+    {
+        let inner = inner.trim();
+        let body = if inner.is_empty() { String::new() } else { format!(" {}", inner) };
+        return Some(line_text(&line.pattern, &body, rules));
+    }
+    if !rules.trim_trailing {
+        return None;
+    }
+    Some(
+        text.split('\n')
+            .map(|line| {
+                let trimmed = line.trim_end_matches('\r').trim_end_matches([' ', '\t']);
+                if line.ends_with('\r') { format!("{}\r", trimmed) } else { trimmed.to_string() }
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    )
+}
@@ -0,0 +1,87 @@
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{ser::PrettyFormatter, Serializer, Value};
+use std::path::Path;
+
+use crate::{detect_file_type, result::RemovalResult, Error, LanguageRules, SyntaxRules};
+
+pub fn is_notebook(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "ipynb")
+}
+
+/// Runs `strip` over the source of every code cell and returns the rewritten notebook along
+/// with the comments of all cells, positioned within their cell. Metadata, outputs and
+/// markdown cells are left as they are.
+pub fn strip_cells(
+    content: &str,
+    rules: &SyntaxRules,
+    mut strip: impl FnMut(&str, &LanguageRules) -> Result<RemovalResult>,
+) -> Result<RemovalResult> {
+    let mut notebook: Value =
+        serde_json::from_str(content).map_err(|e| Error::Notebook(e.to_string()))?;
+    let language = code_language(&notebook, rules)?;
+
+    let mut changed = false;
+    let mut comments = Vec::new();
+    let cells = notebook
+        .get_mut("cells")
+        .and_then(Value::as_array_mut)
+        .ok_or_else(|| Error::Notebook("missing \"cells\" array".to_string()))?;
+    for cell in cells {
+        if cell.get("cell_type").and_then(Value::as_str) != Some("code") {
+            continue;
+        }
+        let Some(source) = cell.get_mut("source") else { continue };
+
+        // nbformat stores sources either as one string or as a list of lines
+        let text = match &*source {
+            Value::String(text) => text.clone(),
+            Value::Array(lines) => lines.iter().filter_map(Value::as_str).collect(),
+            _ => continue,
+        };
+        let result = strip(&text, language)?;
+        comments.extend(result.comments);
+        let stripped = result.content;
+        if stripped == text {
+            continue;
+        }
+
+        changed = true;
+        *source = match source {
+            Value::String(_) => Value::String(stripped),
+            _ => Value::Array(stripped.split_inclusive('\n').map(|line| Value::String(line.to_string())).collect()),
+        };
+    }
+
+    if !changed {
+        return Ok(RemovalResult { content: content.to_string(), comments });
+    }
+    Ok(RemovalResult { content: serialize(&notebook, content)?, comments })
+}
+
+/// The language of the code cells, from the kernel's file extension; Python when unknown.
+fn code_language<'a>(notebook: &Value, rules: &'a SyntaxRules) -> Result<&'a LanguageRules> {
+    let extension = notebook
+        .pointer("/metadata/language_info/file_extension")
+        .and_then(Value::as_str)
+        .unwrap_or(".py");
+    detect_file_type(&format!("cell{}", extension), rules)
+}
+
+/// Writes the notebook back with the indentation of the original file, as Jupyter does.
+fn serialize(notebook: &Value, original: &str) -> Result<String> {
+    let indent = original
+        .lines()
+        .nth(1)
+        .map(|line| &line[..line.len() - line.trim_start().len()])
+        .filter(|indent| !indent.is_empty())
+        .unwrap_or(" ");
+    let mut bytes = Vec::new();
+    let mut serializer = Serializer::with_formatter(&mut bytes, PrettyFormatter::with_indent(indent.as_bytes()));
+    notebook.serialize(&mut serializer)?;
+    let mut serialized = String::from_utf8(bytes)?;
+    if original.ends_with('\n') {
+        serialized.push('\n');
+    }
+    Ok(serialized)
+}
@@ -0,0 +1,85 @@
+//! Status messages go to stderr so stdout only carries a command's actual output
+//! (extracted comments, reports, generated files) and can be piped safely.
+
+use clap::ValueEnum;
+use colored::*;
+use serde_json::json;
+use std::{
+    fmt::Display,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+static JSON_ERRORS: AtomicBool = AtomicBool::new(false);
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// How errors are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ErrorFormat {
+    /// Messages for people
+    Text,
+    /// One JSON object per line with a stable `code`, for wrappers and editor extensions
+    Json,
+}
+
+pub fn set_error_format(format: ErrorFormat) {
+    JSON_ERRORS.store(format == ErrorFormat::Json, Ordering::Relaxed);
+}
+
+/// Why a file was left out of a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFailure {
+    /// Not a file the run can or may change, like a binary or read-only file
+    Skipped,
+    Errored,
+}
+
+/// Reports a file that was not processed; unlike `info!` this is printed even with `--quiet`.
+pub fn file_error(failure: FileFailure, path: impl Display, error: &anyhow::Error) {
+    if JSON_ERRORS.load(Ordering::Relaxed) {
+        let severity = if failure == FileFailure::Skipped { "skipped" } else { "error" };
+        let object = json!({
+            "severity": severity,
+            "code": crate::error_code(error),
+            "path": path.to_string(),
+            "message": format!("{:#}", error),
+        });
+        eprintln!("{}", object);
+    } else {
+        let label = if failure == FileFailure::Skipped { "Skipping" } else { "Error processing" };
+        eprintln!("{} {}: {:#}", label.red(), path, error);
+    }
+}
+
+/// Reports the error that stopped the command and returns the exit status for it.
+pub fn fatal_error(error: &anyhow::Error) -> i32 {
+    let exit_code = crate::exit_code(error);
+    if JSON_ERRORS.load(Ordering::Relaxed) {
+        let object = json!({
+            "severity": "fatal",
+            "code": crate::error_code(error),
+            "message": format!("{:#}", error),
+            "exit_code": exit_code,
+        });
+        eprintln!("{}", object);
+    } else {
+        eprintln!("Error: {:?}", error);
+    }
+    exit_code
+}
+
+/// Prints a human-readable status line to stderr unless `--quiet` is set.
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if !$crate::output::is_quiet() {
+            eprintln!($($arg)*);
+        }
+    };
+}
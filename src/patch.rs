@@ -0,0 +1,34 @@
+use clap::ValueEnum;
+use similar::TextDiff;
+use std::{env, path::Path};
+
+/// What `remove` does with the new contents of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RemoveOutput {
+    /// Modify the files in place
+    Files,
+    /// Leave the files alone and print a unified patch for `git apply` to stdout
+    Patch,
+}
+
+/// `path` the way a patch names it: relative to the current directory, with `/` separators.
+pub fn patch_path(path: &Path) -> String {
+    let relative = env::current_dir()
+        .ok()
+        .and_then(|current_dir| path.strip_prefix(current_dir).ok())
+        .unwrap_or(path);
+    let relative = relative.strip_prefix(".").unwrap_or(relative);
+    relative.to_string_lossy().replace('\\', "/")
+}
+
+/// A git-style unified diff of one file, empty when nothing changed.
+pub fn file_diff(name: &str, before: &str, after: &str) -> String {
+    if before == after {
+        return String::new();
+    }
+    let diff = TextDiff::from_lines(before, after)
+        .unified_diff()
+        .header(&format!("a/{}", name), &format!("b/{}", name))
+        .to_string();
+    format!("diff --git a/{} b/{}\n{}", name, name, diff)
+}
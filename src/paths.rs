@@ -0,0 +1,138 @@
+use std::{
+    ffi::OsString,
+    path::{Component, Path, PathBuf, Prefix, MAIN_SEPARATOR, MAIN_SEPARATOR_STR},
+};
+
+/// `path` without the `\\?\` prefix `fs::canonicalize` adds on Windows, so `\\?\C:\src` becomes
+/// `C:\src` and `\\?\UNC\server\share\src` becomes `\\server\share\src`. The standard library adds
+/// the prefix back by itself for paths too long without it, so the result is as good for file
+/// operations, and it is what users expect to read and what `tar` and `zip` understand.
+pub fn plain(path: &Path) -> PathBuf {
+    let mut components = path.components();
+    let Some(Component::Prefix(prefix)) = components.next() else {
+        return path.to_path_buf();
+    };
+    let mut plain = match prefix.kind() {
+        Prefix::VerbatimDisk(drive) => OsString::from(format!("{}:", drive as char)),
+        Prefix::VerbatimUNC(server, share) => {
+            let mut unc = OsString::from(r"\\");
+            unc.push(server);
+            unc.push(r"\");
+            unc.push(share);
+            unc
+        }
+        _ => return path.to_path_buf(),
+    };
+    // Verbatim paths keep `/` and trailing dots as part of names, which would read differently
+    if components.clone().any(|component| {
+        let name = component.as_os_str().to_string_lossy();
+        name.contains('/') || name.ends_with(['.', ' '])
+    }) {
+        return path.to_path_buf();
+    }
+    plain.push(components.as_path());
+    PathBuf::from(plain)
+}
+
+/// `path` with `.` dropped and `..` taking away the component before it, without looking at
+/// the file system; a `..` at the root stays there, as it does for the OS.
+pub fn normalized(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if matches!(normalized.components().next_back(), Some(Component::Normal(_))) => {
+                normalized.pop();
+            }
+            Component::ParentDir if normalized.has_root() => {}
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// A path given on the command line with the platform's separator, so walking `src/` on Windows
+/// lists `src\lib\main.c` rather than `src/lib\main.c`.
+pub fn native(path: &str) -> PathBuf {
+    if MAIN_SEPARATOR == '/' {
+        PathBuf::from(path)
+    } else {
+        PathBuf::from(path.replace('/', MAIN_SEPARATOR_STR))
+    }
+}
+
+/// Directory names standing for a drive or network share below a backup, history or output
+/// directory: `C:` becomes `C`, `\\server\share` becomes `server/share`.
+pub fn prefix_dirs(prefix: Prefix<'_>) -> Vec<OsString> {
+    match prefix {
+        Prefix::Disk(drive) | Prefix::VerbatimDisk(drive) => vec![OsString::from((drive as char).to_string())],
+        Prefix::UNC(server, share) | Prefix::VerbatimUNC(server, share) => vec![server.to_os_string(), share.to_os_string()],
+        Prefix::Verbatim(name) | Prefix::DeviceNS(name) => vec![name.to_os_string()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+
+    #[test]
+    fn prefixes_become_directory_names() {
+        let dirs = |prefix| prefix_dirs(prefix).into_iter().map(|dir| dir.to_string_lossy().into_owned()).collect::<Vec<_>>();
+        assert_eq!(dirs(Prefix::Disk(b'C')), ["C"]);
+        assert_eq!(dirs(Prefix::VerbatimDisk(b'C')), ["C"]);
+        assert_eq!(dirs(Prefix::UNC(OsStr::new("server"), OsStr::new("share"))), ["server", "share"]);
+        assert_eq!(dirs(Prefix::VerbatimUNC(OsStr::new("server"), OsStr::new("share"))), ["server", "share"]);
+        assert_eq!(dirs(Prefix::Verbatim(OsStr::new("Volume{1}"))), ["Volume{1}"]);
+        assert_eq!(dirs(Prefix::DeviceNS(OsStr::new("COM1"))), ["COM1"]);
+    }
+
+    #[test]
+    fn paths_without_a_prefix_stay_as_they_are() {
+        for path in ["/usr/src/a.c", "src/a.c", "../a.c", "a.", ""] {
+            assert_eq!(plain(Path::new(path)), Path::new(path));
+        }
+    }
+
+    #[test]
+    fn normalized_resolves_dots_lexically() {
+        for (path, expected) in [
+            ("a/./b/../c", "a/c"),
+            ("./a", "a"),
+            ("a/..", ""),
+            ("a/../../b", "../b"),
+            ("../../a", "../../a"),
+            ("/../a", "/a"),
+            ("/a/b/../../..", "/"),
+        ] {
+            assert_eq!(normalized(Path::new(path)), Path::new(expected), "for {}", path);
+        }
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn plain_drops_the_verbatim_prefix() {
+        for (path, expected) in [
+            (r"\\?\C:\", r"C:\"),
+            (r"\\?\C:\src\a.c", r"C:\src\a.c"),
+            (r"\\?\UNC\server\share\", r"\\server\share\"),
+            (r"\\?\UNC\server\share\src\a.c", r"\\server\share\src\a.c"),
+            (r"C:\", r"C:\"),
+            (r"C:\src\a.c", r"C:\src\a.c"),
+            (r"\\server\share\a.c", r"\\server\share\a.c"),
+            // Names a plain path would read differently keep the prefix
+            (r"\\?\C:\src\a.", r"\\?\C:\src\a."),
+            (r"\\?\C:\src\a ", r"\\?\C:\src\a "),
+            (r"\\?\C:\src/a.c", r"\\?\C:\src/a.c"),
+            (r"\\?\Volume{1}\a.c", r"\\?\Volume{1}\a.c"),
+        ] {
+            assert_eq!(plain(Path::new(path)), Path::new(expected), "for {}", path);
+        }
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn native_uses_backslashes() {
+        assert_eq!(native("src/lib/a.c"), Path::new(r"src\lib\a.c"));
+    }
+}
@@ -0,0 +1,23 @@
+use crate::{Comment, CommentKind, LanguageRules};
+
+pub fn is_php(language: &LanguageRules) -> bool {
+    language.name == "PHP"
+}
+
+/// PHP line comments stop at a closing `?>` on the same line, and `#[...]` is an attribute.
+pub fn fix_comments(content: &str, comments: Vec<Comment>) -> Vec<Comment> {
+    comments
+        .into_iter()
+        .filter(|comment| !content[comment.range.clone()].trim_start().starts_with("#["))
+        .map(|comment| {
+            let text = &content[comment.range.clone()];
+            match text.find("?>") {
+                Some(close) if comment.kind == CommentKind::Line => {
+                    let end = comment.range.start + text[..close].trim_end().len();
+                    Comment { range: comment.range.start..end, ..comment }
+                }
+                _ => comment,
+            }
+        })
+        .collect()
+}
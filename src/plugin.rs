@@ -0,0 +1,50 @@
+//! Custom keep/remove policies for tools that embed the engine.
+//!
+//! A project-specific binary registers its handlers with `register` and then calls
+//! `rust_remove_comments_cli::run()`, so rules like "keep comments naming a Jira ticket" need
+//! neither a fork nor changes to syntax_rules.json. Handlers are asked in the order they were
+//! registered, after the protections that always apply (license headers,
+//! `comment-remover:keep` and compiler directives) and before all the optional ones.
+
+use std::sync::{Arc, RwLock};
+
+use crate::result::CommentSpan;
+
+/// The file a comment is in.
+pub struct FileContext<'a> {
+    pub path: &'a str,
+    /// Language name from the syntax rules, e.g. `Rust` or `C++`
+    pub language: &'a str,
+    pub content: &'a str,
+}
+
+/// What a handler decides about a comment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Keep,
+    /// Remove it even if an option such as `--keep-attached` would keep it; interactive runs
+    /// still ask
+    Remove,
+    /// Leave the decision to the next handler and the options
+    Default,
+}
+
+pub trait CommentHandler: Send + Sync {
+    /// Identifies the handler in verbose output and in the cache of clean files.
+    fn name(&self) -> &str;
+
+    /// Decides about a comment; its `outcome` is what the options would do with it.
+    fn classify(&self, comment: &CommentSpan, context: &FileContext) -> Action;
+}
+
+static HANDLERS: RwLock<Vec<Arc<dyn CommentHandler>>> = RwLock::new(Vec::new());
+
+/// Adds a handler for every run started afterwards.
+pub fn register(handler: impl CommentHandler + 'static) {
+    HANDLERS.write().unwrap_or_else(|e| e.into_inner()).push(Arc::new(handler));
+}
+
+/// The handlers registered so far, in order.
+pub fn registered() -> Vec<Arc<dyn CommentHandler>> {
+    HANDLERS.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
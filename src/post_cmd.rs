@@ -0,0 +1,64 @@
+use anyhow::{Context, Result};
+use std::{path::Path, process::Command};
+
+use crate::Error;
+
+/// Placeholder in `--post-cmd` and `--verify-cmd` that is replaced by the quoted path of the modified file
+const FILE_PLACEHOLDER: &str = "{file}";
+
+/// Runs `template` through the shell for `file`; the path is appended when the template has no `{file}`.
+pub fn run(template: &str, file: &Path) -> Result<()> {
+    let quoted = shell_quote(&file.to_string_lossy());
+    let command_line = if template.contains(FILE_PLACEHOLDER) {
+        template.replace(FILE_PLACEHOLDER, &quoted)
+    } else {
+        format!("{} {}", template, quoted)
+    };
+    execute(&command_line, "post command", Error::PostCommand)
+}
+
+/// Runs the `--verify-cmd` `template` for `file`. Without `{file}` the command runs as is,
+/// since checks like `cargo check` look at the whole project.
+pub fn verify(template: &str, file: &Path) -> Result<()> {
+    let command_line = template.replace(FILE_PLACEHOLDER, &shell_quote(&file.to_string_lossy()));
+    execute(&command_line, "verify command", Error::Verification)
+}
+
+fn execute(command_line: &str, what: &str, error: fn(String) -> Error) -> Result<()> {
+    let output = shell(command_line)
+        .output()
+        .with_context(|| format!("Failed to run {}: {}", what, command_line))?;
+    if !output.status.success() {
+        // Compilers report on stdout as often as on stderr
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let details = if stderr.trim().is_empty() { stdout.trim() } else { stderr.trim() };
+        let status = output.status.code().map_or("a signal".to_string(), |code| format!("status {}", code));
+        return Err(error(format!("`{}` exited with {}: {}", command_line, status, details)).into());
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn shell(command_line: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(command_line);
+    command
+}
+
+#[cfg(not(unix))]
+fn shell(command_line: &str) -> Command {
+    let mut command = Command::new("cmd");
+    command.arg("/C").arg(command_line);
+    command
+}
+
+#[cfg(unix)]
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(not(unix))]
+fn shell_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
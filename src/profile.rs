@@ -0,0 +1,56 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::{config::ProjectConfig, StripArgs};
+
+/// Presets for what `remove` keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    /// Also keep documentation comments in every language and TODO/FIXME/HACK/XXX notes
+    Conservative,
+    /// The defaults of the individual options
+    Standard,
+    /// Strip everything that is only a comment: docstrings, crate docs, tool directives,
+    /// comments in macros and license headers
+    Aggressive,
+}
+
+/// Comments a profile keeps, even when `--match` selects them
+const CONSERVATIVE_KEEP: &str = r"\b(TODO|FIXME|HACK|XXX)\b";
+
+/// The options a run ends up with: individual flags win over the project config, which wins
+/// over the profile from the command line or the config.
+#[derive(Debug)]
+pub struct Settings {
+    pub keep_license_header: bool,
+    /// Keep Python docstrings
+    pub keep_docstrings: bool,
+    /// Keep documentation comments (`///`, `/** */`, ...) in every language
+    pub keep_doc_comments: bool,
+    pub strip_inner_docs: bool,
+    pub strip_directives: bool,
+    pub strip_macros: bool,
+    /// `--keep` regexes the profile adds
+    pub keep_patterns: Vec<String>,
+}
+
+impl Settings {
+    pub fn resolve(args: &StripArgs, config: &ProjectConfig) -> Self {
+        let profile = args.profile.or(config.profile).unwrap_or(Profile::Standard);
+        let aggressive = profile == Profile::Aggressive;
+        let conservative = profile == Profile::Conservative;
+        let pick = |flag: Option<bool>, configured: Option<bool>, preset: bool| flag.or(configured).unwrap_or(preset);
+
+        let strip_docstrings = pick(args.strip_docstrings, config.strip_docstrings, aggressive);
+        Settings {
+            keep_license_header: pick(args.keep_license_header, config.keep_license_header, !aggressive),
+            keep_docstrings: !strip_docstrings,
+            keep_doc_comments: conservative && !strip_docstrings,
+            strip_inner_docs: pick(args.strip_inner_docs, config.strip_inner_docs, aggressive),
+            strip_directives: pick(args.strip_directives, config.strip_directives, aggressive),
+            strip_macros: pick(args.strip_macros, config.strip_macros, aggressive),
+            keep_patterns: if conservative { vec![CONSERVATIVE_KEEP.to_string()] } else { Vec::new() },
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use crate::{Comment, CommentKind, LanguageRules};
+
+pub fn is_python(language: &LanguageRules) -> bool {
+    language.name == "Python"
+}
+
+/// Python has no block comments, so the triple-quoted matches from the rules are string literals.
+/// Docstrings become `Doc` comments, bare string statements stay `Block` comments, and strings
+/// used as values are dropped since removing them would change the program.
+pub fn classify_strings(content: &str, comments: Vec<Comment>) -> Vec<Comment> {
+    comments
+        .into_iter()
+        .filter_map(|comment| {
+            if comment.kind != CommentKind::Block {
+                return Some(comment);
+            }
+            let text = &content[comment.range.clone()];
+            let start = comment.range.start + (text.len() - text.trim_start().len());
+            // The rules match from the quotes, a prefix like `r` goes with the string
+            let range = prefix_start(content, start)..comment.range.end;
+            match classify(content, start, comment.range.end) {
+                StringRole::Docstring => Some(Comment { kind: CommentKind::Doc, range }),
+                StringRole::Statement => Some(Comment { range, ..comment }),
+                StringRole::Value => None,
+            }
+        })
+        .collect()
+}
+
+enum StringRole {
+    Docstring,
+    Statement,
+    Value,
+}
+
+fn classify(content: &str, start: usize, end: usize) -> StringRole {
+    let line_start = content[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = content[end..].find('\n').map_or(content.len(), |i| end + i);
+
+    // String prefixes: r and u strings can be docstrings, f and b strings cannot
+    let before = content[line_start..start].trim_start();
+    let prefix_ok = before.len() <= 2 && before.chars().all(|c| "rRuUfFbB".contains(c));
+    let after = content[end..line_end].trim();
+    let after = after.strip_prefix(';').unwrap_or(after).trim_start();
+    if !prefix_ok || !(after.is_empty() || after.starts_with('#')) {
+        return StringRole::Value;
+    }
+
+    let indent = line_indent(&content[line_start..]);
+    let mut previous = code_lines_before(content, line_start);
+    let Some(last) = previous.next() else {
+        return docstring_unless(before);
+    };
+
+    // A string continuing an expression from the line above is a value
+    let last_code = strip_comment(last).trim_end();
+    if last_code.ends_with(['=', '(', '[', '{', ',', '\\', '+', '%']) {
+        return StringRole::Value;
+    }
+    if !last_code.ends_with(':') {
+        return StringRole::Statement;
+    }
+
+    // The header owning this block is the nearest line indented less than the string
+    let header = std::iter::once(last)
+        .chain(previous)
+        .find(|line| line_indent(line) < indent)
+        .map(str::trim_start)
+        .unwrap_or_default();
+    if ["def ", "async def ", "class "].iter().any(|keyword| header.starts_with(keyword)) {
+        docstring_unless(before)
+    } else {
+        StringRole::Statement
+    }
+}
+
+/// Where the string whose quotes open at `start` begins, before its prefix letters.
+fn prefix_start(content: &str, start: usize) -> usize {
+    let prefix = content[..start].bytes().rev().take_while(|byte| b"rRuUfFbB".contains(byte)).count();
+    start - prefix.min(2)
+}
+
+fn docstring_unless(prefix: &str) -> StringRole {
+    if prefix.contains(['f', 'F', 'b', 'B']) {
+        StringRole::Statement
+    } else {
+        StringRole::Docstring
+    }
+}
+
+/// Non-blank, non-comment lines before `offset`, nearest first.
+fn code_lines_before(content: &str, offset: usize) -> impl Iterator<Item = &str> {
+    content[..offset]
+        .lines()
+        .rev()
+        .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+}
+
+fn line_indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn strip_comment(line: &str) -> &str {
+    // Good enough for line endings: a `#` inside a string would rarely end a header line
+    line.find(" #").map_or(line, |i| &line[..i])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Rules, Stripper};
+
+    /// Each string found in `source` with what it was taken for.
+    fn strings(source: &str) -> Vec<(CommentKind, String)> {
+        let rules = Rules::bundled();
+        let result = Stripper::new(&rules, &[]).unwrap().strip(source, "python").unwrap();
+        result.comments.into_iter().map(|comment| (comment.kind, comment.text.trim().to_string())).collect()
+    }
+
+    #[test]
+    fn docstrings_open_modules_classes_and_functions() {
+        let source = "\"\"\"Module.\"\"\"\n\nclass A:\n    \"\"\"Class.\"\"\"\n\n    def f(self):\n        r'''Method.'''\n        return 1\n\nasync def g():\n    \"\"\"Coroutine.\"\"\"\n";
+        let expected = ["\"\"\"Module.\"\"\"", "\"\"\"Class.\"\"\"", "r'''Method.'''", "\"\"\"Coroutine.\"\"\""];
+        assert_eq!(strings(source), expected.map(|text| (CommentKind::Doc, text.to_string())));
+    }
+
+    #[test]
+    fn strings_used_as_values_are_not_comments() {
+        for source in [
+            "x = \"\"\"value\"\"\"\n",
+            "x = (\n    \"\"\"value\"\"\"\n)\n",
+            "f(\"\"\"argument\"\"\")\n",
+            "f(\n    a,\n    \"\"\"argument\"\"\",\n)\n",
+            "def f():\n    return \"\"\"value\"\"\"\n",
+            "x = \"\"\"a\"\"\" + \\\n    \"\"\"b\"\"\"\n",
+        ] {
+            assert_eq!(strings(source), Vec::new(), "in {:?}", source);
+        }
+    }
+
+    #[test]
+    fn other_bare_strings_are_block_comments() {
+        let source = "def f():\n    \"\"\"Doc.\"\"\"\n    x = 1\n    \"\"\"Note.\"\"\"\n    if x:\n        \"\"\"In a branch.\"\"\"\n        pass\n";
+        assert_eq!(
+            strings(source),
+            vec![
+                (CommentKind::Doc, "\"\"\"Doc.\"\"\"".to_string()),
+                (CommentKind::Block, "\"\"\"Note.\"\"\"".to_string()),
+                (CommentKind::Block, "\"\"\"In a branch.\"\"\"".to_string()),
+            ]
+        );
+        assert_eq!(strings("def f():\n    f\"\"\"Not {doc}.\"\"\"\n")[0].0, CommentKind::Block);
+    }
+}
@@ -0,0 +1,139 @@
+use anyhow::{Context, Result};
+use std::{
+    fs::{self, Permissions},
+    path::{Path, PathBuf},
+};
+
+/// Whether `path` cannot be rewritten as is: the file itself is read-only, or the directory
+/// the new version is created in is, as in Bazel output trees. Besides being marked read-only,
+/// either can be closed to this user only, like a file someone else owns, or be on a
+/// read-only mount.
+pub fn is_read_only(path: &Path) -> bool {
+    let read_only = |path: &Path| {
+        fs::metadata(path).is_ok_and(|metadata| metadata.permissions().readonly()) || write_denied(path)
+    };
+    read_only(path) || read_only(parent_dir(path))
+}
+
+/// Why `path` cannot be rewritten even with `--force-readonly`, which only adds write
+/// permission to what this user owns: the file or its directory belongs to someone else, or
+/// sits on a read-only mount.
+pub fn not_writable(path: &Path) -> Option<&'static str> {
+    if let Some(reason) = beyond_owner(path) {
+        return Some(reason);
+    }
+    beyond_owner(parent_dir(path)).map(|reason| match reason {
+        "owned by another user" => "its directory is owned by another user",
+        reason => reason,
+    })
+}
+
+/// Makes a read-only file and its directory writable for `--force-readonly`, and puts the
+/// original permissions back when dropped.
+pub struct Writable {
+    restore: Vec<(PathBuf, Permissions)>,
+}
+
+impl Writable {
+    pub fn make(path: &Path) -> Result<Self> {
+        let mut writable = Writable { restore: Vec::new() };
+        for target in [path, parent_dir(path)] {
+            let permissions = fs::metadata(target)
+                .with_context(|| format!("Failed to read permissions of {}", target.display()))?
+                .permissions();
+            if !permissions.readonly() {
+                continue;
+            }
+            fs::set_permissions(target, with_owner_write(&permissions))
+                .with_context(|| format!("Failed to make {} writable", target.display()))?;
+            writable.restore.push((target.to_path_buf(), permissions));
+        }
+        Ok(writable)
+    }
+}
+
+impl Drop for Writable {
+    fn drop(&mut self) {
+        // The file is restored before its directory, which may be read-only again afterwards
+        for (path, permissions) in self.restore.drain(..) {
+            if let Err(e) = fs::set_permissions(&path, permissions) {
+                eprintln!("Failed to restore the permissions of {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+/// Whether writing `path` would be refused; a missing path is left for the write to report.
+#[cfg(unix)]
+fn write_denied(path: &Path) -> bool {
+    access_error(path).is_some()
+}
+
+/// The errno of a refused write access check of `path`.
+#[cfg(unix)]
+fn access_error(path: &Path) -> Option<i32> {
+    use std::{ffi::CString, io, os::unix::ffi::OsStrExt};
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: `path` is a NUL-terminated string
+    if unsafe { libc::access(path.as_ptr(), libc::W_OK) } == 0 {
+        return None;
+    }
+    io::Error::last_os_error()
+        .raw_os_error()
+        .filter(|errno| matches!(*errno, libc::EACCES | libc::EPERM | libc::EROFS))
+}
+
+#[cfg(unix)]
+fn beyond_owner(path: &Path) -> Option<&'static str> {
+    use std::os::unix::fs::MetadataExt;
+    match access_error(path)? {
+        libc::EROFS => Some("on a read-only file system"),
+        // SAFETY: geteuid cannot fail
+        _ if fs::metadata(path).is_ok_and(|metadata| metadata.uid() != unsafe { libc::geteuid() }) => {
+            Some("owned by another user")
+        }
+        _ => None,
+    }
+}
+
+/// Whether writing `path` would be refused; a missing path is left for the write to report.
+/// Directories cannot be opened for writing, and their read-only attribute does not stop
+/// files being created in them, so only files are tried.
+#[cfg(not(unix))]
+fn write_denied(path: &Path) -> bool {
+    path.is_file()
+        && fs::OpenOptions::new()
+            .write(true)
+            .open(path)
+            .is_err_and(|e| e.kind() == std::io::ErrorKind::PermissionDenied)
+}
+
+/// A refused write to a file without the read-only attribute comes from its access control
+/// list, which clearing the attribute does not change.
+#[cfg(not(unix))]
+fn beyond_owner(path: &Path) -> Option<&'static str> {
+    let read_only = fs::metadata(path).is_ok_and(|metadata| metadata.permissions().readonly());
+    (!read_only && write_denied(path)).then_some("denied by its access control list")
+}
+
+#[cfg(unix)]
+fn with_owner_write(permissions: &Permissions) -> Permissions {
+    use std::os::unix::fs::PermissionsExt;
+    // Only the owner gains write access, unlike `set_readonly(false)`
+    Permissions::from_mode(permissions.mode() | 0o200)
+}
+
+#[cfg(not(unix))]
+fn with_owner_write(permissions: &Permissions) -> Permissions {
+    let mut permissions = permissions.clone();
+    #[allow(clippy::permissions_set_readonly_false)]
+    permissions.set_readonly(false);
+    permissions
+}
@@ -0,0 +1,70 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path};
+
+use crate::atomic_write;
+
+/// Bumped whenever the file layout changes, so an old recording is rejected rather than misread
+const RECORDING_VERSION: u32 = 1;
+
+/// One answer to the "Remove this comment?" prompt.
+#[derive(Serialize, Deserialize)]
+struct Answer {
+    comment: String,
+    remove: bool,
+}
+
+/// Keep/remove answers from an interactive run for `--record` and `--replay`. Comments are
+/// matched by their text rather than their position, so answers still apply after the code
+/// around them changed, e.g. in a regenerated file or after a merge.
+#[derive(Serialize, Deserialize)]
+pub struct Recording {
+    version: u32,
+    /// Answers by file, in the order the comments were asked about
+    files: BTreeMap<String, Vec<Answer>>,
+}
+
+impl Default for Recording {
+    fn default() -> Self {
+        Recording { version: RECORDING_VERSION, files: BTreeMap::new() }
+    }
+}
+
+impl Recording {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read decisions file: {}", path.display()))?;
+        let recording: Recording = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid decisions file: {}", path.display()))?;
+        if recording.version != RECORDING_VERSION {
+            anyhow::bail!("Unsupported decisions file version {} in {}", recording.version, path.display());
+        }
+        Ok(recording)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        atomic_write::write_atomic(path, (serde_json::to_string_pretty(self)? + "\n").as_bytes())
+            .with_context(|| format!("Failed to write decisions file: {}", path.display()))
+    }
+
+    /// Takes the first unused answer for `comment` in `file`, so a comment that occurs several
+    /// times gets its answers in order.
+    pub fn take(&mut self, file: &str, comment: &str) -> Option<bool> {
+        let answers = self.files.get_mut(file)?;
+        let comment = normalize(comment);
+        let index = answers.iter().position(|answer| answer.comment == comment)?;
+        Some(answers.remove(index).remove)
+    }
+
+    pub fn push(&mut self, file: &str, comment: &str, remove: bool) {
+        self.files
+            .entry(file.to_string())
+            .or_default()
+            .push(Answer { comment: normalize(comment), remove });
+    }
+}
+
+/// Comment text without the indentation of its lines, which reformatting changes.
+pub fn normalize(comment: &str) -> String {
+    comment.lines().map(str::trim).collect::<Vec<_>>().join("\n")
+}
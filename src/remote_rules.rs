@@ -0,0 +1,115 @@
+use anyhow::{Context, Result};
+use colored::*;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    atomic_write,
+    cache::fingerprint,
+    history, parse_syntax_rules,
+    validate_rules::{self, Severity},
+    Error,
+};
+
+/// How long a downloaded rules file is used before the server is asked whether it changed
+const MAX_AGE: Duration = Duration::from_secs(60 * 60);
+/// Give up on a server that does not answer, and use the cached copy
+const TIMEOUT_SECS: &str = "30";
+
+/// The local copy of the rules at `url`, in `.comment-remover/rules`. A copy younger than an
+/// hour is used as is; an older one is revalidated with its ETag, and used anyway when the
+/// server cannot be reached, so offline runs keep working. Downloading goes through `curl`.
+pub fn fetch(url: &str) -> Result<PathBuf> {
+    let dir = history::state_dir()?.join("rules");
+    let name = fingerprint(url.as_bytes());
+    let cached = dir.join(format!("{}.json", name));
+    let etag = dir.join(format!("{}.etag", name));
+
+    let age = fs::metadata(&cached).and_then(|metadata| metadata.modified()).ok().and_then(|time| time.elapsed().ok());
+    if age.is_some_and(|age| age < MAX_AGE) {
+        return Ok(cached);
+    }
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+    match download(url, &cached, &etag) {
+        Ok(()) => Ok(cached),
+        Err(e) if cached.is_file() => {
+            info!("{} Could not update the rules from {}, using the cached copy: {:#}", "Warning:".yellow(), url, e);
+            Ok(cached)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Replaces `cached` with the rules at `url` unless the server reports them unchanged.
+fn download(url: &str, cached: &Path, etag: &Path) -> Result<()> {
+    let body = cached.with_extension("download");
+    let new_etag = etag.with_extension("etag.download");
+    let mut command = Command::new("curl");
+    command
+        .args(["--silent", "--show-error", "--fail", "--location", "--max-time", TIMEOUT_SECS])
+        .arg("--write-out")
+        .arg("%{http_code}")
+        .arg("--output")
+        .arg(&body)
+        .arg("--etag-save")
+        .arg(&new_etag);
+    // Without a copy to fall back on, a 304 would leave nothing to use
+    if cached.is_file() && etag.is_file() {
+        command.arg("--etag-compare").arg(etag);
+    }
+    let output = command.arg(url).output().context("Failed to run curl, is it installed?")?;
+    let status = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let result = if !output.status.success() {
+        Err(Error::SyntaxRules(format!(
+            "could not download {}: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+        .into())
+    } else if status == "304" {
+        // Unchanged: the copy is good for another `MAX_AGE`
+        fs::File::options()
+            .append(true)
+            .open(cached)
+            .and_then(|file| file.set_modified(SystemTime::now()))
+            .with_context(|| format!("Failed to update {}", cached.display()))
+    } else {
+        store(url, &body, cached).map(|()| {
+            // A server without ETags is simply asked again after `MAX_AGE`
+            if fs::metadata(&new_etag).is_ok_and(|metadata| metadata.len() > 0) {
+                let _ = fs::rename(&new_etag, etag);
+            } else {
+                let _ = fs::remove_file(etag);
+            }
+        })
+    };
+    let _ = fs::remove_file(&body);
+    let _ = fs::remove_file(&new_etag);
+    result
+}
+
+/// Keeps the downloaded rules only if `validate-rules` finds no error in them and they load,
+/// so a broken upload never replaces a good copy.
+fn store(url: &str, body: &Path, cached: &Path) -> Result<()> {
+    let content = fs::read_to_string(body).with_context(|| format!("Failed to read the rules downloaded from {}", url))?;
+    let origin = format!("the rules downloaded from {}", url);
+    let diagnostics = validate_rules::check(&content);
+    let mut errors = diagnostics.iter().filter(|diagnostic| diagnostic.severity == Severity::Error);
+    if let Some(first) = errors.next() {
+        return Err(Error::SyntaxRules(format!(
+            "{} have {} error(s), the first on line {}: {}",
+            origin,
+            errors.count() + 1,
+            first.line,
+            first.message
+        ))
+        .into());
+    }
+    parse_syntax_rules(&content, &origin)?;
+    atomic_write::write_atomic(cached, content.as_bytes())
+        .with_context(|| format!("Failed to write {}", cached.display()))
+}
@@ -0,0 +1,800 @@
+use anyhow::{Context, Result};
+use colored::*;
+use encoding_rs::Encoding;
+use indicatif::ProgressBar;
+use regex::Regex;
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, VecDeque},
+    env,
+    fmt::Display,
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::Arc,
+};
+
+use crate::{
+    age::{self, LineAges},
+    atomic_write,
+    backup::{self, mirrored_path, BackupPolicy, BackupStrategy},
+    c_family,
+    cache::Cache,
+    check_engine, config::ProjectConfig, detect_comments, detect_file_type, encoding, find_language, find_license_header,
+    git, history::Journal, ignore::IgnoreList,
+    lock::FileLock, output::FileFailure, patch::{self, RemoveOutput}, paths, plugin::{self, CommentHandler}, post_cmd, profile::Settings,
+    readonly::{self, Writable},
+    recording::Recording,
+    result::{CommentSpan, RemovalResult},
+    get_comment_patterns, is_supported, markdown, notebook, python, remove_comments, resolve_overlaps, review::Candidate, rust,
+    ask_about_comment, syntax_tree, CommentFilters, PromptAnswer,
+    CommentAction, CommentPatterns, Engine, Error, LanguageRules, RemovalPolicy, RemoveArgs, ReportFormat, SyntaxRules,
+};
+
+/// Applies one set of `remove` options to any number of files.
+pub struct Remover<'a> {
+    rules: &'a SyntaxRules,
+    args: RemoveArgs,
+    forced_language: Option<&'a LanguageRules>,
+    license_regex: Option<Regex>,
+    forced_encoding: Option<&'static Encoding>,
+    filters: CommentFilters,
+    /// Each language's rules compiled once a file in it comes up; the rules are borrowed for
+    /// as long as the remover lives, so their address identifies them
+    patterns: RefCell<HashMap<*const LanguageRules, Rc<CommentPatterns>>>,
+    /// Comments the prompt never asks about; only read when there is a prompt
+    ignored: Option<RefCell<IgnoreList>>,
+    /// Answers from `--replay` not used yet
+    replay: Option<RefCell<Recording>>,
+    /// Answers given in this run, for `--record`
+    record: Option<RefCell<Recording>>,
+    /// Only comments last changed before this Unix timestamp are removed, with `--older-than`
+    cutoff: Option<i64>,
+    ages: RefCell<Option<(String, Rc<LineAges>)>>,
+    settings: Settings,
+    handlers: Vec<Arc<dyn CommentHandler>>,
+    backups: BackupPolicy,
+    /// Copies written below `--out-dir` in this run, with the file each was made from
+    copies: RefCell<HashMap<PathBuf, PathBuf>>,
+    journal: Option<Journal>,
+    post_cmd: Option<String>,
+    verify_cmd: Option<String>,
+    cache: Option<RefCell<Cache>>,
+    decisions: Decisions,
+    progress: ProgressBar,
+    /// The files are scratch copies, see `set_scratch`
+    scratch: Cell<bool>,
+    /// Which of how many files is being processed, for the prompt
+    position: Cell<Option<(usize, usize)>>,
+}
+
+/// Who decides about each comment the policy allows removing.
+pub enum Decisions {
+    /// `--auto` and `--check`: all of them go
+    All,
+    /// Ask on stdin, one file at a time
+    Prompt(RefCell<PromptQueue>),
+    /// Remove nothing and collect the comments for `review`
+    Collect(RefCell<Vec<Candidate>>),
+    /// What `review` chose, per file in the order the comments were collected
+    Chosen(RefCell<HashMap<String, VecDeque<bool>>>),
+}
+
+/// The comments of the file being processed interactively: they are collected first and then
+/// asked about together, so an answer can be changed until the file is written.
+pub enum PromptQueue {
+    Collecting(Vec<Candidate>),
+    /// Whether to remove each comment, in the order they were collected
+    Answering(VecDeque<bool>),
+}
+
+impl Default for PromptQueue {
+    fn default() -> Self {
+        PromptQueue::Collecting(Vec::new())
+    }
+}
+
+/// What happened to a single file.
+pub struct FileReport {
+    pub changed: bool,
+    pub comments_removed: usize,
+    /// Every comment found and what happened to it; in notebooks positions are within the cell
+    pub comments: Vec<CommentSpan>,
+    /// The diff of the file with `--output patch`
+    pub patch: Option<String>,
+    /// The original and new text of a changed file with `--report html`
+    pub contents: Option<(String, String)>,
+}
+
+/// Totals over a run, so one bad file is reported at the end instead of stopping the others.
+#[derive(Default)]
+pub struct RunSummary {
+    pub processed: usize,
+    pub modified: usize,
+    pub skipped: usize,
+    pub errored: usize,
+    pub comments_removed: usize,
+}
+
+impl RunSummary {
+    /// Counts a file's outcome; returns the error to show, if there is one.
+    pub fn record(&mut self, result: Result<FileReport>) -> Option<(FileFailure, anyhow::Error)> {
+        self.processed += 1;
+        match result {
+            Ok(report) => {
+                if report.changed {
+                    self.modified += 1;
+                }
+                self.comments_removed += report.comments_removed;
+                None
+            }
+            Err(e) if matches!(
+                e.root_cause().downcast_ref::<Error>(),
+                Some(
+                    Error::UnsupportedFileType(_)
+                        | Error::BinaryFile
+                        | Error::FileTooLarge(..)
+                        | Error::ReadOnly
+                        | Error::NotWritable(_)
+                        | Error::Locked(_)
+                )
+            ) => {
+                self.skipped += 1;
+                Some((FileFailure::Skipped, e))
+            }
+            Err(e) => {
+                self.errored += 1;
+                Some((FileFailure::Errored, e))
+            }
+        }
+    }
+
+    pub fn render(&self, check: bool) -> String {
+        let (modified, removed) = if check {
+            ("With comments", "Comments removable")
+        } else {
+            ("Modified", "Comments removed")
+        };
+        [
+            ("Processed", self.processed),
+            (modified, self.modified),
+            ("Skipped", self.skipped),
+            ("Errored", self.errored),
+            (removed, self.comments_removed),
+        ]
+        .iter()
+        .map(|(label, count)| format!("  {:<20} {:>6}", label, count))
+        .collect::<Vec<_>>()
+        .join("\n")
+    }
+}
+
+impl<'a> Remover<'a> {
+    pub fn new(rules: &'a SyntaxRules, args: &RemoveArgs, config: &ProjectConfig) -> Result<Self> {
+        check_engine(args.strip.engine)?;
+        let settings = Settings::resolve(&args.strip, config);
+        let license_regex = if settings.keep_license_header {
+            Some(Regex::new(&args.strip.license_pattern).map_err(|e| Error::InvalidPattern(e.to_string()))?)
+        } else {
+            None
+        };
+        let forced_encoding = args.encoding.as_deref().map(encoding::parse_encoding).transpose()?;
+        let forced_language = args.strip.lang.as_deref().map(|name| find_language(name, rules)).transpose()?;
+        let match_patterns = [config.match_patterns.as_slice(), args.strip.match_patterns.as_slice()].concat();
+        let keep_patterns =
+            [config.keep.as_slice(), args.strip.keep_patterns.as_slice(), settings.keep_patterns.as_slice()].concat();
+        let filters = CommentFilters::new(&match_patterns, &keep_patterns)?;
+        let handlers = plugin::registered();
+        if args.older_than.is_some() {
+            git::ensure_repository()?;
+        }
+
+        let backups = BackupPolicy::new(
+            if args.force { BackupStrategy::None } else { args.backup_strategy },
+            args.backup_dir.as_ref().map(PathBuf::from),
+        );
+
+        let writes = !args.check && args.output == RemoveOutput::Files;
+        let journal = if writes && !args.no_history { Some(Journal::open()?) } else { None };
+
+        // Prompts can leave removable comments behind, so only automatic runs prove a file clean
+        // Ages change with time and commits, which the cache would not notice
+        let cache = if args.no_cache || !(args.auto || args.check) || args.older_than.is_some() {
+            None
+        } else {
+            let fingerprint = format!(
+                "{} {:?} {:?} {:?} {} {:?} {:?} {:?} {} {} {:?} {} {} {} {:?} {:?} {:?}",
+                env!("CARGO_PKG_VERSION"),
+                rules.languages,
+                rules.suffix_overrides,
+                settings,
+                args.strip.license_pattern,
+                args.encoding,
+                args.strip.engine,
+                args.strip.lang,
+                args.strip.only_dead_code,
+                args.strip.preserve_lines,
+                args.strip.action,
+                args.strip.keep_attached,
+                args.markdown,
+                args.strip.strip_if0,
+                match_patterns,
+                keep_patterns,
+                handlers.iter().map(|handler| handler.name()).collect::<Vec<_>>(),
+            );
+            Some(RefCell::new(Cache::open(&fingerprint)?))
+        };
+
+        let decisions = if args.auto || args.check { Decisions::All } else { Decisions::Prompt(RefCell::default()) };
+        Ok(Remover {
+            rules,
+            args: args.clone(),
+            forced_language,
+            license_regex,
+            forced_encoding,
+            filters,
+            patterns: RefCell::default(),
+            ignored: match decisions {
+                Decisions::Prompt(_) => Some(RefCell::new(IgnoreList::open()?)),
+                _ => None,
+            },
+            replay: args.replay.as_deref().map(|path| Recording::load(Path::new(path))).transpose()?.map(RefCell::new),
+            record: args.record.as_ref().map(|_| RefCell::default()),
+            cutoff: args.older_than.map(age::cutoff),
+            ages: RefCell::new(None),
+            settings,
+            handlers,
+            backups,
+            copies: RefCell::default(),
+            journal,
+            post_cmd: args.post_cmd.clone().or_else(|| config.post_cmd.clone()),
+            verify_cmd: args.verify_cmd.clone().or_else(|| config.verify_cmd.clone()),
+            cache,
+            decisions,
+            progress: ProgressBar::hidden(),
+            scratch: Cell::new(false),
+            position: Cell::new(None),
+        })
+    }
+
+    /// While a progress bar is visible it replaces the per-file messages.
+    pub fn set_progress(&mut self, progress: ProgressBar) {
+        self.progress = progress;
+    }
+
+    /// Files processed from now on are scratch copies, like the members of an unpacked archive:
+    /// they get no backup, history entry or cache entry.
+    pub fn set_scratch(&self, scratch: bool) {
+        self.scratch.set(scratch);
+    }
+
+    /// The file processed next is number `file` of `files`, as the prompt shows.
+    pub fn set_position(&self, file: usize, files: usize) {
+        self.position.set(Some((file, files)));
+    }
+
+    /// Replaces how comments are decided on; only automatic runs prove a file clean, so this
+    /// also stops using the cache.
+    pub fn set_decisions(&mut self, decisions: Decisions) {
+        self.decisions = decisions;
+        self.cache = None;
+    }
+
+    /// The comments collected with `Decisions::Collect`, in the order they were found.
+    pub fn take_candidates(&self) -> Vec<Candidate> {
+        match &self.decisions {
+            Decisions::Collect(candidates) => candidates.take(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Writes the answers given in this run for `--record`; call once the run is over.
+    pub fn save_recording(&self) -> Result<()> {
+        match (&self.record, &self.args.record) {
+            (Some(record), Some(path)) => record.borrow().save(Path::new(path)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes the cache of files found to have nothing to remove; call once the run is over.
+    pub fn save_cache(&self) -> Result<()> {
+        match &self.cache {
+            Some(cache) => cache.borrow().save(),
+            None => Ok(()),
+        }
+    }
+
+    fn say(&self, message: impl Display) {
+        if self.progress.is_hidden() {
+            info!("{}", message);
+        }
+    }
+
+    /// Whether `path` is a file these options would process; with `--lang` that is every file.
+    pub fn supports(&self, path: &Path) -> bool {
+        self.forced_language.is_some()
+            || is_supported(path, self.rules)
+            || (self.args.markdown && markdown::is_markdown(path))
+    }
+
+    /// Processes one file; in check mode `changed` means it contains comments to remove.
+    pub fn process_file(&self, path: &Path) -> Result<FileReport> {
+        let args = &self.args;
+        let verbose = args.verbose;
+        let file_path = &*path.to_string_lossy();
+        // Check before reading so a huge generated file is never loaded into memory
+        if args.max_size > 0
+            && let Ok(metadata) = fs::metadata(path)
+            && metadata.len() > args.max_size
+        {
+            return Err(Error::FileTooLarge(metadata.len(), args.max_size).into());
+        }
+        let html_report = args.report == Some(ReportFormat::Html);
+        let out_dir = args.out_dir.as_deref().filter(|_| !self.scratch.get()).map(Path::new);
+        let writes = !args.check
+            && out_dir.is_none()
+            && !html_report
+            && args.output != RemoveOutput::Patch
+            && !matches!(self.decisions, Decisions::Collect(_));
+        if writes {
+            if let Some(reason) = readonly::not_writable(path) {
+                return Err(Error::NotWritable(reason).into());
+            }
+            if !args.force_readonly && readonly::is_read_only(path) {
+                return Err(Error::ReadOnly.into());
+            }
+        }
+        let (lock, bytes) = FileLock::acquire(path)?;
+        // A copy is written even when nothing changed, so the cache cannot skip the file
+        let cache = self.cache.as_ref().filter(|_| !self.scratch.get() && out_dir.is_none());
+        if let Some(cache) = cache
+            && cache.borrow().is_clean(path, &bytes)
+        {
+            if verbose {
+                info!("Unchanged since an earlier run found nothing to remove, skipping: {}", file_path);
+            }
+            return Ok(FileReport { changed: false, comments_removed: 0, comments: Vec::new(), patch: None, contents: None });
+        }
+        let source = encoding::decode(&bytes, self.forced_encoding)
+            .with_context(|| format!("Failed to decode file: {}", file_path))?;
+        let content = &source.text;
+
+        if verbose {
+            info!("File content length: {} bytes", content.len());
+            info!("File encoding: {}{}", source.encoding.name(), if source.bom { " (with BOM)" } else { "" });
+        }
+        
+        let mut result = self.strip_file(path, content, true)?;
+        if let Decisions::Prompt(queue) = &self.decisions {
+            // The first pass only collected the comments; now they are asked about together so
+            // answers can still be changed, and the second pass applies them
+            let PromptQueue::Collecting(candidates) = queue.take() else {
+                unreachable!("the queue collects until the file's comments are asked about")
+            };
+            let answers = self.ask_all(file_path, candidates);
+            *queue.borrow_mut() = PromptQueue::Answering(answers);
+            let answered = self.strip_file(path, content, false);
+            *queue.borrow_mut() = PromptQueue::default();
+            result = answered?;
+        }
+        let (comments_found, comments_removed) = (result.found(), result.removed());
+        let new_content = result.content;
+        let comments = result.comments;
+
+        let changed = new_content != *content;
+        if !changed && let Some(cache) = cache {
+            cache.borrow_mut().mark_clean(path, &bytes);
+        }
+        if html_report {
+            if changed {
+                self.say(format!("Added {} removals from {} to the report", comments_removed, file_path.yellow()));
+            }
+            let contents = changed.then(|| (content.clone(), new_content));
+            return Ok(FileReport { changed, comments_removed, comments, patch: None, contents });
+        }
+        if args.check || matches!(self.decisions, Decisions::Collect(_)) {
+            if changed {
+                self.say(format!("Would remove {} comments from: {}", comments_removed, file_path.yellow()));
+            }
+            return Ok(FileReport { changed, comments_removed, comments, patch: None, contents: None });
+        }
+        if args.output == RemoveOutput::Patch {
+            if changed {
+                self.say(format!("Added {} removals from {} to the patch", comments_removed, file_path.yellow()));
+            }
+            let diff = patch::file_diff(&patch::patch_path(path), content, &new_content);
+            return Ok(FileReport { changed, comments_removed, comments, patch: Some(diff), contents: None });
+        }
+        if let Some(out_dir) = out_dir {
+            let encoded = if changed { encoding::encode(&new_content, &source)? } else { bytes };
+            self.write_copy(path, out_dir, &encoded)?;
+            if changed {
+                self.say(format!("Removed {} comments from: {}", comments_removed, file_path.green()));
+            }
+            return Ok(FileReport { changed, comments_removed, comments, patch: None, contents: None });
+        }
+
+        if changed {
+            let encoded = encoding::encode(&new_content, &source)?;
+            lock.check_unchanged()?;
+            let target = self.write_target(path)?;
+            let path = target.as_path();
+            let _writable = if args.force_readonly { Some(Writable::make(path)?) } else { None };
+            let journal = self.journal.as_ref().filter(|_| !self.scratch.get());
+            let entry = match journal {
+                Some(journal) => Some(journal.record(path, content, &new_content)?),
+                None => None,
+            };
+            let written = (|| {
+                if !self.scratch.get()
+                    && let Some(backup_path) = self.backups.create(path)?
+                {
+                    self.say(format!("Created backup file: {}", backup_path.display().to_string().blue()));
+                }
+                atomic_write::write_atomic(path, &encoded).with_context(|| format!("Failed to write modified file: {}", file_path))
+            })();
+            if let Err(e) = written {
+                // The snapshot has to be taken before writing, so drop it again when the file was left untouched
+                if let (Some(journal), Some(entry)) = (journal, &entry) {
+                    journal.forget(entry);
+                }
+                return Err(e);
+            }
+            let mut checked = match &self.post_cmd {
+                Some(post_cmd) => post_cmd::run(post_cmd, path),
+                None => Ok(()),
+            };
+            if checked.is_ok()
+                && let Some(verify_cmd) = &self.verify_cmd
+            {
+                checked = post_cmd::verify(verify_cmd, path);
+            }
+            if let Err(e) = checked {
+                // Put the original back so a failed formatter or a broken file is never left behind
+                atomic_write::write_atomic(path, &encoding::encode(content, &source)?)
+                    .with_context(|| format!("Failed to restore {} after a failed command", file_path))?;
+                if let (Some(journal), Some(entry)) = (journal, &entry) {
+                    journal.forget(entry);
+                }
+                return Err(e.context(format!("Restored the original {}", file_path)));
+            }
+            self.say(format!("Successfully removed comments from: {}", file_path.green()));
+            if verbose {
+                info!("Statistics:");
+                info!("  - Total comments found: {}", comments_found);
+                info!("  - Comments removed: {}", comments_removed);
+                info!("  - Comments preserved: {}", comments_found - comments_removed);
+            }
+        } else {
+            self.say(format!("No comments were removed from: {}", file_path.yellow()));
+            if verbose {
+                info!("  - No comments were found in the file");
+            }
+        }
+
+        Ok(FileReport { changed, comments_removed, comments, patch: None, contents: None })
+    }
+
+    /// Records that `copy` below `--out-dir` is made from `source`, failing if another file of
+    /// this run already went there.
+    pub fn claim_copy(&self, copy: &Path, source: &Path) -> Result<()> {
+        backup::claim(&self.copies, copy, source)
+    }
+
+    /// Writes `encoded` to the copy of `path` below `out_dir`, where the source tree is mirrored
+    /// like in a backup directory. The post and verify commands run on the copy, which is
+    /// deleted if they fail.
+    fn write_copy(&self, path: &Path, out_dir: &Path, encoded: &[u8]) -> Result<()> {
+        let copy = out_dir.join(mirrored_path(path, &env::current_dir()?)?);
+        if fs::canonicalize(&copy).ok() == Some(fs::canonicalize(path)?) {
+            return Err(Error::InvalidOptions(format!("--out-dir would overwrite the original {}", path.display())).into());
+        }
+        self.claim_copy(&copy, path)?;
+        if let Some(parent) = copy.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+        atomic_write::write_atomic(&copy, encoded).with_context(|| format!("Failed to write {}", copy.display()))?;
+        let mut checked = match &self.post_cmd {
+            Some(post_cmd) => post_cmd::run(post_cmd, &copy),
+            None => Ok(()),
+        };
+        if checked.is_ok()
+            && let Some(verify_cmd) = &self.verify_cmd
+        {
+            checked = post_cmd::verify(verify_cmd, &copy);
+        }
+        if let Err(e) = checked {
+            let _ = fs::remove_file(&copy);
+            return Err(e.context(format!("Deleted the copy {}", copy.display())));
+        }
+        self.say(format!("Wrote {}", copy.display().to_string().blue()));
+        Ok(())
+    }
+
+    /// Removes comments from the text of `path`, saying which language it is in if `announce`.
+    fn strip_file(&self, path: &Path, content: &str, announce: bool) -> Result<RemovalResult> {
+        let file_path = &*path.to_string_lossy();
+        let say = |language: &str| {
+            if announce {
+                let verb = if self.forced_language.is_some() { "Using" } else { "Detected" };
+                self.say(format!("{} language: {}", verb, language.green()));
+            }
+        };
+        if let Some(language) = self.forced_language {
+            say(&language.name);
+            self.strip(content, file_path, language, Some(1))
+        } else if notebook::is_notebook(path) {
+            say("Jupyter notebook");
+            notebook::strip_cells(content, self.rules, |cell, language| self.strip(cell, file_path, language, None))
+        } else if self.args.markdown && markdown::is_markdown(path) {
+            say("Markdown");
+            markdown::strip_blocks(content, self.rules, |code, language, first_line| {
+                self.strip(code, file_path, language, Some(first_line))
+            })
+        } else {
+            let language = detect_file_type(file_path, self.rules)?;
+            say(&language.name);
+            self.strip(content, file_path, language, Some(1))
+        }
+    }
+
+    /// The file to write for `path`: with `--dereference` the file a symlink points to, otherwise
+    /// the path itself, which replaces a symlink with a regular file.
+    fn write_target(&self, path: &Path) -> Result<PathBuf> {
+        if !fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+            return Ok(path.to_path_buf());
+        }
+        let target =
+            paths::plain(&fs::canonicalize(path).with_context(|| format!("Failed to resolve symlink: {}", path.display()))?);
+        if self.args.dereference {
+            return Ok(target);
+        }
+        info!(
+            "{} {} is a symlink, it is replaced by a regular file and {} stays unchanged (use --dereference to write {} instead)",
+            "Warning:".yellow(),
+            path.display(),
+            target.display(),
+            target.display()
+        );
+        Ok(path.to_path_buf())
+    }
+
+    /// The language of text that is not read from disk, e.g. an editor buffer; `language_id` is
+    /// the editor's name for it, used when `--lang` and the path do not tell.
+    pub fn language_of(&self, file_path: &str, language_id: Option<&str>) -> Result<&'a LanguageRules> {
+        if let Some(language) = self.forced_language {
+            return Ok(language);
+        }
+        match (detect_file_type(file_path, self.rules), language_id) {
+            (Err(_), Some(language_id)) => find_language(language_id, self.rules),
+            (detected, _) => detected,
+        }
+    }
+
+    /// Removes every comment these options allow from `content` without touching the disk.
+    pub fn strip_text(&self, content: &str, file_path: &str, language: &'a LanguageRules) -> Result<RemovalResult> {
+        self.strip_with(content, file_path, language, None, &mut |_| true)
+    }
+
+    /// The file at `path` with every comment these options allow removed, encoded like the
+    /// file, without writing anything.
+    pub fn strip_path(&self, path: &Path) -> Result<Vec<u8>> {
+        if self.args.max_size > 0
+            && let Ok(metadata) = fs::metadata(path)
+            && metadata.len() > self.args.max_size
+        {
+            return Err(Error::FileTooLarge(metadata.len(), self.args.max_size).into());
+        }
+        let source = encoding::read_source(path, self.forced_encoding)?;
+        let result = self.strip_file(path, &source.text, false)?;
+        encoding::encode(&result.content, &source)
+    }
+
+    /// Byte ranges of the comments these options allow removing from `content`, in order.
+    pub fn removable(&self, content: &str, file_path: &str, language: &'a LanguageRules) -> Result<Vec<Range<usize>>> {
+        let mut ranges = Vec::new();
+        self.strip_with(content, file_path, language, None, &mut |range| {
+            ranges.push(range.clone());
+            false
+        })?;
+        Ok(ranges)
+    }
+
+    /// `content` with only the comment at `range` removed, replaced the way these options say.
+    pub fn remove_one(&self, content: &str, file_path: &str, language: &'a LanguageRules, range: &Range<usize>) -> Result<String> {
+        Ok(self.strip_with(content, file_path, language, None, &mut |candidate| candidate == range)?.content)
+    }
+
+    /// Removes comments from source text in `language`, returning the new text and what
+    /// happened to each comment. `first_line` is the line of the file the text starts on, if
+    /// it is part of the file as is.
+    fn strip(
+        &self,
+        content: &str,
+        file_path: &str,
+        language: &'a LanguageRules,
+        first_line: Option<usize>,
+    ) -> Result<RemovalResult> {
+        let mut decide = |range: &Range<usize>| match &self.decisions {
+            Decisions::All => true,
+            Decisions::Prompt(queue) => match &mut *queue.borrow_mut() {
+                PromptQueue::Collecting(candidates) => {
+                    let mut candidate = Candidate::new(file_path, language, content, range, self.args.context);
+                    // Markdown blocks start further down the file
+                    if let Some(first_line) = first_line {
+                        candidate.line += first_line - 1;
+                        candidate.context_line += first_line - 1;
+                    }
+                    candidates.push(candidate);
+                    false
+                }
+                PromptQueue::Answering(answers) => answers.pop_front().unwrap_or(false),
+            },
+            Decisions::Collect(candidates) => {
+                candidates.borrow_mut().push(Candidate::new(file_path, language, content, range, self.args.context));
+                false
+            }
+            Decisions::Chosen(chosen) => chosen
+                .borrow_mut()
+                .get_mut(file_path)
+                .and_then(VecDeque::pop_front)
+                .unwrap_or(false),
+        };
+        self.strip_with(content, file_path, language, first_line, &mut decide)
+    }
+
+    /// Asks about the comments of one file in turn, going back on request, except those
+    /// `--replay` has an answer for or that are ignored; the answers are kept for `--record`.
+    fn ask_all(&self, file_path: &str, candidates: Vec<Candidate>) -> VecDeque<bool> {
+        let mut answers: Vec<Option<bool>> = candidates
+            .iter()
+            .map(|candidate| {
+                let comment = candidate.text();
+                let replayed = self.replay.as_ref().and_then(|replay| replay.borrow_mut().take(file_path, comment));
+                match replayed {
+                    Some(remove) => {
+                        if self.args.verbose {
+                            info!("Replayed answer for a comment in {}: {}", file_path, if remove { "remove" } else { "keep" });
+                        }
+                        Some(remove)
+                    }
+                    None if self.ignored.as_ref().is_some_and(|ignored| ignored.borrow().contains(Path::new(file_path), comment)) => {
+                        if self.args.verbose {
+                            info!("Keeping an ignored comment in {} without asking", file_path);
+                        }
+                        Some(false)
+                    }
+                    None => None,
+                }
+            })
+            .collect();
+        let asked: Vec<usize> = (0..candidates.len()).filter(|&i| answers[i].is_none()).collect();
+        let mut ignore = vec![false; candidates.len()];
+
+        let mut index = 0;
+        while index < asked.len() {
+            let i = asked[index];
+            let position = match self.position.get() {
+                Some((file, files)) => format!("file {}/{}, comment {}/{}", file, files, index + 1, asked.len()),
+                None => format!("comment {}/{}", index + 1, asked.len()),
+            };
+            let answer = ask_about_comment(&candidates[i], self.rules, &position);
+            ignore[i] = answer == PromptAnswer::Ignore;
+            match answer {
+                PromptAnswer::Remove => answers[i] = Some(true),
+                PromptAnswer::Keep | PromptAnswer::Ignore => answers[i] = Some(false),
+                PromptAnswer::Back => {
+                    index = index.saturating_sub(1);
+                    continue;
+                }
+                PromptAnswer::SkipFile => {
+                    self.say(format!("Skipping the rest of {}, it is left unchanged", file_path.yellow()));
+                    return VecDeque::new();
+                }
+            }
+            index += 1;
+        }
+
+        for (i, candidate) in candidates.iter().enumerate() {
+            let remove = answers[i].unwrap_or(false);
+            if ignore[i]
+                && let Some(ignored) = &self.ignored
+                && let Err(e) = ignored.borrow_mut().add(Path::new(file_path), candidate.text())
+            {
+                eprintln!("{} {:#}", "Error:".red(), e);
+            }
+            if let Some(record) = &self.record {
+                record.borrow_mut().push(file_path, candidate.text(), remove);
+            }
+        }
+        answers.into_iter().map(|answer| answer.unwrap_or(false)).collect()
+    }
+
+    /// Blames a file once, however many blocks of it are processed.
+    fn line_ages(&self, file_path: &str) -> Rc<LineAges> {
+        let mut cached = self.ages.borrow_mut();
+        if let Some((path, ages)) = &*cached
+            && path == file_path
+        {
+            return ages.clone();
+        }
+        let ages = Rc::new(LineAges::blame(Path::new(file_path)).unwrap_or_else(|e| {
+            if self.args.verbose {
+                info!("No git history for {}, keeping its comments: {:#}", file_path, e);
+            }
+            LineAges::unknown()
+        }));
+        *cached = Some((file_path.to_string(), ages.clone()));
+        ages
+    }
+
+    /// The compiled rules of `language`, compiled on first use.
+    fn patterns(&self, language: &'a LanguageRules) -> Result<Rc<CommentPatterns>> {
+        let key = language as *const LanguageRules;
+        if let Some(patterns) = self.patterns.borrow().get(&key) {
+            return Ok(patterns.clone());
+        }
+        let patterns = Rc::new(get_comment_patterns(language, self.args.verbose)?);
+        self.patterns.borrow_mut().insert(key, patterns.clone());
+        Ok(patterns)
+    }
+
+    fn strip_with(
+        &self,
+        content: &str,
+        file_path: &str,
+        language: &'a LanguageRules,
+        first_line: Option<usize>,
+        decide: &mut dyn FnMut(&Range<usize>) -> bool,
+    ) -> Result<RemovalResult> {
+        let args = &self.args;
+        let verbose = args.verbose;
+        let patterns = self.patterns(language)?;
+        let license_header_end = self
+            .license_regex
+            .as_ref()
+            .and_then(|license_regex| find_license_header(content, language, license_regex));
+        if verbose && license_header_end.is_some() {
+            info!("Detected license header, it will be preserved");
+        }
+        if verbose && args.strip.engine == Engine::TreeSitter && !syntax_tree::has_grammar(file_path, language) {
+            info!("No tree-sitter grammar for {}, using the regex engine", language.name);
+        }
+        let mut comments = detect_comments(content, file_path, language, &patterns, args.strip.engine)?;
+        if args.strip.strip_if0 && c_family::is_c_family(language) {
+            comments = c_family::add_if0_blocks(content, comments);
+        }
+        if rust::is_rust(language) {
+            comments = rust::skip_protected(content, file_path, language, comments, self.settings.strip_macros);
+        }
+        let comments = resolve_overlaps(content, comments, args.strip.strict)?;
+        // Text that is not the file as on disk, like a notebook cell, has no known age
+        let ages = self.cutoff.map(|_| match first_line {
+            Some(_) => self.line_ages(file_path),
+            None => Rc::new(LineAges::unknown()),
+        });
+        let policy = RemovalPolicy {
+            file_path,
+            language,
+            license_header_end,
+            keep_license: self.license_regex.is_some(),
+            only_dead_code: args.strip.only_dead_code,
+            preserve_lines: args.strip.preserve_lines || args.strip.action == CommentAction::Blank,
+            collapse: args.strip.action == CommentAction::Collapse,
+            annotations: args.strip.action == CommentAction::Annotations,
+            keep_docstrings: self.settings.keep_doc_comments
+                || (python::is_python(language) && self.settings.keep_docstrings),
+            keep_inner_docs: rust::is_rust(language) && !self.settings.strip_inner_docs,
+            keep_directives: !self.settings.strip_directives,
+            keep_attached: args.strip.keep_attached,
+            filters: &self.filters,
+            age_limit: ages
+                .as_deref()
+                .zip(self.cutoff)
+                .map(|(ages, cutoff)| (ages, first_line.unwrap_or(1), cutoff)),
+            handlers: &self.handlers,
+        };
+        Ok(remove_comments(content, &comments, &policy, decide, verbose))
+    }
+}
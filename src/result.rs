@@ -0,0 +1,94 @@
+use serde::Serialize;
+use std::ops::Range;
+
+use crate::CommentKind;
+
+/// A 1-based line and column; columns count characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// What happened to a comment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "action", content = "reason", rename_all = "lowercase")]
+pub enum Outcome {
+    Removed,
+    /// Protected by a rule, option or handler, named by the reason
+    Kept(String),
+    /// Removable, but not chosen at the prompt or in the review
+    Declined,
+}
+
+/// A comment found in a text and what was done with it.
+#[derive(Debug, Clone, Serialize)]
+pub struct CommentSpan {
+    /// Byte range in the text
+    pub range: Range<usize>,
+    pub start: Position,
+    /// Position just past the comment
+    pub end: Position,
+    pub kind: CommentKind,
+    /// The syntax rule that matched, as its markers from the rules (`//`, `/* */`), or the
+    /// construct for comments found otherwise (`docstring`, `#if 0`)
+    pub rule: String,
+    pub text: String,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+}
+
+impl CommentSpan {
+    /// Moves the span by the position of the text it was found in within a larger one.
+    pub fn shift(&mut self, bytes: usize, lines: usize) {
+        self.range = self.range.start + bytes..self.range.end + bytes;
+        self.start.line += lines;
+        self.end.line += lines;
+    }
+}
+
+/// The text left after removing comments and what happened to each of them, in order.
+#[derive(Debug, Clone)]
+pub struct RemovalResult {
+    pub content: String,
+    pub comments: Vec<CommentSpan>,
+}
+
+impl RemovalResult {
+    pub fn found(&self) -> usize {
+        self.comments.len()
+    }
+
+    pub fn removed(&self) -> usize {
+        self.comments.iter().filter(|comment| comment.outcome == Outcome::Removed).count()
+    }
+}
+
+/// Line and column of increasing offsets in one text, counting on from the previous one so a
+/// whole file costs a single pass.
+pub(crate) struct Positions<'a> {
+    content: &'a str,
+    offset: usize,
+    position: Position,
+}
+
+impl<'a> Positions<'a> {
+    pub(crate) fn new(content: &'a str) -> Self {
+        Positions { content, offset: 0, position: Position { line: 1, column: 1 } }
+    }
+
+    pub(crate) fn at(&mut self, offset: usize) -> Position {
+        if offset < self.offset {
+            *self = Positions::new(self.content);
+        }
+        for c in self.content[self.offset..offset].chars() {
+            if c == '\n' {
+                self.position = Position { line: self.position.line + 1, column: 1 };
+            } else {
+                self.position.column += 1;
+            }
+        }
+        self.offset = offset;
+        self.position
+    }
+}
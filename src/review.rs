@@ -0,0 +1,325 @@
+use anyhow::Result;
+#[cfg(not(target_arch = "wasm32"))]
+use anyhow::Context;
+#[cfg(not(target_arch = "wasm32"))]
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, Paragraph},
+    DefaultTerminal, Frame,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    ops::Range,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::{self, IsTerminal};
+
+use crate::{find_language, line_number, Error, LanguageRules, SyntaxRules};
+
+/// Rows moved by Page Up and Page Down
+#[cfg(not(target_arch = "wasm32"))]
+const PAGE: u16 = 10;
+
+/// A comment the policy allows removing, with the code around it for the review.
+pub struct Candidate {
+    pub file: String,
+    pub language: String,
+    /// Line the comment starts on
+    pub line: usize,
+    /// The comment's lines and `--context` lines on either side
+    pub context: String,
+    /// Line the context starts on
+    pub context_line: usize,
+    /// Where the comment is in `context`
+    pub comment: Range<usize>,
+    pub remove: bool,
+}
+
+impl Candidate {
+    pub fn new(file: &str, language: &LanguageRules, content: &str, range: &Range<usize>, context_lines: usize) -> Self {
+        let mut start = content[..range.start].rfind('\n').map_or(0, |i| i + 1);
+        for _ in 0..context_lines {
+            if start == 0 {
+                break;
+            }
+            start = content[..start - 1].rfind('\n').map_or(0, |i| i + 1);
+        }
+        let mut end = content[range.end..].find('\n').map_or(content.len(), |i| range.end + i);
+        for _ in 0..context_lines {
+            if end == content.len() {
+                break;
+            }
+            end = content[end + 1..].find('\n').map_or(content.len(), |i| end + 1 + i);
+        }
+        Candidate {
+            file: file.to_string(),
+            language: language.name.clone(),
+            line: line_number(content, range.start),
+            context: content[start..end].to_string(),
+            context_line: line_number(content, start),
+            comment: range.start - start..range.end - start,
+            remove: true,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.context[self.comment.clone()]
+    }
+
+    /// The context line by line: the line number and the code before, in and after the comment.
+    pub fn lines(&self) -> Vec<(usize, &str, &str, &str)> {
+        let mut offset = 0;
+        let mut lines = Vec::new();
+        // A context running to the end of the text has no line after its last line break
+        let context = self.context.strip_suffix('\n').unwrap_or(&self.context);
+        for (number, line) in context.split('\n').enumerate() {
+            let text = line.trim_end_matches('\r');
+            let start = self.comment.start.saturating_sub(offset).min(text.len());
+            let end = self.comment.end.saturating_sub(offset).min(text.len());
+            lines.push((self.context_line + number, &text[..start], &text[start..end], &text[end..]));
+            offset += line.len() + 1;
+        }
+        lines
+    }
+}
+
+/// The words highlighted in code of `language`: its keywords and declaration keywords.
+pub fn keywords<'r>(language: &str, rules: &'r SyntaxRules) -> Vec<&'r str> {
+    find_language(language, rules)
+        .map(|language| {
+            language
+                .keywords
+                .iter()
+                .chain(&language.declarations)
+                .map(String::as_str)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Splits code into words and the text between them, telling which pieces are keywords.
+pub fn split_keywords<'c>(code: &'c str, keywords: &[&str]) -> Vec<(&'c str, bool)> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut pieces = Vec::new();
+    let mut rest = code;
+    while !rest.is_empty() {
+        let word = rest.starts_with(is_word);
+        let len = rest.find(|c: char| is_word(c) != word).unwrap_or(rest.len());
+        let (piece, tail) = rest.split_at(len);
+        pieces.push((piece, word && keywords.contains(&piece)));
+        rest = tail;
+    }
+    pieces
+}
+
+/// The decisions of a finished review, per file in the order the comments were collected.
+pub fn decisions(candidates: &[Candidate]) -> HashMap<String, VecDeque<bool>> {
+    let mut chosen: HashMap<String, VecDeque<bool>> = HashMap::new();
+    for candidate in candidates {
+        chosen.entry(candidate.file.clone()).or_default().push_back(candidate.remove);
+    }
+    chosen
+}
+
+/// Shows the comments full-screen for toggling; returns whether the choices should be applied.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn run(candidates: &mut [Candidate], rules: &SyntaxRules) -> Result<bool> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Err(Error::NotATerminal.into());
+    }
+    let mut terminal = ratatui::try_init().context("Failed to start the terminal UI")?;
+    let mut review = Review::new(candidates, rules);
+    let result = review.run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+/// Builds for the web have no terminal to show the review in.
+#[cfg(target_arch = "wasm32")]
+pub fn run(_candidates: &mut [Candidate], _rules: &SyntaxRules) -> Result<bool> {
+    Err(Error::NotATerminal.into())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+struct Review<'a> {
+    candidates: &'a mut [Candidate],
+    rules: &'a SyntaxRules,
+    /// Indices of the candidates matching the filter
+    visible: Vec<usize>,
+    list: ListState,
+    filter: String,
+    editing_filter: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<'a> Review<'a> {
+    fn new(candidates: &'a mut [Candidate], rules: &'a SyntaxRules) -> Self {
+        let visible = (0..candidates.len()).collect();
+        Review {
+            candidates,
+            rules,
+            visible,
+            list: ListState::default().with_selected(Some(0)),
+            filter: String::new(),
+            editing_filter: false,
+        }
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<bool> {
+        loop {
+            terminal.draw(|frame| self.draw(frame)).context("Failed to draw the review")?;
+            let Event::Key(key) = event::read().context("Failed to read from the terminal")? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if let Some(apply) = self.handle_key(key) {
+                return Ok(apply);
+            }
+        }
+    }
+
+    /// Updates the review for a key press; `Some` ends it, applying the choices or not.
+    fn handle_key(&mut self, key: KeyEvent) -> Option<bool> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Some(false);
+        }
+        if self.editing_filter {
+            match key.code {
+                KeyCode::Char(c) => self.filter.push(c),
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Enter => self.editing_filter = false,
+                KeyCode::Esc => {
+                    self.filter.clear();
+                    self.editing_filter = false;
+                }
+                _ => return None,
+            }
+            self.apply_filter();
+            return None;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Some(false),
+            KeyCode::Char('w') | KeyCode::Enter => return Some(true),
+            KeyCode::Up | KeyCode::Char('k') => self.list.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => self.list.select_next(),
+            KeyCode::PageUp => self.list.scroll_up_by(PAGE),
+            KeyCode::PageDown => self.list.scroll_down_by(PAGE),
+            KeyCode::Home | KeyCode::Char('g') => self.list.select_first(),
+            KeyCode::End | KeyCode::Char('G') => self.list.select_last(),
+            KeyCode::Char(' ') => {
+                if let Some(index) = self.selected() {
+                    self.candidates[index].remove = !self.candidates[index].remove;
+                    self.list.select_next();
+                }
+            }
+            KeyCode::Char('a') => self.set_visible(true),
+            KeyCode::Char('n') => self.set_visible(false),
+            KeyCode::Char('/') => self.editing_filter = true,
+            _ => {}
+        }
+        None
+    }
+
+    fn selected(&self) -> Option<usize> {
+        self.list.selected().and_then(|row| self.visible.get(row)).copied()
+    }
+
+    fn set_visible(&mut self, remove: bool) {
+        for &index in &self.visible {
+            self.candidates[index].remove = remove;
+        }
+    }
+
+    /// Shows only comments containing the filter text, ignoring case, e.g. `todo` or `///`.
+    fn apply_filter(&mut self) {
+        let filter = self.filter.to_lowercase();
+        self.visible = (0..self.candidates.len())
+            .filter(|&index| self.candidates[index].text().to_lowercase().contains(&filter))
+            .collect();
+        self.list.select(if self.visible.is_empty() { None } else { Some(0) });
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] = Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [list_area, context_area] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(main);
+
+        let items: Vec<ListItem> = self
+            .visible
+            .iter()
+            .map(|&index| {
+                let candidate = &self.candidates[index];
+                let action = if candidate.remove { "remove".red() } else { "keep  ".green() };
+                let first_line = candidate.text().lines().next().unwrap_or_default().trim().to_string();
+                ListItem::new(Line::from(vec![
+                    action,
+                    Span::raw(format!(" {}:{} ", candidate.file, candidate.line)),
+                    first_line.yellow(),
+                ]))
+            })
+            .collect();
+        let removing = self.candidates.iter().filter(|candidate| candidate.remove).count();
+        let title = format!(" {} of {} comments to remove ", removing, self.candidates.len());
+        let list = List::new(items)
+            .block(Block::bordered().title(title))
+            .highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(list, list_area, &mut self.list);
+
+        let context = match self.selected() {
+            Some(index) => {
+                let candidate = &self.candidates[index];
+                let title = format!(" {} ({}) ", candidate.file, candidate.language);
+                Paragraph::new(self.highlight(candidate)).block(Block::bordered().title(title))
+            }
+            None => Paragraph::new("No comments match the filter").block(Block::bordered()),
+        };
+        frame.render_widget(context, context_area);
+
+        let status_line = if self.editing_filter {
+            format!(" Filter: {}_  (enter to keep, esc to clear)", self.filter)
+        } else {
+            let filter = if self.filter.is_empty() { String::new() } else { format!("  [filter: {}]", self.filter) };
+            format!(
+                " ↑↓ move  space toggle  a remove shown  n keep shown  / filter  enter apply  q cancel{}",
+                filter
+            )
+        };
+        frame.render_widget(Paragraph::new(status_line).reversed(), status);
+    }
+
+    /// The comment's context with line numbers, the comment itself and the language's keywords
+    /// highlighted.
+    fn highlight(&self, candidate: &Candidate) -> Vec<Line<'static>> {
+        let keywords = keywords(&candidate.language, self.rules);
+        candidate
+            .lines()
+            .into_iter()
+            .map(|(number, before, comment, after)| {
+                let mut spans = vec![format!("{:>5} ", number).dark_gray()];
+                spans.extend(code_spans(before, &keywords));
+                if !comment.is_empty() {
+                    spans.push(comment.to_string().yellow());
+                }
+                spans.extend(code_spans(after, &keywords));
+                Line::from(spans)
+            })
+            .collect()
+    }
+}
+
+/// Code with the keywords highlighted.
+#[cfg(not(target_arch = "wasm32"))]
+fn code_spans(code: &str, keywords: &[&str]) -> Vec<Span<'static>> {
+    split_keywords(code, keywords)
+        .into_iter()
+        .map(|(piece, keyword)| if keyword { piece.to_string().magenta().bold() } else { Span::raw(piece.to_string()) })
+        .collect()
+}
@@ -0,0 +1,119 @@
+use anyhow::{Context, Result};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    env,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::{atomic_write, history, Error};
+
+/// Bumped whenever the file layout changes, so an old log is rejected rather than misread
+const RUN_LOG_VERSION: u32 = 1;
+
+/// The first line of the log: how the run was started and everything it was going to process.
+#[derive(Serialize, Deserialize)]
+struct Header {
+    version: u32,
+    /// The directory the paths are relative to
+    dir: PathBuf,
+    /// The command line after the program name
+    args: Vec<String>,
+    files: Vec<PathBuf>,
+}
+
+/// A run that stopped before processing all its files.
+pub struct Interrupted {
+    pub args: Vec<String>,
+    pub pending: Vec<PathBuf>,
+}
+
+/// Progress of a `remove` run that writes files, in `.comment-remover/run.log`: a header line,
+/// then a line per finished file, appended and flushed as the run goes. A run that gets to the
+/// end deletes it; one that crashes or is interrupted leaves it behind for `--resume`.
+pub struct RunLog {
+    path: PathBuf,
+    file: File,
+}
+
+fn log_path() -> Result<PathBuf> {
+    Ok(history::state_dir()?.join("run.log"))
+}
+
+impl RunLog {
+    /// Starts logging a run of `args` over `files`, replacing the log of an interrupted run.
+    pub fn start(args: &[String], files: &[PathBuf]) -> Result<Self> {
+        let path = log_path()?;
+        if path.is_file() {
+            info!("{} Starting over instead of resuming an interrupted run", "Warning:".yellow());
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+        }
+        let header =
+            Header { version: RUN_LOG_VERSION, dir: env::current_dir()?, args: args.to_vec(), files: files.to_vec() };
+        atomic_write::write_atomic(&path, (serde_json::to_string(&header)? + "\n").as_bytes())
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Self::open(path)
+    }
+
+    /// Goes on logging the interrupted run.
+    pub fn resume() -> Result<Self> {
+        Self::open(log_path()?)
+    }
+
+    fn open(path: PathBuf) -> Result<Self> {
+        let file = File::options()
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        Ok(RunLog { path, file })
+    }
+
+    /// Notes that `file` is done, so a resumed run skips it.
+    pub fn finished(&mut self, file: &Path) -> Result<()> {
+        // JSON strings, so any file name fits on one line
+        writeln!(self.file, "{}", serde_json::to_string(file)?)
+            .and_then(|()| self.file.sync_data())
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+
+    /// The run got to the end, there is nothing to resume.
+    pub fn complete(self) -> Result<()> {
+        fs::remove_file(&self.path).with_context(|| format!("Failed to remove {}", self.path.display()))
+    }
+}
+
+/// The run `--resume` continues: its command line and the files it has not finished.
+pub fn interrupted() -> Result<Interrupted> {
+    let path = log_path()?;
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(Error::InvalidOptions("there is no interrupted run to resume".to_string()).into());
+        }
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let mut lines = content.lines();
+    let header: Header = lines
+        .next()
+        .and_then(|line| serde_json::from_str(line).ok())
+        .with_context(|| format!("Invalid run log: {}", path.display()))?;
+    if header.version != RUN_LOG_VERSION {
+        anyhow::bail!("Unsupported run log version {} in {}", header.version, path.display());
+    }
+    if header.dir != env::current_dir()? {
+        return Err(Error::InvalidOptions(format!(
+            "the interrupted run was started in {}, resume it from there",
+            header.dir.display()
+        ))
+        .into());
+    }
+    // A line cut short by the crash is simply not counted as finished
+    let finished: HashSet<PathBuf> = lines.filter_map(|line| serde_json::from_str(line).ok()).collect();
+    let pending = header.files.into_iter().filter(|file| !finished.contains(file)).collect();
+    Ok(Interrupted { args: header.args, pending })
+}
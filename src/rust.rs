@@ -0,0 +1,143 @@
+use regex::Regex;
+use std::ops::Range;
+
+use crate::{literals, Comment, LanguageRules};
+
+pub fn is_rust(language: &LanguageRules) -> bool {
+    language.name == "Rust"
+}
+
+/// `//!` and `/*! */` document the enclosing crate or module rather than the next item.
+pub fn is_inner_doc(comment: &str) -> bool {
+    let comment = comment.trim_start();
+    comment.starts_with("//!") || comment.starts_with("/*!")
+}
+
+/// Drops comments inside `doc` and `cfg_attr(doc, ...)` attributes, and inside `macro_rules!`
+/// bodies unless `strip_macros` is set: a macro body is a token stream whose doc comments
+/// become `#[doc]` attributes matched by `$(#[$meta:meta])*` fragments.
+pub fn skip_protected(
+    content: &str,
+    file_path: &str,
+    language: &LanguageRules,
+    comments: Vec<Comment>,
+    strip_macros: bool,
+) -> Vec<Comment> {
+    let mut skipped: Vec<Range<usize>> = literals::scan(content, language, file_path).strings;
+    skipped.extend(comments.iter().map(|comment| comment.range.clone()));
+    skipped.sort_by_key(|range| range.start);
+
+    let mut patterns = vec![r"#!?\[\s*(?:cfg_attr\s*\(\s*doc\b|doc\b)"];
+    if !strip_macros {
+        patterns.push(r"\bmacro_rules!\s*[A-Za-z_][A-Za-z0-9_]*\s*[{(\[]");
+    }
+    let mut regions = Vec::new();
+    for pattern in patterns {
+        let regex = Regex::new(pattern).unwrap();
+        for found in regex.find_iter(content) {
+            if inside(&skipped, found.start()) {
+                continue;
+            }
+            // The opening bracket is the `[` of an attribute or the last byte of a macro match
+            let open = if content[found.start()..].starts_with('#') {
+                found.start() + content[found.start()..].find('[').unwrap_or(0)
+            } else {
+                found.end() - 1
+            };
+            regions.push(open..matching_bracket(content, open, &skipped));
+        }
+    }
+    if regions.is_empty() {
+        return comments;
+    }
+
+    comments
+        .into_iter()
+        .filter(|comment| {
+            !regions
+                .iter()
+                .any(|region| region.start < comment.range.start && comment.range.end <= region.end)
+        })
+        .collect()
+}
+
+fn inside(ranges: &[Range<usize>], position: usize) -> bool {
+    let index = ranges.partition_point(|range| range.start <= position);
+    ranges[..index].iter().any(|range| position < range.end)
+}
+
+/// End of the token tree opened at `open`, skipping brackets inside strings, chars and comments.
+fn matching_bracket(content: &str, open: usize, skipped: &[Range<usize>]) -> usize {
+    let bytes = content.as_bytes();
+    let mut next_skip = skipped.partition_point(|range| range.start <= open);
+    let mut depth = 0usize;
+    let mut position = open;
+    while position < bytes.len() {
+        if next_skip < skipped.len() && skipped[next_skip].start == position {
+            position = skipped[next_skip].end.max(position + 1);
+            next_skip += 1;
+            while next_skip < skipped.len() && skipped[next_skip].start < position {
+                next_skip += 1;
+            }
+            continue;
+        }
+        match bytes[position] {
+            b'{' | b'(' | b'[' => depth += 1,
+            b'}' | b')' | b']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return position + 1;
+                }
+            }
+            _ => {}
+        }
+        position += 1;
+    }
+    content.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Rules, Stripper};
+
+    fn strip(options: &[&str], source: &str) -> String {
+        let rules = Rules::bundled();
+        Stripper::new(&rules, options).unwrap().strip(source, "rust").unwrap().content
+    }
+
+    #[test]
+    fn macro_rules_bodies_keep_their_comments() {
+        let source = "macro_rules! m {\n    ($(#[$meta:meta])* $name:ident) => {\n        // c\n        /// Doc\n        $(#[$meta])* struct $name;\n    };\n}\n// after\nm!(A);\n";
+        assert_eq!(
+            strip(&[], source),
+            "macro_rules! m {\n    ($(#[$meta:meta])* $name:ident) => {\n        // c\n        /// Doc\n        $(#[$meta])* struct $name;\n    };\n}\n\nm!(A);\n"
+        );
+        assert_eq!(
+            strip(&["--strip-macros"], source),
+            "macro_rules! m {\n    ($(#[$meta:meta])* $name:ident) => {\n\n\n        $(#[$meta])* struct $name;\n    };\n}\n\nm!(A);\n"
+        );
+    }
+
+    #[test]
+    fn macro_bodies_end_at_their_own_bracket() {
+        let source = "macro_rules! m [\n    () => { \"}]\" };\n    // c\n];\n/* after */\nfn f() {}\n";
+        assert_eq!(strip(&[], source), "macro_rules! m [\n    () => { \"}]\" };\n    // c\n];\n\nfn f() {}\n");
+        // A macro_rules! in a string or comment opens nothing
+        let quoted = "let s = \"macro_rules! m {\";\n// c\n";
+        assert_eq!(strip(&[], quoted), "let s = \"macro_rules! m {\";\n\n");
+    }
+
+    #[test]
+    fn doc_attributes_keep_their_comments() {
+        for source in [
+            "#[cfg_attr(doc, doc = \"x\" /* c */)]\nfn f() {}\n",
+            "#[cfg_attr(\n    doc,\n    // c\n    doc = \"x\"\n)]\nfn f() {}\n",
+            "#![doc = /* c */ \"x\"]\n",
+            "#[doc(\n    // c\n    hidden\n)]\nfn f() {}\n",
+        ] {
+            assert_eq!(strip(&[], source), source);
+        }
+        let other = "#[cfg_attr(test, derive(Debug /* c */))]\nstruct A;\n";
+        assert_eq!(strip(&[], other), "#[cfg_attr(test, derive(Debug ))]\nstruct A;\n");
+    }
+}
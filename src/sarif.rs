@@ -0,0 +1,69 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+
+use crate::{
+    help::BIN_NAME,
+    notebook,
+    patch::patch_path,
+    result::{CommentSpan, Outcome},
+};
+
+const RULE_ID: &str = "removable-comment";
+
+/// A SARIF log with one result per removed comment, or removable one with `--check`.
+/// Findings carry no severity, since a comment is a matter of hygiene rather than a defect.
+pub fn render(files: &[(PathBuf, Vec<CommentSpan>)], check: bool) -> Result<String> {
+    let mut results = Vec::new();
+    for (path, comments) in files {
+        let uri = patch_path(path);
+        for comment in comments.iter().filter(|comment| comment.outcome == Outcome::Removed) {
+            let mut location = json!({ "artifactLocation": { "uri": uri, "uriBaseId": "%SRCROOT%" } });
+            // Positions in a notebook are within a cell, which a region cannot express
+            if !notebook::is_notebook(path) {
+                location["region"] = json!({
+                    "startLine": comment.start.line,
+                    "startColumn": comment.start.column,
+                    "endLine": comment.end.line,
+                    "endColumn": comment.end.column,
+                });
+            }
+            let kind = format!("{:?}", comment.kind).to_lowercase();
+            let message = if check {
+                format!("Removable {} comment `{}`", kind, comment.rule)
+            } else {
+                format!("Removed {} comment `{}`", kind, comment.rule)
+            };
+            results.push(json!({
+                "ruleId": RULE_ID,
+                "level": "none",
+                "message": { "text": message },
+                "locations": [{ "physicalLocation": location }],
+            }));
+        }
+    }
+
+    let log: Value = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": BIN_NAME,
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": [{
+                        "id": RULE_ID,
+                        "shortDescription": { "text": "Comment that can be removed" },
+                        "fullDescription": {
+                            "text": "A comment none of the configured rules, options or directives protect."
+                        },
+                        "defaultConfiguration": { "level": "none" },
+                    }],
+                },
+            },
+            "columnKind": "unicodeCodePoints",
+            "results": results,
+        }],
+    });
+    Ok(serde_json::to_string_pretty(&log)? + "\n")
+}
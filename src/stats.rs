@@ -0,0 +1,190 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use encoding_rs::Encoding;
+use serde::Serialize;
+use std::{collections::BTreeMap, fs, path::Path};
+
+use crate::{
+    collect_files, detect_comments, detect_file_type, encoding, get_comment_patterns,
+    output::{self, FileFailure},
+    Comment, CommentKind, Engine, Error, SyntaxRules,
+};
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum StatsFormat {
+    Table,
+    Json,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct CommentStats {
+    pub files: usize,
+    pub code_lines: usize,
+    pub comment_lines: usize,
+    pub blank_lines: usize,
+    pub line_comments: usize,
+    pub block_comments: usize,
+    pub doc_comments: usize,
+}
+
+impl CommentStats {
+    pub fn add(&mut self, other: &CommentStats) {
+        self.files += other.files;
+        self.code_lines += other.code_lines;
+        self.comment_lines += other.comment_lines;
+        self.blank_lines += other.blank_lines;
+        self.line_comments += other.line_comments;
+        self.block_comments += other.block_comments;
+        self.doc_comments += other.doc_comments;
+    }
+
+    /// Share of non-blank lines that are comments
+    pub fn density(&self) -> f64 {
+        let total = self.code_lines + self.comment_lines;
+        if total == 0 {
+            0.0
+        } else {
+            self.comment_lines as f64 / total as f64
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileStats {
+    pub path: String,
+    pub language: String,
+    #[serde(flatten)]
+    pub stats: CommentStats,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct StatsReport {
+    pub files: Vec<FileStats>,
+    pub languages: BTreeMap<String, CommentStats>,
+    pub total: CommentStats,
+    /// Files left out because they are binary, too large or not in the expected encoding
+    pub skipped: usize,
+}
+
+pub fn analyze(content: &str, comments: &[Comment]) -> CommentStats {
+    let mut stats = CommentStats { files: 1, ..Default::default() };
+
+    // Blank out comment text so each line can be classified by what is left on it
+    let mut stripped = content.as_bytes().to_vec();
+    for comment in comments {
+        match comment.kind {
+            CommentKind::Line => stats.line_comments += 1,
+            CommentKind::Block => stats.block_comments += 1,
+            CommentKind::Doc => stats.doc_comments += 1,
+        }
+        for byte in &mut stripped[comment.range.clone()] {
+            if *byte != b'\n' {
+                *byte = b' ';
+            }
+        }
+    }
+    let stripped = String::from_utf8_lossy(&stripped);
+
+    for (line, remaining) in content.lines().zip(stripped.lines()) {
+        if !remaining.trim().is_empty() {
+            stats.code_lines += 1;
+        } else if !line.trim().is_empty() {
+            stats.comment_lines += 1;
+        } else {
+            stats.blank_lines += 1;
+        }
+    }
+    stats
+}
+
+/// Statistics of the supported files below `path`. Files that cannot be read are reported
+/// and left out, like files larger than `max_size` bytes unless it is 0.
+pub fn collect(
+    path: &Path,
+    rules: &SyntaxRules,
+    forced_encoding: Option<&'static Encoding>,
+    engine: Engine,
+    max_size: u64,
+) -> Result<StatsReport> {
+    let mut report = StatsReport::default();
+    for file in collect_files(path, false)? {
+        let file_path = file.to_string_lossy();
+        let language = match detect_file_type(&file_path, rules) {
+            Ok(language) => language,
+            // Unsupported files are only an error when asked for explicitly
+            Err(e) if file.as_path() == path => return Err(e),
+            Err(_) => continue,
+        };
+        let content = match read(&file, forced_encoding, max_size) {
+            Ok(content) => content,
+            Err(e) if file.as_path() == path => return Err(e),
+            Err(e) => {
+                output::file_error(FileFailure::Skipped, file.display(), &e);
+                report.skipped += 1;
+                continue;
+            }
+        };
+        let patterns = get_comment_patterns(language, false)?;
+        let comments = detect_comments(&content, &file_path, language, &patterns, engine)?;
+        let stats = analyze(&content, &comments);
+
+        report.languages.entry(language.name.clone()).or_default().add(&stats);
+        report.total.add(&stats);
+        report.files.push(FileStats {
+            path: file_path.into_owned(),
+            language: language.name.clone(),
+            stats,
+        });
+    }
+    Ok(report)
+}
+
+fn read(file: &Path, forced_encoding: Option<&'static Encoding>, max_size: u64) -> Result<String> {
+    // Check before reading so a huge generated file is never loaded into memory
+    if max_size > 0
+        && let Ok(metadata) = fs::metadata(file)
+        && metadata.len() > max_size
+    {
+        return Err(Error::FileTooLarge(metadata.len(), max_size).into());
+    }
+    Ok(encoding::read_source(file, forced_encoding)?.text)
+}
+
+fn table_row(name: &str, stats: &CommentStats) -> String {
+    format!(
+        "{:<24} {:>6} {:>8} {:>8} {:>7} {:>6} {:>6} {:>6} {:>7.1}%\n",
+        name,
+        stats.files,
+        stats.code_lines,
+        stats.comment_lines,
+        stats.blank_lines,
+        stats.line_comments,
+        stats.block_comments,
+        stats.doc_comments,
+        stats.density() * 100.0
+    )
+}
+
+pub fn render(report: &StatsReport, format: StatsFormat, show_files: bool) -> Result<String> {
+    match format {
+        StatsFormat::Json => Ok(serde_json::to_string_pretty(report)? + "\n"),
+        StatsFormat::Table => {
+            let mut output = format!(
+                "{:<24} {:>6} {:>8} {:>8} {:>7} {:>6} {:>6} {:>6} {:>8}\n",
+                "Language", "Files", "Code", "Comment", "Blank", "Line", "Block", "Doc", "Density"
+            );
+            for (language, stats) in &report.languages {
+                output.push_str(&table_row(language, stats));
+            }
+            output.push_str(&table_row("Total", &report.total));
+
+            if show_files {
+                output.push('\n');
+                for file in &report.files {
+                    output.push_str(&table_row(&file.path, &file.stats));
+                }
+            }
+            Ok(output)
+        }
+    }
+}
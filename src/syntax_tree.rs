@@ -0,0 +1,104 @@
+//! Grammar-accurate comment detection backed by tree-sitter, enabled with the `tree-sitter` feature.
+
+pub const AVAILABLE: bool = cfg!(feature = "tree-sitter");
+
+/// Returns `None` when there is no grammar for the file, so callers can fall back to regex matching.
+#[cfg(not(feature = "tree-sitter"))]
+pub fn find_comments(
+    _content: &str,
+    _file_path: &str,
+    _language: &crate::LanguageRules,
+) -> anyhow::Result<Option<Vec<crate::Comment>>> {
+    Ok(None)
+}
+
+#[cfg(not(feature = "tree-sitter"))]
+pub fn has_grammar(_file_path: &str, _language: &crate::LanguageRules) -> bool {
+    false
+}
+
+#[cfg(feature = "tree-sitter")]
+pub use grammar::{find_comments, has_grammar};
+
+#[cfg(feature = "tree-sitter")]
+mod grammar {
+    use anyhow::Result;
+    use std::path::Path;
+    use tree_sitter::{Language, Parser, TreeCursor};
+
+    use crate::{Comment, CommentKind, Error, LanguageRules, RuleClass};
+
+    fn grammar_for(file_path: &str, language: &LanguageRules) -> Option<Language> {
+        let extension = Path::new(file_path).extension().and_then(|ext| ext.to_str());
+        let grammar = match language.name.as_str() {
+            "Rust" => tree_sitter_rust::LANGUAGE,
+            "Python" => tree_sitter_python::LANGUAGE,
+            "JavaScript" => tree_sitter_javascript::LANGUAGE,
+            "TypeScript" if extension == Some("tsx") => tree_sitter_typescript::LANGUAGE_TSX,
+            "TypeScript" => tree_sitter_typescript::LANGUAGE_TYPESCRIPT,
+            "Java" => tree_sitter_java::LANGUAGE,
+            "C" => tree_sitter_c::LANGUAGE,
+            "C++" => tree_sitter_cpp::LANGUAGE,
+            "Go" => tree_sitter_go::LANGUAGE,
+            _ => return None,
+        };
+        Some(grammar.into())
+    }
+
+    pub fn has_grammar(file_path: &str, language: &LanguageRules) -> bool {
+        grammar_for(file_path, language).is_some()
+    }
+
+    pub fn find_comments(content: &str, file_path: &str, language: &LanguageRules) -> Result<Option<Vec<Comment>>> {
+        let Some(grammar) = grammar_for(file_path, language) else {
+            return Ok(None);
+        };
+
+        let mut parser = Parser::new();
+        parser
+            .set_language(&grammar)
+            .map_err(|e| Error::SyntaxTree(e.to_string()))?;
+        let tree = parser
+            .parse(content, None)
+            .ok_or_else(|| Error::SyntaxTree(format!("Failed to parse {}", file_path)))?;
+
+        let mut comments = Vec::new();
+        collect_comments(&mut tree.walk(), content, language, &mut comments);
+        Ok(Some(comments))
+    }
+
+    fn collect_comments(cursor: &mut TreeCursor, content: &str, language: &LanguageRules, comments: &mut Vec<Comment>) {
+        loop {
+            let node = cursor.node();
+            if node.kind().ends_with("comment") {
+                comments.push(to_comment(node.byte_range(), content, language));
+            } else if cursor.goto_first_child() {
+                collect_comments(cursor, content, language, comments);
+                cursor.goto_parent();
+            }
+            if !cursor.goto_next_sibling() {
+                break;
+            }
+        }
+    }
+
+    fn to_comment(range: std::ops::Range<usize>, content: &str, language: &LanguageRules) -> Comment {
+        let text = &content[range.clone()];
+        let kind = if let Some(rule) = language.single_line.iter().find(|rule| rule.strip_marker(text).is_some()) {
+            if rule.class() == RuleClass::Doc { CommentKind::Doc } else { CommentKind::Line }
+        } else if let Some(rule) = language.multi_line.iter().find(|rule| text.starts_with(&rule.start)) {
+            if rule.class() == RuleClass::Doc { CommentKind::Doc } else { CommentKind::Block }
+        } else {
+            CommentKind::Line
+        };
+
+        // Some grammars include the line ending in line comments, the line itself should stay
+        let end = range.start + text.trim_end_matches(['\r', '\n']).len();
+
+        // Like the regex engine, a comment on its own line takes its indentation with it
+        let line_start = content[..range.start].rfind('\n').map_or(0, |i| i + 1);
+        let start = if content[line_start..range.start].trim().is_empty() { line_start } else { range.start };
+
+        Comment { kind, range: start..end }
+    }
+}
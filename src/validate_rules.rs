@@ -0,0 +1,364 @@
+use anyhow::{Context, Result};
+use colored::*;
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::{collections::HashMap, fmt, fs, path::Path};
+
+use crate::{multi_line_pattern, single_line_pattern, with_flags, RULES_SCHEMA};
+
+/// Rule fields that only schema 2 defines
+const SCHEMA_2_FIELDS: &[&str] = &["flags", "must_start_line", "inside_strings", "class"];
+/// The inline flags the regex crate accepts
+const REGEX_FLAGS: &str = "imsRUux";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self.severity {
+            Severity::Error => "error".red().bold(),
+            Severity::Warning => "warning".yellow().bold(),
+        };
+        write!(f, "{}: {}: {}", self.line, label, self.message)
+    }
+}
+
+/// Checks a rules file and returns every problem found, ordered by line.
+pub fn check_file(path: &Path) -> Result<Vec<Diagnostic>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read syntax rules from {}", path.display()))?;
+    Ok(check(&content))
+}
+
+pub fn check(content: &str) -> Vec<Diagnostic> {
+    let root: Value = match serde_json::from_str(content) {
+        Ok(root) => root,
+        Err(e) => {
+            // Nothing else can be checked until the file is valid JSON
+            return vec![Diagnostic {
+                severity: Severity::Error,
+                line: e.line(),
+                message: format!("invalid JSON: {}", e),
+            }];
+        }
+    };
+
+    let mut checker = Checker { content, diagnostics: Vec::new(), schema_2_field: None };
+    match root.as_object() {
+        Some(languages) => {
+            let schema = checker.schema(languages);
+            checker.check_languages(languages);
+            if schema < 2
+                && let Some((line, field)) = checker.schema_2_field.take()
+            {
+                checker.warning(line, format!("'{}' needs \"schema\": 2 at the top level", field));
+            }
+        }
+        None => checker.error(1, "the top level must be an object of languages".to_string()),
+    }
+    checker.diagnostics.sort_by_key(|diagnostic| diagnostic.line);
+    checker.diagnostics
+}
+
+struct Checker<'a> {
+    content: &'a str,
+    diagnostics: Vec<Diagnostic>,
+    /// The first use of a field schema 1 does not have
+    schema_2_field: Option<(usize, &'static str)>,
+}
+
+impl Checker<'_> {
+    fn error(&mut self, line: usize, message: String) {
+        self.diagnostics.push(Diagnostic { severity: Severity::Error, line, message });
+    }
+
+    fn warning(&mut self, line: usize, message: String) {
+        self.diagnostics.push(Diagnostic { severity: Severity::Warning, line, message });
+    }
+
+    /// serde_json keeps no positions, so locate `needle` textually after `from`.
+    /// Returns the byte offset of the match, or `from` when it cannot be found.
+    fn find(&self, needle: &str, from: usize) -> usize {
+        self.content[from..].find(needle).map_or(from, |offset| from + offset)
+    }
+
+    /// Look each language up from the top of the file rather than relying on the map order.
+    fn find_key(&self, key: &str) -> usize {
+        let quoted = regex::escape(&serde_json::to_string(key).unwrap());
+        Regex::new(&format!(r"(?m)^\s*{}\s*:", quoted))
+            .ok()
+            .and_then(|regex| regex.find(self.content))
+            .map_or_else(|| self.find(&serde_json::to_string(key).unwrap(), 0), |found| found.start())
+    }
+
+    fn line_at(&self, offset: usize) -> usize {
+        self.content[..offset].matches('\n').count() + 1
+    }
+
+    /// Reads the top-level `schema`, 1 when missing.
+    fn schema(&mut self, root: &Map<String, Value>) -> u64 {
+        let Some(value) = root.get("schema") else {
+            return 1;
+        };
+        let line = self.line_at(self.find_key("schema"));
+        match value.as_u64() {
+            Some(schema) if (1..=RULES_SCHEMA as u64).contains(&schema) => schema,
+            Some(schema) => {
+                self.error(line, format!("schema {} is not supported, this version reads up to {}", schema, RULES_SCHEMA));
+                RULES_SCHEMA as u64
+            }
+            None => {
+                self.error(line, "'schema' must be a whole number".to_string());
+                1
+            }
+        }
+    }
+
+    fn check_languages(&mut self, languages: &Map<String, Value>) {
+        let mut extension_owners: HashMap<String, (String, i64)> = HashMap::new();
+
+        for (key, language) in languages.iter().filter(|(key, _)| *key != "schema") {
+            let start = self.find_key(key);
+            let line = self.line_at(start);
+
+            let Some(language) = language.as_object() else {
+                self.error(line, format!("language '{}' must be an object", key));
+                continue;
+            };
+
+            match language.get("name") {
+                Some(Value::String(name)) if !name.trim().is_empty() => {}
+                Some(Value::String(_)) => self.error(line, format!("language '{}' has an empty name", key)),
+                Some(_) => self.error(line, format!("language '{}': 'name' must be a string", key)),
+                None => self.error(line, format!("language '{}' is missing 'name'", key)),
+            }
+            let priority = self.priority(&format!("language '{}'", key), language, line);
+
+            for extension in self.strings(key, language, "extensions", start, true) {
+                let line = self.line_at(self.find(&serde_json::to_string(&extension).unwrap(), start));
+                if extension.is_empty() {
+                    self.error(line, format!("language '{}' has an empty extension", key));
+                } else if extension.starts_with('.') {
+                    self.error(line, format!(
+                        "extension '{}' in '{}' never matches, write it without the leading dot",
+                        extension, key
+                    ));
+                } else if let Some((owner, _)) = extension_owners.get(&extension).filter(|(_, other)| *other == priority) {
+                    // Detection falls back to key order, so one of them silently loses its files
+                    self.error(line, format!(
+                        "extension '{}' is used by both '{}' and '{}' with the same priority",
+                        extension, owner, key
+                    ));
+                } else {
+                    extension_owners.insert(extension, (key.clone(), priority));
+                }
+            }
+
+            self.strings(key, language, "filenames", start, false);
+            self.strings(key, language, "keywords", start, false);
+            self.strings(key, language, "declarations", start, false);
+
+            let single_line = self.entries(key, language, "single_line", start);
+            let multi_line = self.entries(key, language, "multi_line", start);
+            let mut markers = Vec::new();
+            for (index, entry) in single_line.iter().enumerate() {
+                let context = format!("'{}' single_line[{}]", key, index);
+                let line = self.entry_line(entry, "pattern", start);
+                let Some(pattern) = self.field(&context, entry, "pattern", line) else { continue };
+                self.description(&context, entry, line);
+                let not_followed_by = match entry.get("not_followed_by") {
+                    Some(Value::Array(values)) if values.iter().all(Value::is_string) => {
+                        values.iter().filter_map(|value| value.as_str().map(str::to_string)).collect()
+                    }
+                    Some(_) => {
+                        self.error(line, format!("{}: 'not_followed_by' must be an array of strings", context));
+                        Vec::new()
+                    }
+                    None => Vec::new(),
+                };
+                let word_boundary = match entry.get("word_boundary") {
+                    Some(Value::Bool(word_boundary)) => *word_boundary,
+                    Some(_) => {
+                        self.error(line, format!("{}: 'word_boundary' must be true or false", context));
+                        false
+                    }
+                    None => false,
+                };
+                let (flags, must_start_line) = self.schema_2(&context, entry, line);
+                let pattern_text = single_line_pattern(&pattern, &not_followed_by, word_boundary, must_start_line.unwrap_or(true));
+                self.compiles(&context, &with_flags(pattern_text, &flags), line);
+                if markers.contains(&pattern) {
+                    self.warning(line, format!("{} duplicates the marker '{}'", context, pattern));
+                }
+                markers.push(pattern);
+            }
+            for (index, entry) in multi_line.iter().enumerate() {
+                let context = format!("'{}' multi_line[{}]", key, index);
+                let line = self.entry_line(entry, "start", start);
+                let start_marker = self.field(&context, entry, "start", line);
+                let end_marker = self.field(&context, entry, "end", line);
+                self.description(&context, entry, line);
+                let (flags, must_start_line) = self.schema_2(&context, entry, line);
+                if let (Some(start_marker), Some(end_marker)) = (start_marker, end_marker) {
+                    let line_start = entry.get("line_start").and_then(Value::as_bool).unwrap_or(false);
+                    let pattern = multi_line_pattern(&start_marker, &end_marker, line_start, must_start_line.unwrap_or(false));
+                    self.compiles(&context, &with_flags(pattern, &flags), line);
+                }
+            }
+            if single_line.is_empty() && multi_line.is_empty() {
+                self.warning(line, format!("language '{}' defines no comment syntax", key));
+            }
+        }
+    }
+
+    /// Reads an array of strings, reporting anything else. Returns the valid strings.
+    fn strings(&mut self, key: &str, language: &Map<String, Value>, field: &str, start: usize, required: bool) -> Vec<String> {
+        let line = self.line_at(self.find(&format!("\"{}\"", field), start));
+        match language.get(field) {
+            Some(Value::Array(values)) => {
+                let strings: Vec<String> = values.iter().filter_map(|value| value.as_str().map(str::to_string)).collect();
+                if strings.len() != values.len() {
+                    self.error(line, format!("language '{}': '{}' must only contain strings", key, field));
+                }
+                strings
+            }
+            Some(_) => {
+                self.error(line, format!("language '{}': '{}' must be an array of strings", key, field));
+                Vec::new()
+            }
+            None if required => {
+                self.error(self.line_at(start), format!("language '{}' is missing '{}'", key, field));
+                Vec::new()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// Reads an array of rule objects, reporting anything else. Returns the objects.
+    fn entries(&mut self, key: &str, language: &Map<String, Value>, field: &str, start: usize) -> Vec<Map<String, Value>> {
+        let line = self.line_at(self.find(&format!("\"{}\"", field), start));
+        match language.get(field) {
+            Some(Value::Array(values)) => {
+                let entries: Vec<_> = values.iter().filter_map(|value| value.as_object().cloned()).collect();
+                if entries.len() != values.len() {
+                    self.error(line, format!("language '{}': '{}' must only contain objects", key, field));
+                }
+                entries
+            }
+            Some(_) => {
+                self.error(line, format!("language '{}': '{}' must be an array", key, field));
+                Vec::new()
+            }
+            None => {
+                self.error(self.line_at(start), format!("language '{}' is missing '{}'", key, field));
+                Vec::new()
+            }
+        }
+    }
+
+    fn entry_line(&self, entry: &Map<String, Value>, field: &str, start: usize) -> usize {
+        match entry.get(field) {
+            Some(value) => {
+                let needle = format!("\"{}\": {}", field, value);
+                self.line_at(self.find(&needle, start))
+            }
+            None => self.line_at(start),
+        }
+    }
+
+    fn field(&mut self, context: &str, entry: &Map<String, Value>, field: &str, line: usize) -> Option<String> {
+        match entry.get(field) {
+            Some(Value::String(value)) if !value.is_empty() => Some(value.clone()),
+            Some(Value::String(_)) => {
+                self.error(line, format!("{} has an empty '{}'", context, field));
+                None
+            }
+            Some(_) => {
+                self.error(line, format!("{}: '{}' must be a string", context, field));
+                None
+            }
+            None => {
+                self.error(line, format!("{} is missing '{}'", context, field));
+                None
+            }
+        }
+    }
+
+    fn description(&mut self, context: &str, entry: &Map<String, Value>, line: usize) {
+        match entry.get("description") {
+            Some(Value::String(_)) => {}
+            Some(_) => self.error(line, format!("{}: 'description' must be a string", context)),
+            None => self.error(line, format!("{} is missing 'description'", context)),
+        }
+        for flag in ["doc", "line_start", "nested"] {
+            if entry.get(flag).is_some_and(|value| !value.is_boolean()) {
+                self.error(line, format!("{}: '{}' must be true or false", context, flag));
+            }
+        }
+        self.priority(context, entry, line);
+    }
+
+    /// Checks the fields schema 2 added to rules. Returns the valid `flags` and `must_start_line`.
+    fn schema_2(&mut self, context: &str, entry: &Map<String, Value>, line: usize) -> (String, Option<bool>) {
+        if self.schema_2_field.is_none()
+            && let Some(field) = SCHEMA_2_FIELDS.iter().find(|field| entry.contains_key(**field))
+        {
+            self.schema_2_field = Some((line, field));
+        }
+        let flags = match entry.get("flags") {
+            Some(Value::String(flags)) if flags.chars().all(|flag| REGEX_FLAGS.contains(flag)) => flags.clone(),
+            Some(_) => {
+                self.error(line, format!("{}: 'flags' must be a string of regex flags from '{}'", context, REGEX_FLAGS));
+                String::new()
+            }
+            None => String::new(),
+        };
+        let must_start_line = match entry.get("must_start_line") {
+            Some(Value::Bool(must_start_line)) => Some(*must_start_line),
+            Some(_) => {
+                self.error(line, format!("{}: 'must_start_line' must be true or false", context));
+                None
+            }
+            None => None,
+        };
+        for (field, allowed) in [("inside_strings", &["never", "always"][..]), ("class", &["normal", "doc", "license", "directive"])] {
+            if let Some(value) = entry.get(field)
+                && !value.as_str().is_some_and(|value| allowed.contains(&value))
+            {
+                self.error(line, format!("{}: '{}' must be one of {}", context, field, allowed.join(", ")));
+            }
+        }
+        (flags, must_start_line)
+    }
+
+    /// Reads an optional `priority`, 0 when missing or invalid.
+    fn priority(&mut self, context: &str, entry: &Map<String, Value>, line: usize) -> i64 {
+        match entry.get("priority") {
+            Some(value) => match value.as_i64().filter(|priority| i32::try_from(*priority).is_ok()) {
+                Some(priority) => priority,
+                None => {
+                    self.error(line, format!("{}: 'priority' must be a whole number", context));
+                    0
+                }
+            },
+            None => 0,
+        }
+    }
+
+    fn compiles(&mut self, context: &str, pattern: &str, line: usize) {
+        if let Err(e) = Regex::new(pattern) {
+            self.error(line, format!("{} does not compile: {}", context, e));
+        }
+    }
+}
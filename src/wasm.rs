@@ -0,0 +1,26 @@
+//! The engine for JavaScript, for a web playground or Node tooling that should not shell out
+//! to the binary. Build with `cargo rustc --lib --release --target wasm32-unknown-unknown
+//! --features wasm --crate-type cdylib` and generate the bindings with `wasm-bindgen`.
+
+use std::sync::OnceLock;
+use wasm_bindgen::prelude::*;
+
+use crate::{Rules, Stripper};
+
+/// The bundled syntax rules, parsed on the first call and kept for the others
+fn rules() -> &'static Rules {
+    static RULES: OnceLock<Rules> = OnceLock::new();
+    RULES.get_or_init(Rules::bundled)
+}
+
+/// Removes comments from `source` in `language` (a language key or name such as `rust` or
+/// `C++`). `options` holds `remove` flags separated by whitespace, e.g. `--strip-docstrings
+/// --preserve-lines`, or is empty. Throws an `Error` with the message if removal fails.
+#[wasm_bindgen]
+pub fn remove_comments(source: &str, language: &str, options: &str) -> Result<String, JsError> {
+    let options: Vec<&str> = options.split_whitespace().collect();
+    Stripper::new(rules(), &options)
+        .and_then(|stripper| stripper.strip(source, language))
+        .map(|result| result.content)
+        .map_err(|e| JsError::new(&format!("{:#}", e)))
+}
@@ -0,0 +1,82 @@
+use anyhow::{Context, Result};
+use colored::*;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Component, Path, PathBuf},
+    sync::mpsc,
+    time::Duration,
+};
+
+use crate::{
+    exclude::ExcludeSet,
+    output::{self, FileFailure},
+    paths,
+    remove::Remover,
+};
+
+/// Editors often save in several steps, so events arriving this close together are handled once
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+pub fn run(dir: &Path, remover: &Remover, excludes: &ExcludeSet) -> Result<()> {
+    let (sender, receiver) = mpsc::channel::<notify::Result<Event>>();
+    let mut watcher = notify::recommended_watcher(sender).context("Failed to start the file watcher")?;
+    watcher
+        .watch(dir, RecursiveMode::Recursive)
+        .with_context(|| format!("Failed to watch directory: {}", dir.display()))?;
+    info!("Watching {} for changes (press Ctrl+C to stop)", dir.display().to_string().green());
+
+    // Event paths are absolute, excludes and output use paths relative to the watched directory.
+    // Event paths have no `\\?\` prefix on Windows, so the canonical root must not either
+    let root = paths::plain(&fs::canonicalize(dir).with_context(|| format!("Failed to resolve path: {}", dir.display()))?);
+
+    while let Ok(event) = receiver.recv() {
+        let mut changed = BTreeSet::new();
+        collect_paths(event, dir, &root, remover, excludes, &mut changed);
+        while let Ok(event) = receiver.recv_timeout(DEBOUNCE) {
+            collect_paths(event, dir, &root, remover, excludes, &mut changed);
+        }
+
+        for path in changed {
+            // A failing file should not stop the watcher
+            if let Err(e) = remover.process_file(&path) {
+                output::file_error(FileFailure::Errored, path.display(), &e);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn collect_paths(
+    event: notify::Result<Event>,
+    dir: &Path,
+    root: &Path,
+    remover: &Remover,
+    excludes: &ExcludeSet,
+    changed: &mut BTreeSet<PathBuf>,
+) {
+    let event = match event {
+        Ok(event) => event,
+        Err(e) => {
+            eprintln!("{} {}", "Watch error:".red(), e);
+            return;
+        }
+    };
+    if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+        return;
+    }
+
+    for path in event.paths {
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        // Hidden entries are skipped like in directory walks, which also covers our temporary files
+        let hidden = relative.components().any(|component| {
+            matches!(component, Component::Normal(name) if name.to_string_lossy().starts_with('.'))
+        });
+        if !hidden && path.is_file() && remover.supports(&path) && !excludes.is_excluded(relative) {
+            changed.insert(dir.join(relative));
+        }
+    }
+}